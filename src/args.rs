@@ -0,0 +1,677 @@
+use std::{
+    ffi::OsString,
+    num::NonZeroUsize,
+    path::{Path, PathBuf},
+    time::Duration,
+};
+
+use anyhow::{anyhow, bail};
+use chrono::{DateTime, NaiveDate, NaiveTime, Utc, Weekday};
+use clap::{
+    CommandFactory, Parser, Subcommand, ValueEnum, builder::ArgPredicate, parser::ValueSource,
+};
+use clap_complete::Shell;
+use serde::Serialize;
+
+use crate::config::closest_match;
+
+/// Options that decide how the config is read, so they can't be defaults in
+/// it.
+const CONFIG_OPTIONS: [&str; 4] = ["config", "config-dir", "allow-missing-env", "local-repos"];
+
+/// Which of a commit's dates it's dated by.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize, ValueEnum)]
+#[serde(rename_all = "lowercase")]
+pub enum DateField {
+    /// When it was authored, which is kept when it's rebased or cherry-picked.
+    Author,
+    /// When it was committed, which is when it was last rebased or
+    /// cherry-picked. Bitbucket only knows when commits were authored.
+    Committer,
+}
+
+impl DateField {
+    /// The name of the date, for explaining what it's used for.
+    pub fn name(self) -> &'static str {
+        match self {
+            DateField::Author => "author",
+            DateField::Committer => "committer",
+        }
+    }
+}
+
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Format {
+    /// A summary for reading in the terminal.
+    Human,
+    /// JSON, including metadata about the run.
+    Json,
+    /// TOML, including metadata about the run.
+    Toml,
+    /// A Markdown table.
+    Markdown,
+    /// CSV with a row for each experience.
+    Csv,
+    /// A standalone HTML page with a table.
+    Html,
+    /// An SVG badge showing the total of `--badge-metric`.
+    Badge,
+    /// JSON with the commits made in each hour of each day of the week in
+    /// `--timezone`, as 7 rows of 24 starting with Monday at midnight.
+    PunchcardJson,
+    /// A single sentence of the totals and top `--top-languages`, for a bio.
+    Oneline,
+    /// JSON shaped for a social preview card, with a title, a subtitle, three
+    /// big numbers and the top `--top-languages` as chips.
+    Card,
+}
+
+/// Parses a duration of whole seconds, minutes or hours, like `90s`, `30m` or
+/// `2h`, with seconds assumed without a unit.
+fn parse_duration(duration: &str) -> anyhow::Result<Duration> {
+    let (amount, seconds_per_unit) = match duration.char_indices().last() {
+        Some((i, 's')) => (&duration[..i], 1),
+        Some((i, 'm')) => (&duration[..i], 60),
+        Some((i, 'h')) => (&duration[..i], 60 * 60),
+        _ => (duration, 1),
+    };
+    let Ok(amount) = amount.parse::<u64>() else {
+        bail!("expected a duration like 90s, 30m or 2h");
+    };
+
+    let Some(seconds) = amount.checked_mul(seconds_per_unit) else {
+        bail!("{duration} is too long of a duration");
+    };
+
+    Ok(Duration::from_secs(seconds))
+}
+
+impl Format {
+    /// Infers the format of an output file from its extension.
+    pub fn from_path(path: &Path) -> anyhow::Result<Self> {
+        match path
+            .extension()
+            .and_then(|extension| extension.to_str())
+            .map(str::to_ascii_lowercase)
+            .as_deref()
+        {
+            Some("json") => Ok(Format::Json),
+            Some("toml") => Ok(Format::Toml),
+            Some("md") => Ok(Format::Markdown),
+            Some("csv") => Ok(Format::Csv),
+            Some("html" | "htm") => Ok(Format::Html),
+            Some("svg") => Ok(Format::Badge),
+            _ => bail!(
+                "can't tell the format of {} from its extension, which should be json, toml, md, csv, html or svg",
+                path.display()
+            ),
+        }
+    }
+}
+
+/// A stat that can be totaled across every experience.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum Metric {
+    Commits,
+    Lines,
+    Reviews,
+    Releases,
+    Assets,
+}
+
+impl Metric {
+    /// The name of the metric, for labelling it.
+    pub fn name(self) -> &'static str {
+        match self {
+            Metric::Commits => "commits",
+            Metric::Lines => "lines",
+            Metric::Reviews => "reviews",
+            Metric::Releases => "releases",
+            Metric::Assets => "assets",
+        }
+    }
+}
+
+/// Which repositories are counted by whether they're public.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Visibility {
+    Public,
+    /// Repositories hidden from the public, including GitLab's internal
+    /// projects.
+    Private,
+    All,
+}
+
+/// What the compiled stats are shown for.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum GroupBy {
+    /// Every experience, with the languages used in it.
+    Experience,
+    /// Every language, with the experiences it was used in.
+    Language,
+}
+
+/// How the lines of merge commits are counted.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum MergeLines {
+    /// Count the merge's whole diff against its first parent, so the changes
+    /// it brings in from the merged branch are counted twice, once in the
+    /// branch's own commits and again in the merge.
+    Full,
+    /// Don't count any lines or languages of merges, without fetching them.
+    Skip,
+}
+
+/// What the lines counted of each file are.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum LineMetric {
+    /// Lines added.
+    Additions,
+    /// Lines removed.
+    Deletions,
+    /// Lines added and removed.
+    Changed,
+    /// Lines added less lines removed, which is never below 0 for a single
+    /// file.
+    Net,
+}
+
+impl LineMetric {
+    /// The lines a file or commit that added and removed lines counts for.
+    pub fn lines(self, additions: u64, deletions: u64) -> u64 {
+        match self {
+            LineMetric::Additions => additions,
+            LineMetric::Deletions => deletions,
+            LineMetric::Changed => additions + deletions,
+            LineMetric::Net => additions.saturating_sub(deletions),
+        }
+    }
+}
+
+/// Which of GitHub's APIs commits are listed with.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum Api {
+    /// Fetch every commit individually to count its lines and languages.
+    Rest,
+    /// List commits along with their lines in far fewer requests, without
+    /// counting languages.
+    Graphql,
+}
+
+/// How an error that stops the run is printed.
+#[derive(Clone, Copy, PartialEq, Eq, ValueEnum)]
+pub enum ErrorFormat {
+    /// A styled message for reading in the terminal.
+    Human,
+    /// A JSON object on each line, with the kind of error and the repository
+    /// it happened in.
+    Json,
+}
+
+/// How commits are attributed to the author.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, ValueEnum, Serialize)]
+#[serde(rename_all = "lowercase")]
+pub enum AuthorMatch {
+    /// By the account the provider linked the commit to.
+    Login,
+    /// By the email the commit was authored with.
+    Email,
+    /// By the name the commit was authored with.
+    Name,
+    /// By any of the account, email, or name.
+    Any,
+}
+
+#[derive(Subcommand)]
+pub enum Command {
+    /// List every file extension changed in the configured repositories, with
+    /// how many files and lines were changed, to help choose `languages`.
+    Languages,
+    /// Print a completion script for a shell.
+    Completions { shell: Shell },
+}
+
+#[derive(Parser)]
+#[command(version, about)]
+pub struct Args {
+    #[command(subcommand)]
+    pub command: Option<Command>,
+    /// Only count contributions made on or after this date (YYYY-MM-DD).
+    #[arg(long)]
+    pub since: Option<NaiveDate>,
+    /// Only count contributions made on or before this date (YYYY-MM-DD).
+    #[arg(long)]
+    pub until: Option<NaiveDate>,
+    /// Also count pull request reviews submitted by the author.
+    ///
+    /// This is expensive: GitHub only lists reviews per pull request, so every
+    /// pull request of every repository has to be requested individually.
+    #[arg(long)]
+    pub include_reviews: bool,
+    /// Also count releases published by the author.
+    #[arg(long)]
+    pub include_releases: bool,
+    /// Also count branches created and deleted by the author, on GitHub.
+    ///
+    /// This is best-effort: GitHub doesn't record who made a branch, so it's
+    /// taken from the repository's events, which only go back 90 days and 300
+    /// events.
+    #[arg(long)]
+    pub include_branches: bool,
+    /// Only count commits that changed a file in this language, and only the
+    /// lines of its files. Can be an extension or, with `linguist`, a language
+    /// name like `Rust`.
+    #[arg(long, value_name = "NAME")]
+    pub language: Option<String>,
+    /// Leave out languages that fewer lines than this were written in for an
+    /// experience, such as a config format only edited once.
+    #[arg(long, value_name = "N")]
+    pub min_language_lines: Option<u64>,
+    /// List the languages GitHub detects in each repository with a counted
+    /// commit instead of those of the files each commit changed.
+    ///
+    /// GitHub's languages are of the whole repository, not just the author's
+    /// commits, and name languages like `Rust` whatever `languages` is. Lines
+    /// are still counted by extension.
+    #[arg(long, conflicts_with_all = ["language", "min_language_lines"])]
+    pub languages_from_linguist_api: bool,
+    /// After compiling, list the languages in the config that no changed file
+    /// had the extension of, which may be typos.
+    #[arg(long, conflicts_with = "commits_only")]
+    pub report_unused_languages: bool,
+    /// Only fetch and show this experience, can be given multiple times.
+    #[arg(long, value_name = "EXPERIENCE")]
+    pub only: Vec<String>,
+    /// Also show what percentage of every commit in the repositories the
+    /// author made, which lists every commit of each repository again.
+    #[arg(long)]
+    pub with_share: bool,
+    /// Also count binary files added or changed, which have no lines to count.
+    #[arg(long)]
+    pub count_assets: bool,
+    /// Config file to read, can be given multiple times to merge several.
+    ///
+    /// Defaults to Stats.toml when neither this nor --config-dir is given.
+    #[arg(long, value_name = "PATH")]
+    pub config: Vec<PathBuf>,
+    /// Directory to read every .toml config file from, in alphabetical order.
+    #[arg(long, value_name = "DIR")]
+    pub config_dir: Option<PathBuf>,
+    /// Only count commits, without fetching each one to count its languages
+    /// and lines, which is much faster.
+    #[arg(long)]
+    pub commits_only: bool,
+    /// Leave ${VAR} references to unset environment variables in config files
+    /// as they are, instead of erroring.
+    #[arg(long)]
+    pub allow_missing_env: bool,
+    /// Only count commits made Monday through Friday (in UTC).
+    #[arg(long, alias = "weekdays", conflicts_with = "weekends_only")]
+    pub weekdays_only: bool,
+    /// Only count commits made on Saturday or Sunday (in UTC).
+    #[arg(long)]
+    pub weekends_only: bool,
+    /// Show how many commits were made on each day of the week.
+    #[arg(long)]
+    pub weekday_breakdown: bool,
+    /// Show how many commits were made and lines written in each year.
+    #[arg(long)]
+    pub year_breakdown: bool,
+    /// Shorten large commit and line counts in the summary, like 12.3k.
+    #[arg(long)]
+    pub humanize: bool,
+    /// Template of the overall progress bar, overriding the config's.
+    #[arg(long, value_name = "TEMPLATE")]
+    pub progress_template: Option<String>,
+    /// Template of the other progress bars, overriding the config's.
+    #[arg(long, value_name = "TEMPLATE")]
+    pub progress_task_template: Option<String>,
+    /// Characters to draw progress bars with, overriding the config's.
+    #[arg(long, value_name = "CHARS")]
+    pub progress_chars: Option<String>,
+    /// Skip repositories that fail to be fetched instead of stopping, listing
+    /// why at the end.
+    #[arg(long)]
+    pub keep_going: bool,
+    /// Exit with an error if no commits were found in any experience.
+    #[arg(long)]
+    pub fail_on_empty: bool,
+    /// Run this shell command after a successful run, with the stats as JSON
+    /// on its stdin and the `--output` paths as its arguments. It failing is
+    /// only warned about.
+    #[arg(long, value_name = "CMD")]
+    pub on_complete: Option<String>,
+    /// Skip commits that change more than this many files, like merges or
+    /// vendored dependencies.
+    #[arg(long, value_name = "N")]
+    pub skip_commits_over: Option<usize>,
+    /// Don't count commits that added fewer lines than this, like typo fixes
+    /// and version bumps.
+    #[arg(long, value_name = "N", conflicts_with = "commits_only")]
+    pub min_commit_lines: Option<u64>,
+    /// Only count commits that are part of a merged pull request, on GitHub
+    /// and GitLab.
+    ///
+    /// This is expensive: the pull requests of each commit are requested
+    /// individually.
+    #[arg(long)]
+    pub merged_prs_only: bool,
+    /// Only fetch the files of this many commits per repository, spread evenly
+    /// across its history, and extrapolate the rest from them. The results are
+    /// estimates.
+    #[arg(long, value_name = "N", conflicts_with = "incremental")]
+    pub sample: Option<NonZeroUsize>,
+    /// How the lines of merge commits are counted.
+    #[arg(long, value_enum, default_value_t = MergeLines::Full)]
+    pub merge_lines: MergeLines,
+    /// What the lines counted of each commit are.
+    #[arg(long, value_enum, default_value_t = LineMetric::Additions)]
+    pub line_metric: LineMetric,
+    /// Don't count merge commits at all.
+    #[arg(long)]
+    pub skip_merges: bool,
+    /// Only count commits in the first-parent history of the default branch,
+    /// like `git log --first-parent`, so work merged from other branches is
+    /// only counted through the merge. This lists every commit of each
+    /// repository.
+    #[arg(long)]
+    pub first_parent: bool,
+    /// Which date of each commit `--since`, `--until` and every date in the
+    /// stats go by.
+    #[arg(long, value_enum, default_value_t = DateField::Author)]
+    pub date_field: DateField,
+    /// Only count repositories with this visibility, which is checked for
+    /// each repository first.
+    #[arg(long, value_enum, default_value_t = Visibility::All)]
+    pub visibility: Visibility,
+    /// Only count the lines and languages of files that are still in the
+    /// default branch, which lists every file of each repository once.
+    #[arg(long, conflicts_with = "commits_only")]
+    pub only_present_files: bool,
+    /// Don't keep track of every file that was changed to count how many
+    /// distinct files were, which takes a lot of memory on huge repositories.
+    #[arg(long)]
+    pub no_distinct_files: bool,
+    /// Show notes about commits that were skipped and why.
+    #[arg(
+        short,
+        long,
+        default_value_if("explain", ArgPredicate::IsPresent, "true")
+    )]
+    pub verbose: bool,
+    /// Show why every commit and file was or wasn't counted, which implies
+    /// `--verbose`.
+    #[arg(long)]
+    pub explain: bool,
+    /// How errors are printed to stderr.
+    #[arg(long, value_enum, default_value_t = ErrorFormat::Human)]
+    pub error_format: ErrorFormat,
+    /// How to print the compiled stats.
+    #[arg(long, value_enum, default_value_t = Format::Human)]
+    pub format: Format,
+    /// Whether to show the stats of each experience or of each language.
+    #[arg(long, value_enum, default_value_t = GroupBy::Experience, conflicts_with = "commits_only")]
+    pub group_by: GroupBy,
+    /// Indent JSON output for reading, instead of keeping it on one line.
+    #[arg(long)]
+    pub json_pretty: bool,
+    /// Which total the badge of `--format badge` shows.
+    #[arg(long, value_enum, default_value_t = Metric::Commits)]
+    pub badge_metric: Metric,
+    /// How many of the languages with the most lines `--format oneline` and
+    /// `--format card` name.
+    #[arg(long, value_name = "N", default_value_t = 3)]
+    pub top_languages: usize,
+    /// How many of the languages with the most lines each experience names in
+    /// the human-readable summary, before saying how many more there are.
+    #[arg(long, value_name = "N", default_value_t = NonZeroUsize::new(10).unwrap())]
+    pub max_languages: NonZeroUsize,
+    /// The timezone commits are bucketed into hours in for
+    /// `--format punchcard-json`, like `America/New_York`.
+    #[arg(long, value_name = "TZ", default_value_t = chrono_tz::UTC)]
+    pub timezone: chrono_tz::Tz,
+    /// Also write the stats to this file, in the format its extension
+    /// suggests. Can be given multiple times.
+    #[arg(short, long, value_name = "PATH")]
+    pub output: Vec<PathBuf>,
+    /// Don't print the stats, only write the `--output` files.
+    #[arg(short, long)]
+    pub quiet: bool,
+    /// How commits are attributed to the author.
+    ///
+    /// `login` filters commits with the provider's API, while the others list
+    /// every commit and compare the author to the commit's author themselves,
+    /// which is slower but also finds commits that were never linked to the
+    /// account.
+    #[arg(long, value_enum, default_value_t = AuthorMatch::Login)]
+    pub author_match: AuthorMatch,
+    /// Count the commits of everyone instead of only the author's, like
+    /// `author = "*"`, for the stats of a whole team.
+    ///
+    /// Every commit of every repository is fetched, which can take many times
+    /// more requests.
+    #[arg(long)]
+    pub all_authors: bool,
+    /// Only fetch commits that are new since the last run, reusing the stats
+    /// of the commits saved in the cache.
+    #[arg(long)]
+    pub incremental: bool,
+    /// Fetch every commit again and replace the cache, such as after history
+    /// was rewritten.
+    #[arg(long, requires = "incremental")]
+    pub refresh_cache: bool,
+    /// How many commits of a repository are counted between saving them to the
+    /// `--incremental` cache, so a run that crashes partway through a large
+    /// repository resumes where it left off.
+    #[arg(long, value_name = "N", default_value = "1000")]
+    pub flush_every: NonZeroUsize,
+    /// Where the cache used by `--incremental` is saved.
+    #[arg(long, value_name = "PATH", default_value = ".resume_stats_cache.json")]
+    pub cache: PathBuf,
+    /// How many hours the metadata of repositories, like whether they moved,
+    /// is reused from the `--incremental` cache before it's fetched again.
+    /// Without `--incremental`, the cache isn't read or written at all, so
+    /// the metadata is fetched on every run.
+    #[arg(long, value_name = "HOURS", default_value_t = 24)]
+    pub metadata_ttl: u64,
+    /// Write every commit counted in this run to this file as JSON Lines,
+    /// with its repository, experience, date, lines and languages.
+    #[arg(long, value_name = "PATH")]
+    pub dump_commits: Option<PathBuf>,
+    /// Save the stats of each experience to this file as soon as it's
+    /// compiled, so a run that fails can be picked up with `--resume`.
+    #[arg(long, value_name = "PATH")]
+    pub checkpoint: Option<PathBuf>,
+    /// Reuse the experiences saved to `--checkpoint` by a run that didn't
+    /// finish, unless their config or the options changed since.
+    #[arg(long, requires = "checkpoint")]
+    pub resume: bool,
+    /// Show experiences as "Experience A" and so on and repositories as "Repo
+    /// 1" and so on, for sharing the stats without naming private projects.
+    #[arg(long, conflicts_with = "dump_commits")]
+    pub anonymize: bool,
+    /// Write which experience and repository each label of `--anonymize`
+    /// stands for to this JSON file, to keep locally.
+    #[arg(long, value_name = "PATH", requires = "anonymize")]
+    pub anonymize_map: Option<PathBuf>,
+    /// Merge the stats of experiences in this JSON file into the compiled
+    /// ones, for experience that can't be fetched from any provider.
+    #[arg(long, value_name = "PATH")]
+    pub import: Option<PathBuf>,
+    /// Print the merged config and the options that affect what's counted,
+    /// as TOML, instead of compiling stats.
+    #[arg(long)]
+    pub print_config: bool,
+    /// Show how long listing commits, fetching their files and counting them
+    /// took for each experience once done.
+    #[arg(long)]
+    pub profile: bool,
+    /// Show the overall progress as a percentage in the terminal title.
+    #[arg(long)]
+    pub set_title: bool,
+    /// Read the GitHub PAT from this file instead of the keyring. The
+    /// `GITHUB_TOKEN` environment variable still takes precedence.
+    #[arg(long, value_name = "PATH")]
+    pub token_file: Option<PathBuf>,
+    /// Never read or save tokens with the system keyring, such as on servers
+    /// without one, prompting for them instead unless they're given otherwise.
+    #[arg(long)]
+    pub no_keyring: bool,
+    /// Use the token the GitHub CLI (`gh`) is logged in with, if the GitHub
+    /// PAT isn't given otherwise, instead of the keyring or prompting for one.
+    #[arg(long)]
+    pub use_gh_auth: bool,
+    /// How many experiences are compiled at once.
+    #[arg(long, value_name = "N", default_value_t = NonZeroUsize::new(4).unwrap())]
+    pub concurrency: NonZeroUsize,
+    /// How many commits of a repository are fetched at once.
+    #[arg(long, value_name = "N", default_value_t = NonZeroUsize::new(4).unwrap())]
+    pub commit_concurrency: NonZeroUsize,
+    /// How many commits are fetched at once across every experience, which
+    /// caps `--concurrency` times `--commit-concurrency` to stay under
+    /// secondary rate limits.
+    #[arg(long, value_name = "N", default_value_t = NonZeroUsize::new(8).unwrap())]
+    pub max_parallel_requests: NonZeroUsize,
+    /// Stop starting new repositories and commits after this long, like `90s`,
+    /// `30m` or `2h`, and show partial results. Commits already being fetched
+    /// are given a little longer to finish.
+    #[arg(long, value_name = "DURATION", value_parser = parse_duration)]
+    pub max_duration: Option<Duration>,
+    /// Give up connecting to an API after this many seconds.
+    #[arg(long, value_name = "SECONDS")]
+    pub connect_timeout: Option<u64>,
+    /// Give up waiting for an API to respond after this many seconds.
+    #[arg(long, value_name = "SECONDS")]
+    pub read_timeout: Option<u64>,
+    /// PEM file of extra root certificates to trust for every provider, such
+    /// as the CA of a corporate proxy.
+    #[arg(long, value_name = "PATH")]
+    pub ca_cert: Option<PathBuf>,
+    /// Which GitHub API commits are counted with.
+    #[arg(long, value_enum, default_value_t = Api::Rest)]
+    pub api: Api,
+    /// Also count every clone of a repository matching this glob, like
+    /// `~/src/*`, under the experience `local`. Clones are read without making
+    /// any requests, and named by their remote.
+    #[arg(long, value_name = "GLOB")]
+    pub local_repos: Option<String>,
+    /// Base URL of the GitHub API, for GitHub Enterprise or testing.
+    #[arg(long, value_name = "URL")]
+    pub base_url: Option<String>,
+}
+
+impl Args {
+    /// Parses `argv` with `defaults` for the options it doesn't give, which
+    /// are the `[defaults]` of the config. Flags are given as `true`, and
+    /// options that can be given multiple times as arrays.
+    pub fn parse_with_defaults<T: Into<OsString> + Clone>(
+        argv: impl IntoIterator<Item = T>,
+        defaults: &toml::Table,
+    ) -> anyhow::Result<Self> {
+        let mut argv: Vec<OsString> = argv.into_iter().map(Into::into).collect();
+        let command = Args::command();
+        let matches = command.clone().try_get_matches_from(&argv)?;
+        let mut default_args = Vec::new();
+
+        for (option, value) in defaults {
+            let long = option.replace('_', "-");
+            let Some(arg) = command
+                .get_arguments()
+                .find(|arg| arg.get_long() == Some(&long))
+            else {
+                let longs: Vec<String> = command
+                    .get_arguments()
+                    .filter_map(|arg| Some(arg.get_long()?.to_string()))
+                    .collect();
+
+                match closest_match(&long, &longs) {
+                    Some(suggestion) => bail!(
+                        "`{option}` in [defaults] isn't an option, did you mean {suggestion:?}?"
+                    ),
+                    None => bail!("`{option}` in [defaults] isn't an option"),
+                }
+            };
+
+            if CONFIG_OPTIONS.contains(&long.as_str()) {
+                bail!("`{option}` decides how the config is read, so it can't be in [defaults]");
+            }
+
+            // Options on the command line take precedence over their defaults.
+            if matches.value_source(arg.get_id().as_str()) == Some(ValueSource::CommandLine) {
+                continue;
+            }
+
+            let values = match value {
+                toml::Value::Array(values) => values.iter().collect(),
+                value => vec![value],
+            };
+
+            for value in values {
+                let value = match value {
+                    toml::Value::Boolean(true) => {
+                        default_args.push(format!("--{long}").into());
+
+                        continue;
+                    }
+                    toml::Value::Boolean(false) => continue,
+                    toml::Value::String(value) => value.clone(),
+                    toml::Value::Integer(value) => value.to_string(),
+                    toml::Value::Float(value) => value.to_string(),
+                    toml::Value::Datetime(value) => value.to_string(),
+                    toml::Value::Array(_) | toml::Value::Table(_) => bail!(
+                        "expected `{option}` in [defaults] to be a string, number, boolean or array of them"
+                    ),
+                };
+
+                default_args.push(format!("--{long}={value}").into());
+            }
+        }
+
+        // The defaults go right after the name of the program, before any
+        // subcommand.
+        let at = argv.len().min(1);
+
+        argv.splice(at..at, default_args);
+
+        Args::try_parse_from(argv).map_err(|e| {
+            anyhow!(
+                "failed to apply the [defaults] of the config: {}",
+                e.to_string()
+                    .trim_start_matches("error: ")
+                    .lines()
+                    .next()
+                    .unwrap_or_default()
+            )
+        })
+    }
+
+    pub fn since(&self) -> Option<DateTime<Utc>> {
+        self.since
+            .map(|since| since.and_time(NaiveTime::MIN).and_utc())
+    }
+
+    pub fn until(&self) -> Option<DateTime<Utc>> {
+        self.until.map(|until| {
+            until
+                .and_hms_opt(23, 59, 59)
+                .expect("23:59:59 should be a valid time")
+                .and_utc()
+        })
+    }
+
+    /// Whether commits made on `weekday` should be counted.
+    pub fn counts_weekday(&self, weekday: Weekday) -> bool {
+        let weekend = matches!(weekday, Weekday::Sat | Weekday::Sun);
+
+        !(self.weekdays_only && weekend || self.weekends_only && !weekend)
+    }
+
+    pub fn contains(&self, date: DateTime<Utc>) -> bool {
+        self.since().is_none_or(|since| date >= since)
+            && self.until().is_none_or(|until| date <= until)
+    }
+}
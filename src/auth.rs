@@ -0,0 +1,161 @@
+use std::path::Path;
+
+use anyhow::{Context, bail};
+use console::style;
+use octocrab::Octocrab;
+
+/// Gets the login of the user the GitHub PAT belongs to.
+pub async fn authenticated_login(octocrab: &Octocrab) -> anyhow::Result<String> {
+    Ok(octocrab.current().user().await?.login)
+}
+
+/// Where [`token`] looks for a token, which can be swapped out so the order it
+/// looks in can be tried without the environment, keyring or a terminal.
+pub trait TokenLookups {
+    fn env_var(&self, name: &str) -> Option<String>;
+    fn read_file(&self, path: &Path) -> std::io::Result<String>;
+    /// Gets the token the GitHub CLI is logged in to `host` with, if it's
+    /// installed and logged in.
+    fn gh_token(&self, host: &str) -> Option<String>;
+    /// Gets the token saved in the keyring under `service`, if there is one.
+    fn keyring_token(&self, service: &str) -> Result<Option<String>, keyring::Error>;
+    fn save_keyring_token(&self, service: &str, token: &str) -> Result<(), keyring::Error>;
+    fn prompt(&self, prompt: &str) -> anyhow::Result<String>;
+}
+
+/// Looks for tokens in the actual environment, keyring and terminal.
+pub struct System;
+
+impl TokenLookups for System {
+    fn env_var(&self, name: &str) -> Option<String> {
+        std::env::var(name).ok()
+    }
+
+    fn read_file(&self, path: &Path) -> std::io::Result<String> {
+        std::fs::read_to_string(path)
+    }
+
+    fn gh_token(&self, host: &str) -> Option<String> {
+        let output = std::process::Command::new("gh")
+            .args(["auth", "token", "--hostname", host])
+            .output();
+
+        match output {
+            Ok(output) if output.status.success() => String::from_utf8(output.stdout)
+                .ok()
+                .map(|token| token.trim().to_string())
+                .filter(|token| !token.is_empty()),
+            _ => {
+                eprintln!(
+                    "{} the GitHub CLI isn't logged in to {host}, so its token can't be used",
+                    style(format!("{:>12}", "Warning")).yellow().bold(),
+                );
+
+                None
+            }
+        }
+    }
+
+    fn keyring_token(&self, service: &str) -> Result<Option<String>, keyring::Error> {
+        match keyring::Entry::new(service, &whoami::username())?.get_password() {
+            Ok(token) => Ok(Some(token)),
+            Err(keyring::Error::NoEntry) => Ok(None),
+            Err(e) => Err(e),
+        }
+    }
+
+    fn save_keyring_token(&self, service: &str, token: &str) -> Result<(), keyring::Error> {
+        keyring::Entry::new(service, &whoami::username())?.set_password(token)
+    }
+
+    fn prompt(&self, prompt: &str) -> anyhow::Result<String> {
+        Ok(dialoguer::Password::new().with_prompt(prompt).interact()?)
+    }
+}
+
+/// Gets a token from the `env_var` environment variable, then `token_file`,
+/// then the GitHub CLI's login to `gh_host` if one is given, then the system
+/// keyring under `service`, prompting for it with `prompt` and saving it to
+/// the keyring if it isn't anywhere yet.
+///
+/// The keyring is skipped entirely unless `use_keyring` is true, and if it
+/// can't be used on this system the token is prompted for without being saved.
+pub fn token(
+    service: &str,
+    env_var: &str,
+    token_file: Option<&Path>,
+    gh_host: Option<&str>,
+    use_keyring: bool,
+    prompt: &str,
+) -> anyhow::Result<String> {
+    token_from(
+        &System,
+        service,
+        env_var,
+        token_file,
+        gh_host,
+        use_keyring,
+        prompt,
+    )
+}
+
+/// Like [`token`], but looks for it through `lookups`.
+pub fn token_from(
+    lookups: &impl TokenLookups,
+    service: &str,
+    env_var: &str,
+    token_file: Option<&Path>,
+    gh_host: Option<&str>,
+    use_keyring: bool,
+    prompt: &str,
+) -> anyhow::Result<String> {
+    if let Some(token) = lookups.env_var(env_var).filter(|token| !token.is_empty()) {
+        return Ok(token);
+    }
+
+    if let Some(token_file) = token_file {
+        let token = lookups
+            .read_file(token_file)
+            .with_context(|| format!("failed to read {}", token_file.display()))?;
+        let token = token.trim();
+
+        if token.is_empty() {
+            bail!("{} is empty", token_file.display());
+        }
+
+        return Ok(token.to_string());
+    }
+
+    if let Some(token) = gh_host.and_then(|gh_host| lookups.gh_token(gh_host)) {
+        return Ok(token);
+    }
+
+    if !use_keyring {
+        return lookups.prompt(prompt);
+    }
+
+    match lookups.keyring_token(service) {
+        Ok(Some(token)) => Ok(token),
+        Ok(None) => {
+            let token = lookups.prompt(prompt)?;
+
+            if let Err(e) = lookups.save_keyring_token(service, &token) {
+                warn_keyring_unavailable(e);
+            }
+
+            Ok(token)
+        }
+        Err(e) => {
+            warn_keyring_unavailable(e);
+
+            lookups.prompt(prompt)
+        }
+    }
+}
+
+fn warn_keyring_unavailable(e: keyring::Error) {
+    eprintln!(
+        "{} the keyring can't be used ({e}), so the token won't be saved",
+        style(format!("{:>12}", "Warning")).yellow().bold(),
+    );
+}
@@ -0,0 +1,170 @@
+//! Caches the stats of each repository between runs, so `--incremental` only
+//! has to fetch commits that are new since the last run, along with the
+//! metadata of repositories for `--metadata-ttl`.
+
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    io::ErrorKind,
+    path::Path,
+};
+
+use anyhow::Context;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{
+    args::{Args, Command},
+    linguist::Linguist,
+    stats::Stats,
+};
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Cache {
+    /// The cached repositories, keyed by provider and path.
+    repositories: HashMap<String, CachedRepository>,
+    /// The metadata of repositories, keyed by provider and configured path.
+    #[serde(default)]
+    metadata: HashMap<String, RepositoryMetadata>,
+}
+
+/// What's known about a repository apart from its commits, which is fetched
+/// as it's needed and reused until it's older than `--metadata-ttl`.
+#[derive(Serialize, Deserialize, Clone)]
+pub struct RepositoryMetadata {
+    pub fetched_at: DateTime<Utc>,
+    /// The path the repository is at now, which differs if it moved, once
+    /// it's been checked.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub current_path: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub private: Option<bool>,
+}
+
+impl Default for RepositoryMetadata {
+    fn default() -> Self {
+        Self {
+            fetched_at: Utc::now(),
+            current_path: None,
+            private: None,
+        }
+    }
+}
+
+#[derive(Serialize, Deserialize, Clone)]
+pub struct CachedRepository {
+    /// A hash of everything that affects which commits are counted and how,
+    /// so that changing any of it doesn't mix stats compiled differently.
+    pub settings_hash: String,
+    /// Every commit that was listed, counted or not.
+    pub shas: HashSet<String>,
+    /// The most recent commit that was listed, which should still be listed
+    /// unless history was rewritten.
+    pub latest_sha: Option<String>,
+    /// The stats of the commits, without reviews or releases, which are always
+    /// fetched in full.
+    pub stats: Stats,
+    /// Whether the run was still counting the commits of the repository when
+    /// it was saved, in which case every commit is listed again and only
+    /// those not in `shas` are counted.
+    #[serde(default, skip_serializing_if = "std::ops::Not::not")]
+    pub partial: bool,
+}
+
+impl Cache {
+    /// Reads the cache at `path`, or an empty cache if there isn't one yet.
+    pub async fn read(path: &Path) -> anyhow::Result<Self> {
+        match tokio::fs::read(path).await {
+            Ok(cache) => serde_json::from_slice(&cache)
+                .with_context(|| format!("failed to parse {}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+
+    /// Writes the cache to `path` through a temporary file, so a run that
+    /// crashes while writing it doesn't leave a corrupt cache behind.
+    pub async fn write(&self, path: &Path) -> anyhow::Result<()> {
+        let temporary = path.with_extension("tmp");
+
+        tokio::fs::write(&temporary, serde_json::to_vec(self)?)
+            .await
+            .with_context(|| format!("failed to write {}", temporary.display()))?;
+        tokio::fs::rename(&temporary, path)
+            .await
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Gets the cached repository under `key` if it was cached with the same
+    /// settings.
+    pub fn get(&self, key: &str, settings_hash: &str) -> Option<&CachedRepository> {
+        self.repositories
+            .get(key)
+            .filter(|repository| repository.settings_hash == settings_hash)
+    }
+
+    pub fn insert(&mut self, key: String, repository: CachedRepository) {
+        self.repositories.insert(key, repository);
+    }
+
+    /// Gets the metadata cached under `key` if it was fetched within `ttl`.
+    pub fn metadata(&self, key: &str, ttl: chrono::Duration) -> Option<&RepositoryMetadata> {
+        self.metadata
+            .get(key)
+            .filter(|metadata| Utc::now() - metadata.fetched_at < ttl)
+    }
+
+    pub fn insert_metadata(&mut self, key: String, metadata: RepositoryMetadata) {
+        self.metadata.insert(key, metadata);
+    }
+}
+
+/// Hashes everything that affects how commits are counted for `author` with
+/// `languages`, ignoring files with `exclude_extensions`, only counting the
+/// lines of `line_count_languages` and naming languages with
+/// `linguist_extensions` after applying `extension_aliases`.
+pub fn settings_hash(
+    args: &Args,
+    author: &str,
+    languages: &BTreeSet<String>,
+    exclude_extensions: &BTreeSet<String>,
+    line_count_languages: &BTreeSet<String>,
+    linguist_extensions: &Linguist,
+    extension_aliases: &BTreeMap<String, String>,
+) -> anyhow::Result<String> {
+    let settings = serde_json::json!({
+        "author": author,
+        "languages": languages,
+        "exclude_extensions": exclude_extensions,
+        "line_count_languages": line_count_languages,
+        "linguist_extensions": linguist_extensions,
+        "extension_aliases": extension_aliases,
+        "since": args.since,
+        "until": args.until,
+        "author_match": format!("{:?}", args.author_match),
+        "commits_only": args.commits_only,
+        "count_assets": args.count_assets,
+        "weekdays_only": args.weekdays_only,
+        "weekends_only": args.weekends_only,
+        "skip_commits_over": args.skip_commits_over,
+        "min_commit_lines": args.min_commit_lines,
+        "language": args.language,
+        "merged_prs_only": args.merged_prs_only,
+        "with_share": args.with_share,
+        "merge_lines": format!("{:?}", args.merge_lines),
+        "line_metric": format!("{:?}", args.line_metric),
+        "skip_merges": args.skip_merges,
+        "first_parent": args.first_parent,
+        "date_field": format!("{:?}", args.date_field),
+        "timezone": args.timezone.name(),
+        "no_distinct_files": args.no_distinct_files,
+        "only_present_files": args.only_present_files,
+        "api": format!("{:?}", args.api),
+        "discovers_languages": matches!(args.command, Some(Command::Languages)),
+    });
+
+    Ok(format!(
+        "{:x}",
+        Sha256::digest(serde_json::to_vec(&settings)?)
+    ))
+}
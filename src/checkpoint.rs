@@ -0,0 +1,83 @@
+//! Saves the stats of each experience as soon as it's compiled, so a run that
+//! fails or is interrupted can be picked up again with `--resume`.
+
+use std::{collections::HashMap, io::ErrorKind, path::Path};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+use sha2::{Digest, Sha256};
+
+use crate::{args::Args, config::Experience, stats::Stats};
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Checkpoint {
+    /// The compiled experiences, keyed by experience.
+    experiences: HashMap<String, CheckpointedExperience>,
+}
+
+#[derive(Serialize, Deserialize)]
+struct CheckpointedExperience {
+    /// A hash of the experience's config and the settings it was compiled
+    /// with, so it's compiled again if either changed.
+    hash: String,
+    /// The stats of the experience, if anything was counted for it.
+    stats: Option<Stats>,
+}
+
+impl Checkpoint {
+    /// Reads the checkpoint at `path`, or an empty checkpoint if there isn't
+    /// one yet.
+    pub async fn read(path: &Path) -> anyhow::Result<Self> {
+        match tokio::fs::read(path).await {
+            Ok(checkpoint) => serde_json::from_slice(&checkpoint)
+                .with_context(|| format!("failed to parse {}", path.display())),
+            Err(e) if e.kind() == ErrorKind::NotFound => Ok(Self::default()),
+            Err(e) => Err(e).with_context(|| format!("failed to read {}", path.display())),
+        }
+    }
+
+    pub async fn write(&self, path: &Path) -> anyhow::Result<()> {
+        tokio::fs::write(path, serde_json::to_vec(self)?)
+            .await
+            .with_context(|| format!("failed to write {}", path.display()))
+    }
+
+    /// Gets the stats of `experience` if it was compiled with the same hash,
+    /// which are `None` if nothing was counted for it.
+    pub fn get(&self, experience: &str, hash: &str) -> Option<Option<&Stats>> {
+        self.experiences
+            .get(experience)
+            .filter(|checkpointed| checkpointed.hash == hash)
+            .map(|checkpointed| checkpointed.stats.as_ref())
+    }
+
+    pub fn insert(&mut self, experience: String, hash: String, stats: Option<Stats>) {
+        self.experiences
+            .insert(experience, CheckpointedExperience { hash, stats });
+    }
+}
+
+/// Hashes the config of `experience` along with the `settings_hash` of the
+/// cache and the options that add to it, since checkpoints include reviews,
+/// releases and branches.
+pub fn experience_hash(
+    args: &Args,
+    settings_hash: &str,
+    experience: &Experience,
+) -> anyhow::Result<String> {
+    let experience = serde_json::json!({
+        "settings_hash": settings_hash,
+        "include_reviews": args.include_reviews,
+        "include_releases": args.include_releases,
+        "include_branches": args.include_branches,
+        "languages_from_linguist_api": args.languages_from_linguist_api,
+        "sample": args.sample,
+        "visibility": format!("{:?}", args.visibility),
+        "experience": experience,
+    });
+
+    Ok(format!(
+        "{:x}",
+        Sha256::digest(serde_json::to_vec(&experience)?)
+    ))
+}
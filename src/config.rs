@@ -0,0 +1,724 @@
+use std::{
+    collections::{BTreeMap, BTreeSet},
+    fmt::{self, Display, Formatter},
+    path::PathBuf,
+};
+
+use anyhow::{Context, anyhow, bail};
+use console::style;
+use serde::{
+    Deserialize, Deserializer, Serialize, Serializer,
+    de::{self, MapAccess, SeqAccess, Visitor},
+};
+
+use crate::{args::Args, linguist::Linguist};
+
+#[derive(Deserialize)]
+#[serde(try_from = "String")]
+pub struct RepositoryPath {
+    pub owner: String,
+    pub repository: String,
+}
+
+impl RepositoryPath {
+    /// Parses the path of a repository hosted by `provider`. Only GitLab has
+    /// groups within groups, so only its paths, and those of clones, which can
+    /// be of a GitLab project, can have more than one slash. Their owner is
+    /// then every group the project is in, like `org/team`.
+    pub fn parse(value: &str, provider: Provider) -> anyhow::Result<Self> {
+        let nested = matches!(provider, Provider::GitLab | Provider::Local);
+        let (owner, repository) = match value.rsplit_once('/') {
+            Some((owner, _)) if owner.contains('/') && !nested => bail!(
+                "expected repository path to be in the format owner/repository, but {value:?} has \
+                more than one slash (only GitLab repositories can be in subgroups)"
+            ),
+            Some(split) => split,
+            None => bail!(
+                "expected repository path to be in the format owner/repository, but {value:?} has \
+                no slash"
+            ),
+        };
+
+        if owner.split('/').chain([repository]).any(str::is_empty) {
+            bail!(
+                "expected repository path to be in the format owner/repository, but {value:?} has \
+                an empty segment"
+            );
+        }
+
+        Ok(Self {
+            owner: owner.to_string(),
+            repository: repository.to_string(),
+        })
+    }
+}
+
+impl TryFrom<String> for RepositoryPath {
+    type Error = anyhow::Error;
+
+    fn try_from(value: String) -> Result<Self, Self::Error> {
+        Self::parse(&value, Provider::default())
+    }
+}
+
+impl Display for RepositoryPath {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        write!(f, "{}/{}", self.owner, self.repository)
+    }
+}
+
+impl Serialize for RepositoryPath {
+    fn serialize<S: Serializer>(&self, serializer: S) -> Result<S::Ok, S::Error> {
+        serializer.collect_str(self)
+    }
+}
+
+/// Where a repository is hosted.
+#[derive(Clone, Copy, PartialEq, Eq, Hash, Debug, Serialize, Deserialize, Default)]
+#[serde(rename_all = "lowercase")]
+pub enum Provider {
+    #[default]
+    GitHub,
+    Bitbucket,
+    GitLab,
+    /// A clone found with `--local-repos`.
+    Local,
+}
+
+impl Display for Provider {
+    fn fmt(&self, f: &mut Formatter<'_>) -> fmt::Result {
+        f.write_str(match self {
+            Provider::GitHub => "GitHub",
+            Provider::Bitbucket => "Bitbucket",
+            Provider::GitLab => "GitLab",
+            Provider::Local => "local",
+        })
+    }
+}
+
+/// A repository to look through for commits, which can be given as just its
+/// path or as a table with more options.
+#[derive(Serialize)]
+pub struct Repository {
+    pub path: RepositoryPath,
+    pub provider: Provider,
+    /// The repository this is a fork of, which contributions are attributed
+    /// to while commits are still fetched from the fork.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub upstream: Option<RepositoryPath>,
+    /// Who to count the commits of in this repository instead of `author`,
+    /// such as an account that was only used in one organization.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// The directory to only count the files in, like `services/payments/`,
+    /// for a subproject of a monorepo. Commits that don't change any file in
+    /// it aren't counted at all.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub path_prefix: Option<String>,
+    /// The login of another GitHub account to fetch the repository with,
+    /// using the token saved for it, and to count the commits of unless
+    /// `author` says otherwise.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+}
+
+#[derive(Deserialize)]
+#[serde(deny_unknown_fields)]
+struct RepositoryTable {
+    path: String,
+    #[serde(default)]
+    provider: Provider,
+    upstream: Option<String>,
+    author: Option<String>,
+    path_prefix: Option<String>,
+    account: Option<String>,
+}
+
+impl<'de> Deserialize<'de> for Repository {
+    fn deserialize<D: Deserializer<'de>>(deserializer: D) -> Result<Self, D::Error> {
+        struct RepositoryVisitor;
+
+        impl<'de> Visitor<'de> for RepositoryVisitor {
+            type Value = Repository;
+
+            fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+                formatter.write_str("a repository path or table")
+            }
+
+            fn visit_str<E: de::Error>(self, value: &str) -> Result<Self::Value, E> {
+                Ok(Repository {
+                    path: RepositoryPath::try_from(value.to_string()).map_err(E::custom)?,
+                    provider: Provider::default(),
+                    upstream: None,
+                    author: None,
+                    path_prefix: None,
+                    account: None,
+                })
+            }
+
+            fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+                let RepositoryTable {
+                    path,
+                    provider,
+                    upstream,
+                    author,
+                    path_prefix,
+                    account,
+                } = RepositoryTable::deserialize(de::value::MapAccessDeserializer::new(map))?;
+                let parse =
+                    |path: &str| RepositoryPath::parse(path, provider).map_err(de::Error::custom);
+
+                Ok(Repository {
+                    path: parse(&path)?,
+                    provider,
+                    upstream: upstream.as_deref().map(parse).transpose()?,
+                    author,
+                    // A prefix without a trailing slash would also match
+                    // directories that only start with the same name.
+                    path_prefix: path_prefix
+                        .map(|prefix| prefix.trim_matches('/').to_string() + "/")
+                        .filter(|prefix| prefix != "/"),
+                    account,
+                })
+            }
+        }
+
+        deserializer.deserialize_any(RepositoryVisitor)
+    }
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct Experience {
+    /// The name to show for the experience, instead of its key.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub name: Option<String>,
+    #[serde(flatten)]
+    pub details: ExperienceDetails,
+    #[serde(flatten)]
+    pub targets: Targets,
+    pub repositories: Vec<Repository>,
+    /// The GitHub account of every repository that doesn't have its own
+    /// `account`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub account: Option<String>,
+    /// Where the experience was in an `[[experience]]` array, which it's
+    /// ordered by.
+    #[serde(skip)]
+    position: Option<usize>,
+}
+
+/// How an experience is described on a resume, which is shown along with its
+/// stats but doesn't change what's counted.
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct ExperienceDetails {
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub role: Option<String>,
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub company: Option<String>,
+    /// When the experience started, as it should be written, like `2021-03`
+    /// or `March 2021`.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub start: Option<String>,
+    /// When the experience ended, or nothing if it's ongoing.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub end: Option<String>,
+}
+
+/// Goals for an experience, which the summary shows progress toward.
+#[derive(Serialize, Deserialize, Default, Clone, Copy)]
+pub struct Targets {
+    #[serde(
+        default,
+        rename = "target_commits",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub commits: Option<u64>,
+    #[serde(
+        default,
+        rename = "target_lines",
+        skip_serializing_if = "Option::is_none"
+    )]
+    pub lines: Option<u64>,
+}
+
+/// Deserializes experiences from either a table keyed by experience or an
+/// `[[experience]]` array, where each is keyed by its name.
+fn deserialize_experiences<'de, D: Deserializer<'de>>(
+    deserializer: D,
+) -> Result<BTreeMap<String, Experience>, D::Error> {
+    struct ExperiencesVisitor;
+
+    impl<'de> Visitor<'de> for ExperiencesVisitor {
+        type Value = BTreeMap<String, Experience>;
+
+        fn expecting(&self, formatter: &mut Formatter) -> fmt::Result {
+            formatter.write_str("a table or array of experiences")
+        }
+
+        fn visit_map<A: MapAccess<'de>>(self, map: A) -> Result<Self::Value, A::Error> {
+            BTreeMap::deserialize(de::value::MapAccessDeserializer::new(map))
+        }
+
+        fn visit_seq<A: SeqAccess<'de>>(self, mut seq: A) -> Result<Self::Value, A::Error> {
+            let mut experiences = BTreeMap::new();
+
+            while let Some(mut experience) = seq.next_element::<Experience>()? {
+                let name = experience
+                    .name
+                    .clone()
+                    .ok_or_else(|| de::Error::custom("experiences in an array need a `name`"))?;
+
+                experience.position = Some(experiences.len());
+
+                if experiences.insert(name.clone(), experience).is_some() {
+                    return Err(de::Error::custom(format!(
+                        "experience {name:?} is in the array more than once"
+                    )));
+                }
+            }
+
+            Ok(experiences)
+        }
+    }
+
+    deserializer.deserialize_any(ExperiencesVisitor)
+}
+
+/// Merges an optional field of two experiences, which have to match if both
+/// have it.
+fn merge_field<T: PartialEq + fmt::Debug>(
+    field: &str,
+    experience: &str,
+    value: &mut Option<T>,
+    other_value: Option<T>,
+) -> anyhow::Result<()> {
+    match (&value, other_value) {
+        (Some(value), Some(other_value)) if *value != other_value => {
+            bail!(
+                "conflicting {field}s {value:?} and {other_value:?} for experience {experience:?}"
+            );
+        }
+        (None, other_value) => *value = other_value,
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// The `author` that counts the contributions of everyone instead of a single
+/// author.
+pub const ALL_AUTHORS: &str = "*";
+
+/// Reads `user.email` and `user.name` from the global git config, leaving out
+/// whichever isn't set.
+pub fn git_identities() -> BTreeSet<String> {
+    let Ok(config) = git2::Config::open_default() else {
+        return BTreeSet::new();
+    };
+
+    ["user.email", "user.name"]
+        .into_iter()
+        .filter_map(|key| config.get_string(key).ok())
+        .filter(|identity| !identity.is_empty())
+        .collect()
+}
+
+#[derive(Serialize, Deserialize, Default)]
+pub struct NeededStats {
+    /// Defaults to the user the GitHub PAT belongs to, or everyone if it's
+    /// [`ALL_AUTHORS`].
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub author: Option<String>,
+    /// The email and name in the git config, which commits are also attributed
+    /// to the author by if `author` isn't set, with `--author-match` other than
+    /// `login` or in clones.
+    #[serde(skip)]
+    pub git_identities: BTreeSet<String>,
+    #[serde(default)]
+    pub languages: BTreeSet<String>,
+    /// The only languages whose lines are counted, like leaving out `md` and
+    /// `json` while still showing them, or every language if it's empty.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub line_count_languages: BTreeSet<String>,
+    /// A linguist `languages.yml` to name languages by, in which case
+    /// `languages` can have names like `Rust` as well as extensions.
+    #[serde(default, skip_serializing_if = "Option::is_none")]
+    pub linguist: Option<PathBuf>,
+    /// The languages of extensions in `linguist`, which is read once every
+    /// config file has been merged.
+    #[serde(skip)]
+    pub linguist_extensions: Linguist,
+    /// Extensions counted as another extension, like `jsx` as `js`, before
+    /// they're named with `linguist`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extension_aliases: BTreeMap<String, String>,
+    /// Extensions of files to ignore entirely, like `lock` or `min.js`.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub exclude_extensions: BTreeSet<String>,
+    /// Keys of experiences in the order they're shown, before any others in
+    /// alphabetical order.
+    #[serde(default, skip_serializing_if = "Vec::is_empty")]
+    pub order: Vec<String>,
+    #[serde(default, deserialize_with = "deserialize_experiences")]
+    pub experience: BTreeMap<String, Experience>,
+    #[serde(default)]
+    pub progress: ProgressConfig,
+    /// Values for command-line options that aren't given on the command line,
+    /// by their long name like `format` or `commit-concurrency`.
+    #[serde(default, skip_serializing_if = "toml::Table::is_empty")]
+    pub defaults: toml::Table,
+}
+
+/// Overrides for how progress bars look, see [`indicatif::ProgressStyle`].
+#[derive(Serialize, Deserialize, Default)]
+pub struct ProgressConfig {
+    /// Template of the bar showing how many experiences have been compiled.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub template: Option<String>,
+    /// Template of the bars showing progress within an experience, which have
+    /// a message describing what's being fetched.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub task_template: Option<String>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub chars: Option<String>,
+}
+
+impl NeededStats {
+    /// Adds the clones found with `--local-repos` to the experience `local`.
+    pub fn add_local_repositories(&mut self, paths: impl IntoIterator<Item = RepositoryPath>) {
+        let experience = self.experience.entry("local".to_string()).or_default();
+
+        experience
+            .repositories
+            .extend(paths.into_iter().map(|path| Repository {
+                path,
+                provider: Provider::Local,
+                upstream: None,
+                author: None,
+                path_prefix: None,
+                account: None,
+            }));
+    }
+
+    /// Whether any experience has a repository hosted by `provider`.
+    pub fn uses_provider(&self, provider: Provider) -> bool {
+        self.experience
+            .values()
+            .flat_map(|experience| &experience.repositories)
+            .any(|repository| repository.provider == provider)
+    }
+
+    /// Whether there are repositories and every one of them is on `provider`.
+    pub fn only_uses_provider(&self, provider: Provider) -> bool {
+        let mut repositories = self
+            .experience
+            .values()
+            .flat_map(|experience| &experience.repositories)
+            .peekable();
+
+        repositories.peek().is_some()
+            && repositories.all(|repository| repository.provider == provider)
+    }
+
+    /// The logins of every GitHub account other than the default one that
+    /// repositories are fetched with.
+    pub fn github_accounts(&self) -> BTreeSet<&str> {
+        self.experience
+            .values()
+            .flat_map(|experience| {
+                experience.repositories.iter().filter_map(|repository| {
+                    repository
+                        .account
+                        .as_ref()
+                        .or(experience.account.as_ref())
+                        .filter(|_| repository.provider == Provider::GitHub)
+                })
+            })
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// Merges another config file into this one.
+    ///
+    /// Languages are unioned, orders are appended to each other, and the
+    /// repositories of an experience that's in both are appended to this one's.
+    /// Authors, and the names and details of experiences, have to match if
+    /// both have one.
+    fn merge(&mut self, other: NeededStats) -> anyhow::Result<()> {
+        match (&self.author, other.author) {
+            (Some(author), Some(other_author)) if *author != other_author => {
+                bail!("conflicting authors {author:?} and {other_author:?}");
+            }
+            (None, other_author) => self.author = other_author,
+            _ => (),
+        }
+
+        self.languages.extend(other.languages);
+        self.exclude_extensions.extend(other.exclude_extensions);
+        self.line_count_languages.extend(other.line_count_languages);
+        self.linguist = self.linguist.take().or(other.linguist);
+
+        for (extension, other_alias) in other.extension_aliases {
+            match self.extension_aliases.get(&extension) {
+                Some(alias) if *alias != other_alias => {
+                    bail!(
+                        "conflicting aliases {alias:?} and {other_alias:?} for extension {extension:?}"
+                    );
+                }
+                _ => {
+                    self.extension_aliases.insert(extension, other_alias);
+                }
+            }
+        }
+
+        for experience in other.order {
+            if !self.order.contains(&experience) {
+                self.order.push(experience);
+            }
+        }
+
+        self.progress.template = self.progress.template.take().or(other.progress.template);
+        self.progress.task_template = self
+            .progress
+            .task_template
+            .take()
+            .or(other.progress.task_template);
+        self.progress.chars = self.progress.chars.take().or(other.progress.chars);
+
+        for (option, value) in other.defaults {
+            self.defaults.entry(option).or_insert(value);
+        }
+
+        for (key, other_experience) in other.experience {
+            let experience = self.experience.entry(key.clone()).or_default();
+
+            let details = &mut experience.details;
+            let other_details = other_experience.details;
+
+            merge_field("name", &key, &mut experience.name, other_experience.name)?;
+            merge_field("role", &key, &mut details.role, other_details.role)?;
+            merge_field("company", &key, &mut details.company, other_details.company)?;
+            merge_field("start", &key, &mut details.start, other_details.start)?;
+            merge_field("end", &key, &mut details.end, other_details.end)?;
+            merge_field(
+                "account",
+                &key,
+                &mut experience.account,
+                other_experience.account,
+            )?;
+
+            let targets = &mut experience.targets;
+            let other_targets = other_experience.targets;
+
+            merge_field(
+                "target_commits",
+                &key,
+                &mut targets.commits,
+                other_targets.commits,
+            )?;
+            merge_field(
+                "target_lines",
+                &key,
+                &mut targets.lines,
+                other_targets.lines,
+            )?;
+            experience
+                .repositories
+                .extend(other_experience.repositories);
+        }
+
+        Ok(())
+    }
+}
+
+/// Reads and merges every config file given through the arguments.
+pub async fn read_needed_stats(args: &Args) -> anyhow::Result<NeededStats> {
+    let mut paths = args.config.clone();
+
+    if let Some(config_dir) = &args.config_dir {
+        let mut read_dir = tokio::fs::read_dir(config_dir)
+            .await
+            .with_context(|| format!("failed to read {}", config_dir.display()))?;
+        let mut dir_paths = Vec::new();
+
+        while let Some(entry) = read_dir.next_entry().await? {
+            let path = entry.path();
+
+            if path
+                .extension()
+                .is_some_and(|extension| extension == "toml")
+            {
+                dir_paths.push(path);
+            }
+        }
+
+        dir_paths.sort();
+        paths.extend(dir_paths);
+    }
+
+    let mut needed_stats: Option<NeededStats> = None;
+
+    if paths.is_empty() {
+        // The default config is optional when clones are counted instead.
+        if args.local_repos.is_some() && !tokio::fs::try_exists("Stats.toml").await? {
+            needed_stats = Some(NeededStats::default());
+        } else {
+            paths.push(PathBuf::from("Stats.toml"));
+        }
+    }
+
+    for path in paths {
+        let stats_toml = tokio::fs::read_to_string(&path)
+            .await
+            .with_context(|| format!("failed to read {}", path.display()))?;
+        let mut file_needed_stats: NeededStats = toml::from_str(&stats_toml)
+            .map_err(anyhow::Error::from)
+            .and_then(|mut value| {
+                expand_env_vars(&mut value, args.allow_missing_env)?;
+
+                Ok(toml::Value::try_into(value)?)
+            })
+            .with_context(|| format!("failed to parse {}", path.display()))?;
+        let mut listed: Vec<(usize, &String)> = file_needed_stats
+            .experience
+            .iter()
+            .filter_map(|(key, experience)| Some((experience.position?, key)))
+            .collect();
+
+        // Experiences in an array are shown in the order they're listed,
+        // after any that are ordered explicitly.
+        listed.sort_unstable();
+
+        let listed: Vec<String> = listed
+            .into_iter()
+            .map(|(_, key)| key.clone())
+            .filter(|key| !file_needed_stats.order.contains(key))
+            .collect();
+
+        file_needed_stats.order.extend(listed);
+
+        match &mut needed_stats {
+            Some(needed_stats) => needed_stats
+                .merge(file_needed_stats)
+                .with_context(|| format!("failed to merge {}", path.display()))?,
+            None => needed_stats = Some(file_needed_stats),
+        }
+    }
+
+    let mut needed_stats = needed_stats.ok_or(anyhow!("no config files found"))?;
+
+    if let Some(linguist) = &needed_stats.linguist {
+        let linguist_extensions = Linguist::read(linguist).await?;
+
+        let resolve = |languages: BTreeSet<String>| {
+            languages
+                .into_iter()
+                .map(|language| {
+                    linguist_extensions
+                        .language(&language)
+                        .map_or(language, str::to_string)
+                })
+                .collect()
+        };
+
+        needed_stats.languages = resolve(needed_stats.languages);
+        needed_stats.line_count_languages = resolve(needed_stats.line_count_languages);
+
+        for (field, languages) in [
+            ("languages", &needed_stats.languages),
+            ("line_count_languages", &needed_stats.line_count_languages),
+        ] {
+            for language in languages {
+                if linguist_extensions.knows(language) {
+                    continue;
+                }
+
+                let suggestion = match closest_match(language, linguist_extensions.names()) {
+                    Some(suggestion) => format!(", did you mean {suggestion:?}?"),
+                    None => String::new(),
+                };
+
+                eprintln!(
+                    "{} {language:?} in `{field}` isn't a language or extension linguist knows{suggestion}",
+                    style(format!("{:>12}", "Warning")).yellow().bold(),
+                );
+            }
+        }
+        needed_stats.linguist_extensions = linguist_extensions;
+    }
+
+    for experience in &needed_stats.order {
+        if !needed_stats.experience.contains_key(experience) {
+            match closest_match(experience, needed_stats.experience.keys()) {
+                Some(suggestion) => bail!(
+                    "experience {experience:?} in `order` is not in the config, did you mean {suggestion:?}?"
+                ),
+                None => bail!("experience {experience:?} in `order` is not in the config"),
+            }
+        }
+    }
+
+    Ok(needed_stats)
+}
+
+/// Expands `${VAR}` references to environment variables in every string of a
+/// config file. Unset variables are an error unless `allow_missing` is true, in
+/// which case they're left as they are.
+fn expand_env_vars(value: &mut toml::Value, allow_missing: bool) -> anyhow::Result<()> {
+    match value {
+        toml::Value::String(string) => {
+            let mut expanded = String::with_capacity(string.len());
+            let mut rest = string.as_str();
+
+            while let Some((before, after)) = rest.split_once("${") {
+                let Some((name, after)) = after.split_once('}') else {
+                    break;
+                };
+
+                expanded.push_str(before);
+
+                match std::env::var(name) {
+                    Ok(var) => expanded.push_str(&var),
+                    Err(_) if allow_missing => {
+                        expanded.push_str("${");
+                        expanded.push_str(name);
+                        expanded.push('}');
+                    }
+                    Err(e) => bail!("failed to expand ${{{name}}}: {e}"),
+                }
+
+                rest = after;
+            }
+
+            expanded.push_str(rest);
+            *string = expanded;
+        }
+        toml::Value::Array(array) => {
+            for value in array {
+                expand_env_vars(value, allow_missing)?;
+            }
+        }
+        toml::Value::Table(table) => {
+            for (_, value) in table.iter_mut() {
+                expand_env_vars(value, allow_missing)?;
+            }
+        }
+        _ => (),
+    }
+
+    Ok(())
+}
+
+/// Finds the candidate closest to `name`, if any is close enough that `name`
+/// was probably a typo of it.
+pub fn closest_match<'a>(
+    name: &str,
+    candidates: impl IntoIterator<Item = &'a String>,
+) -> Option<&'a String> {
+    candidates
+        .into_iter()
+        .map(|candidate| (strsim::levenshtein(name, candidate), candidate))
+        .filter(|(distance, candidate)| *distance <= candidate.chars().count().div_ceil(3))
+        .min_by_key(|(distance, _)| *distance)
+        .map(|(_, candidate)| candidate)
+}
@@ -0,0 +1,45 @@
+//! Runs the `--on-complete` command once the stats have been written, to
+//! automate things like rebuilding a site with them.
+
+use std::{path::PathBuf, process::Stdio};
+
+use anyhow::{Context, bail};
+use tokio::io::AsyncWriteExt;
+
+/// Runs `command` through the shell with `json` on its stdin and `outputs` as
+/// its arguments, failing if it can't be run or exits unsuccessfully.
+pub async fn run_on_complete(command: &str, json: &str, outputs: &[PathBuf]) -> anyhow::Result<()> {
+    let mut shell = if cfg!(windows) {
+        let mut shell = tokio::process::Command::new("cmd");
+
+        shell.arg("/C").arg(command);
+        shell
+    } else {
+        let mut shell = tokio::process::Command::new("sh");
+
+        // The argument after the command is `$0`, so the outputs start at `$1`.
+        shell.arg("-c").arg(command).arg("sh");
+        shell
+    };
+    let mut child = shell
+        .args(outputs)
+        .stdin(Stdio::piped())
+        .spawn()
+        .context("--on-complete command couldn't be run")?;
+
+    if let Some(mut stdin) = child.stdin.take() {
+        // The command doesn't have to read its stdin.
+        let _ = stdin.write_all(json.as_bytes()).await;
+    }
+
+    let status = child
+        .wait()
+        .await
+        .context("--on-complete command couldn't be run")?;
+
+    if !status.success() {
+        bail!("--on-complete command failed ({status})");
+    }
+
+    Ok(())
+}
@@ -0,0 +1,93 @@
+//! Stats written by hand for experience that can't be compiled, like work from
+//! before GitHub or in a repository that's no longer reachable, which are
+//! merged into the compiled stats with `--import`.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use anyhow::Context;
+use serde::Deserialize;
+
+use crate::stats::{CompiledStats, Stats};
+
+/// How imported stats are combined with the compiled stats of the same
+/// experience.
+#[derive(Deserialize, Default, Clone, Copy, PartialEq, Eq, Debug)]
+#[serde(rename_all = "lowercase")]
+pub enum ImportMode {
+    /// Add the imported numbers to the compiled ones.
+    #[default]
+    Add,
+    /// Throw away the compiled stats and use only the imported ones.
+    Replace,
+}
+
+#[derive(Deserialize, Default)]
+#[serde(deny_unknown_fields)]
+pub struct ImportedExperience {
+    /// Lines written in each language, which can be 0 to only list it.
+    #[serde(default)]
+    pub languages: BTreeMap<String, u64>,
+    #[serde(default)]
+    pub commits: u64,
+    /// The lines written in total, which are the lines of every language added
+    /// up if not given.
+    pub lines: Option<u64>,
+    #[serde(default)]
+    pub mode: ImportMode,
+}
+
+/// The imported stats of each experience, keyed by experience.
+#[derive(Deserialize, Default)]
+#[serde(transparent)]
+pub struct Import {
+    pub experiences: HashMap<String, ImportedExperience>,
+}
+
+impl ImportedExperience {
+    fn stats(&self) -> Stats {
+        Stats {
+            languages: self.languages.keys().cloned().collect(),
+            language_lines: self
+                .languages
+                .iter()
+                .filter(|(_, lines)| **lines != 0)
+                .map(|(language, lines)| (language.clone(), *lines))
+                .collect(),
+            commits: self.commits,
+            lines: self.lines.unwrap_or_else(|| self.languages.values().sum()),
+            ..Stats::default()
+        }
+    }
+}
+
+impl Import {
+    pub fn parse(json: &str) -> anyhow::Result<Self> {
+        Ok(serde_json::from_str(json)?)
+    }
+
+    pub async fn read(path: &Path) -> anyhow::Result<Self> {
+        let json = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("failed to read {}", path.display()))?;
+
+        Self::parse(&json).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    /// Merges the imported stats into `compiled_stats` according to the mode
+    /// of each experience. Experiences that weren't compiled are added as they
+    /// are.
+    pub fn merge_into(self, compiled_stats: &mut CompiledStats) {
+        for (experience, imported) in self.experiences {
+            let stats = compiled_stats.experiences.entry(experience).or_default();
+
+            if imported.mode == ImportMode::Replace {
+                *stats = Stats::default();
+            }
+
+            stats.merge(&imported.stats());
+        }
+    }
+}
@@ -0,0 +1,15 @@
+//! Compiles statistics about a GitHub user's contributions for their resume.
+
+pub mod args;
+pub mod auth;
+pub mod badge;
+pub mod cache;
+pub mod checkpoint;
+pub mod config;
+pub mod hook;
+pub mod import;
+pub mod linguist;
+pub mod output;
+pub mod report;
+pub mod source;
+pub mod stats;
@@ -0,0 +1,88 @@
+//! Names languages the way GitHub does, going by the extensions listed in a
+//! [linguist](https://github.com/github-linguist/linguist) `languages.yml`.
+
+use std::{
+    collections::{BTreeMap, HashMap},
+    path::Path,
+};
+
+use anyhow::Context;
+use serde::{Deserialize, Serialize};
+
+#[derive(Deserialize)]
+struct LinguistLanguage {
+    #[serde(default)]
+    extensions: Vec<String>,
+}
+
+/// The language each file extension is in.
+#[derive(Serialize, Default)]
+pub struct Linguist {
+    extensions: BTreeMap<String, String>,
+}
+
+impl Linguist {
+    /// Parses a linguist `languages.yml`.
+    ///
+    /// An extension used by several languages, like `h`, goes to the language
+    /// it's the primary (first) extension of, or else the first language in
+    /// alphabetical order. Extensions with more than one part, like `d.ts`,
+    /// are left out since only the last part of a file's extension is looked
+    /// at.
+    pub fn parse(yaml: &str) -> anyhow::Result<Self> {
+        let languages: BTreeMap<String, LinguistLanguage> = serde_yaml_ng::from_str(yaml)?;
+        let mut extensions: HashMap<String, (bool, String)> = HashMap::new();
+
+        for (name, language) in languages {
+            for (i, extension) in language.extensions.iter().enumerate() {
+                let Some(extension) = extension
+                    .strip_prefix('.')
+                    .filter(|extension| !extension.contains('.'))
+                    .map(str::to_ascii_lowercase)
+                else {
+                    continue;
+                };
+                let primary = i == 0;
+
+                match extensions.get(&extension) {
+                    Some((other_primary, _)) if *other_primary || !primary => (),
+                    _ => {
+                        extensions.insert(extension, (primary, name.clone()));
+                    }
+                }
+            }
+        }
+
+        Ok(Self {
+            extensions: extensions
+                .into_iter()
+                .map(|(extension, (_, name))| (extension, name))
+                .collect(),
+        })
+    }
+
+    pub async fn read(path: &Path) -> anyhow::Result<Self> {
+        let yaml = tokio::fs::read_to_string(path)
+            .await
+            .with_context(|| format!("failed to read {}", path.display()))?;
+
+        Self::parse(&yaml).with_context(|| format!("failed to parse {}", path.display()))
+    }
+
+    /// The name of the language files with `extension` are in, if linguist
+    /// knows it.
+    pub fn language(&self, extension: &str) -> Option<&str> {
+        self.extensions.get(extension).map(String::as_str)
+    }
+
+    /// Whether `language` is the name of a language linguist knows.
+    pub fn knows(&self, language: &str) -> bool {
+        self.extensions.values().any(|name| name == language)
+    }
+
+    /// Every extension and language name linguist knows, to suggest in place
+    /// of one it doesn't.
+    pub fn names(&self) -> impl Iterator<Item = &String> {
+        self.extensions.keys().chain(self.extensions.values())
+    }
+}
@@ -1,280 +1,627 @@
-use std::{
-    collections::{HashMap, HashSet},
-    ops::Deref,
-    path::PathBuf,
-    sync::Arc,
-};
+use std::{cmp::Reverse, collections::BTreeMap, process::ExitCode, sync::Arc, time::Duration};
 
-use anyhow::{anyhow, bail};
-use chrono::{DateTime, Utc};
+use anyhow::{Context, bail};
+use clap::{CommandFactory, Parser};
 use console::style;
-use futures_util::StreamExt;
-use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
-use octocrab::{Octocrab, models::repos::RepoCommit};
-use serde::Deserialize;
-use tokio::{sync::Mutex, task::JoinSet};
-
-#[derive(Deserialize)]
-#[serde(try_from = "String")]
-struct RepositoryPath {
-    owner: String,
-    repository: String,
+use http::{
+    HeaderValue, Uri,
+    header::{AUTHORIZATION, USER_AGENT},
+};
+use hyper_rustls::HttpsConnectorBuilder;
+use hyper_util::rt::TokioExecutor;
+use octocrab::{
+    AuthState, Octocrab, OctocrabBuilder,
+    service::middleware::{
+        auth_header::AuthHeaderLayer, base_uri::BaseUriLayer, extra_headers::ExtraHeadersLayer,
+    },
+};
+use resume_stats::{
+    args::{Api, Args, Command, ErrorFormat, Format, GroupBy},
+    auth::token,
+    config::{ALL_AUTHORS, Provider, closest_match, git_identities, read_needed_stats},
+    hook::run_on_complete,
+    import::Import,
+    output::{humanize, render, summary},
+    report::{EffectiveConfig, ErrorReport, config_hash},
+    source::{Bitbucket, GitHubGraphql, GitLab, Local, Sources},
+    stats::{
+        CompiledStats, ExtensionStats, RepositoryContext, RepositoryError, compile_stats,
+        progress_styles,
+    },
+};
+use rustls::{ClientConfig, RootCertStore, pki_types::CertificateDer, pki_types::pem::PemObject};
+use tower_http::follow_redirect::FollowRedirectLayer;
+
+/// Prints the stats of each language, most written first, for a skills-first
+/// view of the stats.
+fn print_by_language(args: &Args, compiled_stats: &CompiledStats) {
+    let count = |count: u64| {
+        if args.humanize {
+            humanize(count)
+        } else {
+            count.to_string()
+        }
+    };
+    let mut languages: Vec<_> = compiled_stats.by_language().into_iter().collect();
+
+    languages.sort_by_key(|(_, stats)| Reverse((stats.lines, stats.commits)));
+
+    let len = languages.len();
+
+    for (i, (language, stats)) in languages.into_iter().enumerate() {
+        println!("{}", style(format!("{language}:")).green().bold());
+        println!(
+            "    {} {}",
+            style(format!("{:12}", "Experiences:")).cyan().bold(),
+            stats.experiences.join(", "),
+        );
+        println!(
+            "    {} {}",
+            style(format!("{:12}", "Commits:")).cyan().bold(),
+            count(stats.commits),
+        );
+        println!(
+            "    {} {}",
+            style(format!("{:12}", "Lines:")).cyan().bold(),
+            count(stats.lines),
+        );
+
+        if i + 1 != len {
+            println!();
+        }
+    }
 }
 
-impl TryFrom<String> for RepositoryPath {
-    type Error = anyhow::Error;
+/// Prints every file extension that was changed, most lines first.
+fn print_languages(compiled_stats: &CompiledStats) {
+    let mut extensions: BTreeMap<&str, ExtensionStats> = BTreeMap::new();
 
-    fn try_from(value: String) -> Result<Self, Self::Error> {
-        let (owner, repository) = value.split_once('/').ok_or(anyhow!(
-            "expected repository path to be in the format owner/repository"
-        ))?;
+    for (extension, other_extension) in compiled_stats
+        .experiences
+        .values()
+        .flat_map(|stats| &stats.extensions)
+    {
+        let extension = extensions.entry(extension).or_default();
 
-        Ok(Self {
-            owner: owner.to_string(),
-            repository: repository.to_string(),
-        })
+        extension.files += other_extension.files;
+        extension.lines += other_extension.lines;
+    }
+
+    let mut extensions: Vec<_> = extensions.into_iter().collect();
+
+    extensions.sort_by_key(|(_, extension)| Reverse((extension.lines, extension.files)));
+
+    for (extension, ExtensionStats { files, lines }) in extensions {
+        println!(
+            "{} {files} files, {lines} lines",
+            style(format!("{:10}", format!("{extension}:")))
+                .cyan()
+                .bold(),
+        );
     }
 }
 
-#[derive(Deserialize)]
-struct Experience {
-    repositories: Vec<RepositoryPath>,
+/// Reads the PEM bundle of extra root certificates given with `--ca-cert`, if
+/// one was given.
+fn read_ca_cert(args: &Args) -> anyhow::Result<Option<Vec<u8>>> {
+    args.ca_cert
+        .as_ref()
+        .map(|ca_cert| {
+            std::fs::read(ca_cert).with_context(|| format!("failed to read {}", ca_cert.display()))
+        })
+        .transpose()
 }
 
-#[derive(Deserialize)]
-struct NeededStats {
-    author: String,
-    languages: HashSet<String>,
-    experience: HashMap<String, Experience>,
+/// Builds the GitHub client with `token` and the base URL and timeouts given
+/// through the arguments.
+///
+/// Octocrab's own client only trusts the system's root certificates, so with
+/// `ca_cert`, requests go through a connector that trusts its certificates
+/// too, with everything else Octocrab's client does layered on top.
+fn github_client(
+    args: &Args,
+    token: Option<String>,
+    ca_cert: Option<&[u8]>,
+) -> anyhow::Result<Octocrab> {
+    let mut octocrab_builder = Octocrab::builder()
+        .set_connect_timeout(args.connect_timeout.map(Duration::from_secs))
+        .set_read_timeout(args.read_timeout.map(Duration::from_secs));
+
+    let Some(pem_bundle) = ca_cert else {
+        if let Some(token) = token {
+            octocrab_builder = octocrab_builder.personal_token(token);
+        }
+
+        if let Some(base_url) = &args.base_url {
+            octocrab_builder = octocrab_builder.base_uri(base_url.as_str())?;
+        }
+
+        return Ok(octocrab_builder.build()?);
+    };
+
+    let mut roots = RootCertStore::empty();
+
+    roots.add_parsable_certificates(rustls_native_certs::load_native_certs().certs);
+
+    for certificate in CertificateDer::pem_slice_iter(pem_bundle) {
+        roots.add(certificate.context("failed to parse the certificates of --ca-cert")?)?;
+    }
+
+    let tls_config =
+        ClientConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()?
+            .with_root_certificates(roots)
+            .with_no_client_auth();
+    let connector = octocrab_builder.set_connect_timeout_service(
+        HttpsConnectorBuilder::new()
+            .with_tls_config(tls_config)
+            .https_or_http()
+            .enable_http1()
+            .build(),
+    );
+    let client = octocrab_builder.set_connector_retry_service(
+        hyper_util::client::legacy::Client::builder(TokioExecutor::new()).build(connector),
+    );
+    let base_uri: Uri = args
+        .base_url
+        .as_deref()
+        .unwrap_or("https://api.github.com")
+        .parse()
+        .context("failed to parse --base-url")?;
+    let auth_header = token
+        .map(|token| HeaderValue::try_from(format!("Bearer {token}")))
+        .transpose()
+        .with_context(|| format!("the token can't be used in the {AUTHORIZATION} header"))?;
+
+    Ok(OctocrabBuilder::new_empty()
+        .with_service(client)
+        .with_layer(&FollowRedirectLayer::new())
+        .with_layer(&ExtraHeadersLayer::new(Arc::new(vec![(
+            USER_AGENT,
+            HeaderValue::from_static("octocrab"),
+        )])))
+        .with_layer(&BaseUriLayer::new(base_uri.clone()))
+        .with_layer(&AuthHeaderLayer::new(
+            auth_header,
+            base_uri,
+            Uri::from_static("https://uploads.github.com"),
+        ))
+        .with_auth(AuthState::None)
+        .build()?)
 }
 
-struct Stats {
-    earliest_commit: DateTime<Utc>,
-    latest_commit: DateTime<Utc>,
-    languages: HashSet<String>,
-    commits: u64,
-    lines: u64,
+/// Builds the HTTP client for Bitbucket and GitLab with the timeouts given
+/// through the arguments, trusting the certificates of `ca_cert` too.
+fn http_client(args: &Args, ca_cert: Option<&[u8]>) -> anyhow::Result<reqwest::Client> {
+    let mut client_builder = reqwest::Client::builder();
+
+    if let Some(connect_timeout) = args.connect_timeout {
+        client_builder = client_builder.connect_timeout(Duration::from_secs(connect_timeout));
+    }
+
+    if let Some(read_timeout) = args.read_timeout {
+        client_builder = client_builder.read_timeout(Duration::from_secs(read_timeout));
+    }
+
+    if let Some(pem_bundle) = ca_cert {
+        for certificate in reqwest::Certificate::from_pem_bundle(pem_bundle)
+            .context("failed to parse the certificates of --ca-cert")?
+        {
+            client_builder = client_builder.add_root_certificate(certificate);
+        }
+    }
+
+    Ok(client_builder.build()?)
 }
 
-async fn try_main() -> anyhow::Result<()> {
-    let stats_toml = tokio::fs::read_to_string("Stats.toml").await?;
-    let needed_stats: NeededStats = toml::from_str(&stats_toml)?;
-    let author = Arc::new(needed_stats.author);
-    let needed_languages = Arc::new(needed_stats.languages);
-    let needed_experience = needed_stats.experience;
-    let keyring_entry = keyring::Entry::new("resume_stats", &whoami::username())?;
-    let pat = match keyring_entry.get_password() {
-        Ok(pat) => pat,
-        Err(e) => {
-            if let keyring::Error::NoEntry = e {
-                let pat = dialoguer::Password::new()
-                    .with_prompt("Please provide a GitHub PAT")
-                    .interact()?;
-
-                keyring_entry.set_password(&pat)?;
-
-                pat
-            } else {
-                bail!(e);
-            }
+/// Runs the command in `args`, which are replaced with the ones `[defaults]`
+/// fills in once the config is read.
+async fn try_main(args: &mut Arc<Args>) -> anyhow::Result<()> {
+    if let Some(Command::Completions { shell }) = args.command {
+        clap_complete::generate(
+            shell,
+            &mut Args::command(),
+            env!("CARGO_BIN_NAME"),
+            &mut std::io::stdout(),
+        );
+
+        return Ok(());
+    }
+
+    let mut needed_stats = read_needed_stats(args).await?;
+
+    // Errors are reported with the defaults too, like `error-format`.
+    if !needed_stats.defaults.is_empty() {
+        *args = Arc::new(Args::parse_with_defaults(
+            std::env::args_os(),
+            &needed_stats.defaults,
+        )?);
+    }
+
+    let args = args.clone();
+
+    if args.all_authors {
+        needed_stats.author = Some(ALL_AUTHORS.to_string());
+    }
+
+    // Clones are matched against emails and names even with `login`.
+    if needed_stats.author.is_none() {
+        needed_stats.git_identities = git_identities();
+    }
+
+    let local = match &args.local_repos {
+        Some(pattern) => {
+            let local = Local::discover(pattern)?;
+
+            needed_stats.add_local_repositories(local.paths());
+
+            Some(local)
         }
+        None => None,
     };
 
-    octocrab::initialise(Octocrab::builder().personal_token(pat).build()?);
-
-    let stats: Arc<Mutex<HashMap<String, Stats>>> = Arc::new(Mutex::new(HashMap::new()));
-    let mut join_set: JoinSet<Result<(), anyhow::Error>> = JoinSet::new();
-    let multi_progress = MultiProgress::new();
-    let experience_progress_bar = multi_progress.add(
-        ProgressBar::new(needed_experience.len() as u64)
-            .with_style(
-                ProgressStyle::with_template("{prefix:>12.cyan.bold} [{bar:25}] {pos}/{len}")?
-                    .progress_chars("=> "),
-            )
-            .with_prefix("Compiling"),
-    );
+    progress_styles(&args, &needed_stats.progress)?;
 
-    experience_progress_bar.tick();
-
-    for (experience, Experience { repositories }) in needed_experience {
-        let author = author.clone();
-        let needed_languages = needed_languages.clone();
-        let octocrab = octocrab::instance();
-        let stats = stats.clone();
-        let multi_progress = multi_progress.clone();
-        let progress_style = ProgressStyle::with_template(
-            "{prefix:>12.cyan.bold} [{bar:25}] {pos}/{len}: {wide_msg}",
-        )?
-        .progress_chars("=> ");
-
-        join_set.spawn(async move {
-            let repository_progress_bar = multi_progress.add(
-                ProgressBar::new(repositories.len() as u64)
-                    .with_style(progress_style.clone())
-                    .with_prefix("Fetching"),
-            );
+    if let Some(Command::Languages) = args.command
+        && args.commits_only
+    {
+        bail!("languages can't be listed with --commits-only");
+    }
 
-            repository_progress_bar.tick();
-
-            for RepositoryPath { owner, repository } in repositories {
-                repository_progress_bar.set_message(format!("{owner}/{repository} ({experience})"));
-
-                let commits = octocrab
-                    .repos(owner.clone(), repository.clone())
-                    .list_commits()
-                    .author(author.deref())
-                    .send()
-                    .await?
-                    .into_stream(&octocrab)
-                    .collect::<Vec<Result<RepoCommit, octocrab::Error>>>()
-                    .await;
-                let commit_handler = octocrab.commits(owner.clone(), repository.clone());
-                let commits_progress_bar = multi_progress.add(
-                    ProgressBar::new(commits.len() as u64)
-                        .with_style(progress_style.clone())
-                        .with_prefix("Fetching"),
-                );
-
-                for commit in commits {
-                    let commit_sha = commit?.sha;
-
-                    commits_progress_bar
-                        .set_message(format!("{} ({owner}/{repository})", &commit_sha[..6]));
-
-                    let commit = commit_handler.get(commit_sha).await?;
-                    let date = commit
-                        .commit
-                        .author
-                        .ok_or(anyhow!("commit is missing author"))?
-                        .date
-                        .ok_or(anyhow!("commit is missing date"))?;
-                    let mut languages = HashSet::new();
-                    let mut lines = 0;
-
-                    if let Some(files) = commit.files {
-                        for file in files {
-                            if let Some(extension) = PathBuf::from(file.filename).extension() {
-                                let language = extension.to_string_lossy().to_string();
-
-                                if needed_languages.contains(&language) {
-                                    languages.insert(language);
-                                }
-                            }
-
-                            lines += file.additions;
-                        }
-                    }
-
-                    stats
-                        .lock()
-                        .await
-                        .entry(experience.clone())
-                        .and_modify(|stats| {
-                            if date < stats.earliest_commit {
-                                stats.earliest_commit = date;
-                            }
-
-                            if date > stats.latest_commit {
-                                stats.latest_commit = date;
-                            }
-
-                            for language in languages.clone() {
-                                stats.languages.insert(language);
-                            }
-
-                            stats.commits += 1;
-                            stats.lines += lines;
-                        })
-                        .or_insert(Stats {
-                            earliest_commit: date,
-                            latest_commit: date,
-                            languages,
-                            commits: 1,
-                            lines,
-                        });
-
-                    commits_progress_bar.inc(1);
+    for path in &args.output {
+        Format::from_path(path)?;
+    }
+
+    if !args.only.is_empty() {
+        for experience in &args.only {
+            if !needed_stats.experience.contains_key(experience) {
+                match closest_match(experience, needed_stats.experience.keys()) {
+                    Some(suggestion) => bail!(
+                        "experience {experience:?} is not in the config, did you mean {suggestion:?}?"
+                    ),
+                    None => bail!("experience {experience:?} is not in the config"),
                 }
+            }
+        }
+
+        needed_stats
+            .experience
+            .retain(|experience, _| args.only.contains(experience));
+    }
 
-                commits_progress_bar.finish_and_clear();
-                repository_progress_bar.inc(1);
-                repository_progress_bar.println(format!(
-                    "{} {owner}/{repository}",
-                    style(format!("{:>12}", "Fetched")).green().bold()
-                ));
+    // The config is printed without looking for any tokens, so the author is
+    // only resolved when the stats are compiled.
+    if args.print_config {
+        if needed_stats.author.is_none() {
+            if needed_stats.only_uses_provider(Provider::Local) {
+                println!("# `author` isn't set, so it's the email and name in the git config");
+            } else {
+                println!("# `author` isn't set, so it's the user the GitHub PAT belongs to");
             }
+        }
 
-            repository_progress_bar.finish_and_clear();
+        print!(
+            "{}",
+            toml::to_string(&EffectiveConfig::new(&args, &needed_stats))?
+        );
 
-            Ok(())
-        });
+        return Ok(());
     }
 
-    while let Some(join_result) = join_set.join_next().await {
-        join_result??;
+    // Clones are matched by the git config anyway, so counting only them
+    // doesn't need a GitHub PAT to find out who the author is.
+    if needed_stats.author.is_none() && needed_stats.only_uses_provider(Provider::Local) {
+        let Some(identity) = needed_stats.git_identities.first() else {
+            bail!(
+                "`author` isn't set, and the git config doesn't have a user.email or user.name \
+                 to count the commits of clones by"
+            );
+        };
 
-        experience_progress_bar.inc(1);
+        needed_stats.author = Some(identity.clone());
     }
 
-    experience_progress_bar.finish_and_clear();
+    let ca_cert = read_ca_cert(&args)?;
+    let octocrab = |token: Option<String>| github_client(&args, token, ca_cert.as_deref());
+    let mut github_token = None;
+
+    // Counting only clones doesn't need GitHub.
+    if needed_stats.uses_provider(Provider::GitHub) || needed_stats.author.is_none() {
+        // The GitHub CLI keeps a token for each host it's logged in to.
+        let gh_host = args.use_gh_auth.then(|| {
+            args.base_url
+                .as_deref()
+                .and_then(|base_url| reqwest::Url::parse(base_url).ok())
+                .and_then(|base_url| base_url.host_str().map(str::to_string))
+                .unwrap_or("github.com".to_string())
+        });
 
-    println!(
-        "{} compiling stats",
-        style(format!("{:>12}", "Finished")).green().bold(),
-    );
+        github_token = Some(token(
+            "resume_stats",
+            "GITHUB_TOKEN",
+            args.token_file.as_deref(),
+            gh_host.as_deref(),
+            !args.no_keyring,
+            "Please provide a GitHub PAT",
+        )?);
+    }
 
-    let stats = stats.lock().await;
-
-    for (
-        i,
-        (
-            experience,
-            Stats {
-                earliest_commit,
-                latest_commit,
-                languages,
-                commits,
-                lines,
-            },
-        ),
-    ) in stats.iter().enumerate()
-    {
-        println!("{}", style(format!("{experience}:")).green().bold());
-        println!(
-            "    {} {}–{}",
-            style(format!("{:10}", "Timeline:")).cyan().bold(),
-            earliest_commit.format("%B %Y"),
-            latest_commit.format("%B %Y"),
-        );
-        println!(
-            "    {} {}",
-            style(format!("{:10}", "Languages:")).cyan().bold(),
-            languages
-                .iter()
-                .cloned()
-                .collect::<Vec<String>>()
-                .join(", ")
+    let mut sources = match args.api {
+        Api::Rest => Sources::new(octocrab(github_token)?),
+        Api::Graphql => Sources::new(GitHubGraphql::new(octocrab(github_token)?)),
+    };
+
+    for account in needed_stats.github_accounts() {
+        let account_token = token(
+            &format!("resume_stats_github_{account}"),
+            &account_env_var(account),
+            None,
+            None,
+            !args.no_keyring,
+            &format!("Please provide a GitHub PAT for {account}"),
+        )?;
+
+        sources = match args.api {
+            Api::Rest => sources.with_github_account(account, octocrab(Some(account_token))?),
+            Api::Graphql => sources
+                .with_github_account(account, GitHubGraphql::new(octocrab(Some(account_token))?)),
+        };
+    }
+
+    let http_client = http_client(&args, ca_cert.as_deref())?;
+
+    if needed_stats.uses_provider(Provider::Bitbucket) {
+        sources = sources.with_bitbucket(
+            Bitbucket::new(token(
+                "resume_stats_bitbucket",
+                "BITBUCKET_TOKEN",
+                None,
+                None,
+                !args.no_keyring,
+                "Please provide a Bitbucket access token",
+            )?)
+            .with_client(http_client.clone()),
         );
-        println!(
-            "    {} {commits}",
-            style(format!("{:10}", "Commits:")).cyan().bold(),
+    }
+
+    if needed_stats.uses_provider(Provider::GitLab) {
+        sources = sources.with_gitlab(
+            GitLab::new(token(
+                "resume_stats_gitlab",
+                "GITLAB_TOKEN",
+                None,
+                None,
+                !args.no_keyring,
+                "Please provide a GitLab access token",
+            )?)
+            .with_client(http_client),
         );
-        println!(
-            "    {} {lines}",
-            style(format!("{:10}", "Lines:")).cyan().bold(),
+    }
+
+    if let Some(local) = local {
+        sources = sources.with_local(local);
+    }
+
+    let config_hash = config_hash(&args, &needed_stats)?;
+    let needed_languages = needed_stats.languages.clone();
+    let import = match &args.import {
+        Some(path) => Some(Import::read(path).await?),
+        None => None,
+    };
+    let mut compiled_stats = compile_stats(sources, args.clone(), needed_stats).await?;
+
+    if let Some(import) = import {
+        import.merge_into(&mut compiled_stats);
+    }
+
+    if args.anonymize {
+        let anonymized = compiled_stats.anonymize();
+
+        if let Some(path) = &args.anonymize_map {
+            tokio::fs::write(path, serde_json::to_string_pretty(&anonymized)? + "\n")
+                .await
+                .with_context(|| format!("failed to write {}", path.display()))?;
+        }
+    }
+
+    let mut status = if compiled_stats.partial {
+        format!(
+            "{} compiling stats, showing partial results",
+            style(format!("{:>12}", "Interrupted")).yellow().bold(),
+        )
+    } else {
+        format!(
+            "{} compiling stats",
+            style(format!("{:>12}", "Finished")).green().bold(),
+        )
+    };
+
+    if let Some(sample) = args.sample
+        && compiled_stats.estimated()
+    {
+        status += &format!(", estimated from samples of {sample} commits per repository");
+    }
+
+    // Machine-readable output is kept alone on stdout so it can be piped.
+    if !args.quiet {
+        match args.format {
+            Format::Human => {
+                println!("{status}");
+
+                if let Some(Command::Languages) = args.command {
+                    print_languages(&compiled_stats);
+                } else if args.group_by == GroupBy::Language {
+                    print_by_language(&args, &compiled_stats);
+                } else {
+                    print!("{}", summary(&args, &compiled_stats));
+                }
+            }
+            format => {
+                eprintln!("{status}");
+                print!("{}", render(format, &args, &config_hash, &compiled_stats)?);
+            }
+        }
+    }
+
+    for path in &args.output {
+        tokio::fs::write(
+            path,
+            render(
+                Format::from_path(path)?,
+                &args,
+                &config_hash,
+                &compiled_stats,
+            )?,
+        )
+        .await
+        .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    if args.report_unused_languages {
+        let unused_languages = compiled_stats.unused_languages(&needed_languages);
+
+        if unused_languages.is_empty() {
+            eprintln!(
+                "{} every language in the config",
+                style(format!("{:>12}", "Used")).green().bold(),
+            );
+        } else {
+            eprintln!(
+                "{} {}, no file with these extensions was changed",
+                style(format!("{:>12}", "Unused")).yellow().bold(),
+                unused_languages.join(", "),
+            );
+        }
+    }
+
+    if args.profile {
+        print_profile(&compiled_stats);
+    }
+
+    for RepositoryContext {
+        experience,
+        repository,
+    } in &compiled_stats.interrupted
+    {
+        eprintln!(
+            "{} {repository} ({experience}), the commits counted in it so far are left out",
+            style(format!("{:>12}", "Interrupted")).yellow().bold(),
         );
+    }
 
-        if i + 1 != stats.len() {
-            println!();
+    if !compiled_stats.errors.is_empty() {
+        if args.error_format == ErrorFormat::Json {
+            for error in &compiled_stats.errors {
+                eprintln!("{}", serde_json::to_string(&ErrorReport::from(error))?);
+            }
+        } else {
+            eprintln!();
+            eprintln!("{}", style("Errors:").red().bold());
+
+            for RepositoryError {
+                experience,
+                repository,
+                error,
+                ..
+            } in &compiled_stats.errors
+            {
+                eprintln!("    {} ({experience}): {error}", style(repository).bold());
+            }
+
+            eprintln!();
+        }
+
+        bail!("some repositories couldn't be fetched, see the errors above");
+    }
+
+    if args.fail_on_empty
+        && compiled_stats
+            .experiences
+            .values()
+            .all(|stats| stats.commits == 0)
+    {
+        bail!("no commits were found, check that the author and GitHub PAT are correct");
+    }
+
+    if let Some(command) = &args.on_complete {
+        // The stats were already written, so the run still succeeds.
+        if let Err(e) = run_on_complete(
+            command,
+            &render(Format::Json, &args, &config_hash, &compiled_stats)?,
+            &args.output,
+        )
+        .await
+        {
+            eprintln!(
+                "{} {e:#}",
+                style(format!("{:>12}", "Warning")).yellow().bold(),
+            );
         }
     }
 
     Ok(())
 }
 
+/// The environment variable the token of another GitHub `account` can be given
+/// with, like `GITHUB_TOKEN_WORK_LOGIN` for `work-login`.
+fn account_env_var(account: &str) -> String {
+    format!(
+        "GITHUB_TOKEN_{}",
+        account
+            .chars()
+            .map(|char| match char {
+                'a'..='z' | 'A'..='Z' | '0'..='9' => char.to_ascii_uppercase(),
+                _ => '_',
+            })
+            .collect::<String>()
+    )
+}
+
 #[tokio::main]
-async fn main() {
-    if let Err(e) = try_main().await {
-        eprintln!("{}: {e:#}", style("error").red());
+async fn main() -> ExitCode {
+    let mut args = Arc::new(Args::parse());
+
+    if let Err(e) = try_main(&mut args).await {
+        match args.error_format {
+            ErrorFormat::Human => eprintln!("{}: {e:#}", style("error").red()),
+            ErrorFormat::Json => eprintln!(
+                "{}",
+                serde_json::to_string(&ErrorReport::new(&e))
+                    .expect("error reports should always serialize")
+            ),
+        }
+
+        return ExitCode::FAILURE;
+    }
+
+    ExitCode::SUCCESS
+}
+
+/// Prints how long each phase took for every experience compiled in this run,
+/// in seconds.
+fn print_profile(compiled_stats: &CompiledStats) {
+    eprintln!();
+    eprintln!(
+        "{}",
+        style(format!(
+            "{:20} {:>10} {:>10} {:>10} {:>10}",
+            "Profile:", "Listing", "Fetching", "Counting", "Reviews"
+        ))
+        .bold()
+    );
+
+    for (experience, _) in compiled_stats.ordered() {
+        let Some(profile) = compiled_stats.profiles.get(experience) else {
+            continue;
+        };
+
+        eprintln!(
+            "{:20} {:>10.2} {:>10.2} {:>10.2} {:>10.2}",
+            compiled_stats.name(experience),
+            profile.listing.as_secs_f64(),
+            profile.fetching.as_secs_f64(),
+            profile.counting.as_secs_f64(),
+            profile.reviews_and_releases.as_secs_f64(),
+        );
     }
 }
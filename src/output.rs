@@ -0,0 +1,769 @@
+//! Renders compiled stats in every format but the human-readable one, along
+//! with helpers for it.
+
+use std::cmp::Reverse;
+
+use anyhow::bail;
+use chrono::{Datelike, Weekday};
+use console::style;
+use serde::Serialize;
+
+use crate::{
+    args::{Args, Format, GroupBy},
+    badge::badge,
+    config::{ALL_AUTHORS, ExperienceDetails},
+    report::Report,
+    stats::{CompiledStats, Stats, Streaks, YearStats},
+};
+
+/// Shortens large counts with a k, M or B suffix and a single decimal, like
+/// 12.3k for 12,347.
+pub fn humanize(count: u64) -> String {
+    if count < 1000 {
+        return count.to_string();
+    }
+
+    let mut value = count as f64;
+
+    for suffix in ["k", "M", "B"] {
+        value /= 1000.0;
+
+        // Rounding to a single decimal can make a value reach the next
+        // suffix, which it should be shown with instead.
+        if (value * 10.0).round() < 10_000.0 || suffix == "B" {
+            let humanized = format!("{value:.1}");
+
+            return format!(
+                "{}{suffix}",
+                humanized.strip_suffix(".0").unwrap_or(&humanized)
+            );
+        }
+    }
+
+    unreachable!("the last suffix should always be used")
+}
+
+/// A column of the tables made for Markdown, CSV and HTML.
+struct Column {
+    header: &'static str,
+    value: fn(&Stats) -> String,
+}
+
+/// The columns shown for the given arguments, beside the experience's name.
+fn columns(args: &Args) -> Vec<Column> {
+    let mut columns = vec![
+        Column {
+            header: "Since",
+            value: |stats| {
+                stats
+                    .earliest_commit
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default()
+            },
+        },
+        Column {
+            header: "Until",
+            value: |stats| {
+                stats
+                    .latest_commit
+                    .map(|date| date.format("%Y-%m-%d").to_string())
+                    .unwrap_or_default()
+            },
+        },
+        Column {
+            header: "Commits",
+            value: |stats| stats.commits.to_string(),
+        },
+        Column {
+            header: "Repositories",
+            value: |stats| stats.repositories.len().to_string(),
+        },
+    ];
+
+    if args.with_share {
+        columns.push(Column {
+            header: "Share",
+            value: |stats| {
+                stats
+                    .share()
+                    .map(|share| format!("{share:.0}%"))
+                    .unwrap_or_default()
+            },
+        });
+    }
+
+    if !args.commits_only {
+        columns.push(Column {
+            header: "Languages",
+            value: |stats| {
+                let mut languages: Vec<&str> = stats.languages.iter().map(String::as_str).collect();
+
+                languages.sort_unstable();
+                languages.join(", ")
+            },
+        });
+        columns.push(Column {
+            header: "Lines",
+            value: |stats| stats.lines.to_string(),
+        });
+    }
+
+    if args.include_reviews {
+        columns.push(Column {
+            header: "Reviews",
+            value: |stats| stats.reviews.to_string(),
+        });
+    }
+
+    if args.include_releases {
+        columns.push(Column {
+            header: "Releases",
+            value: |stats| stats.releases.to_string(),
+        });
+    }
+
+    if args.include_branches {
+        columns.push(Column {
+            header: "Branches created",
+            value: |stats| stats.branches_created.to_string(),
+        });
+        columns.push(Column {
+            header: "Branches deleted",
+            value: |stats| stats.branches_deleted.to_string(),
+        });
+    }
+
+    if args.count_assets {
+        columns.push(Column {
+            header: "Assets",
+            value: |stats| stats.assets.to_string(),
+        });
+    }
+
+    columns
+}
+
+/// The header and rows of the table, in the order experiences are shown, or
+/// with a row for each language with `--group-by language`.
+fn table(args: &Args, compiled_stats: &CompiledStats) -> (Vec<String>, Vec<Vec<String>>) {
+    if args.group_by == GroupBy::Language {
+        return (
+            ["Language", "Experiences", "Commits", "Lines"]
+                .into_iter()
+                .map(str::to_string)
+                .collect(),
+            compiled_stats
+                .by_language()
+                .into_iter()
+                .map(|(language, stats)| {
+                    vec![
+                        language.to_string(),
+                        stats.experiences.join(", "),
+                        stats.commits.to_string(),
+                        stats.lines.to_string(),
+                    ]
+                })
+                .collect(),
+        );
+    }
+
+    let columns = columns(args);
+
+    (
+        ["Experience"]
+            .into_iter()
+            .chain(columns.iter().map(|column| column.header))
+            .map(str::to_string)
+            .collect(),
+        compiled_stats
+            .ordered()
+            .into_iter()
+            .map(|(experience, stats)| {
+                [compiled_stats.name(experience).to_string()]
+                    .into_iter()
+                    .chain(columns.iter().map(|column| (column.value)(stats)))
+                    .collect()
+            })
+            .collect(),
+    )
+}
+
+fn markdown(args: &Args, compiled_stats: &CompiledStats) -> String {
+    let (header, rows) = table(args, compiled_stats);
+    let row = |cells: &[String]| {
+        format!(
+            "| {} |\n",
+            cells
+                .iter()
+                .map(|cell| cell.replace('|', "\\|"))
+                .collect::<Vec<_>>()
+                .join(" | ")
+        )
+    };
+    let mut markdown = row(&header);
+
+    markdown += &format!("|{}\n", " --- |".repeat(header.len()));
+
+    for cells in rows {
+        markdown += &row(&cells);
+    }
+
+    markdown
+}
+
+fn csv(args: &Args, compiled_stats: &CompiledStats) -> String {
+    let (header, rows) = table(args, compiled_stats);
+    let row = |cells: &[String]| {
+        cells
+            .iter()
+            .map(|cell| {
+                if cell.contains([',', '"', '\n']) {
+                    format!("\"{}\"", cell.replace('"', "\"\""))
+                } else {
+                    cell.clone()
+                }
+            })
+            .collect::<Vec<_>>()
+            .join(",")
+            + "\n"
+    };
+    let mut csv = row(&header);
+
+    for cells in rows {
+        csv += &row(&cells);
+    }
+
+    csv
+}
+
+fn escape_html(text: &str) -> String {
+    text.replace('&', "&amp;")
+        .replace('<', "&lt;")
+        .replace('>', "&gt;")
+        .replace('"', "&quot;")
+}
+
+fn html(args: &Args, compiled_stats: &CompiledStats) -> String {
+    let (header, rows) = table(args, compiled_stats);
+    let row = |cell_tag: &str, cells: &[String]| {
+        format!(
+            "      <tr>{}</tr>\n",
+            cells
+                .iter()
+                .map(|cell| format!("<{cell_tag}>{}</{cell_tag}>", escape_html(cell)))
+                .collect::<String>()
+        )
+    };
+    let mut body = String::new();
+
+    for cells in rows {
+        body += &row("td", &cells);
+    }
+
+    format!(
+        r#"<!DOCTYPE html>
+<html lang="en">
+<head>
+  <meta charset="utf-8">
+  <title>Stats of {author}</title>
+</head>
+<body>
+  <table>
+    <thead>
+{header}    </thead>
+    <tbody>
+{body}    </tbody>
+  </table>
+</body>
+</html>
+"#,
+        author = escape_html(&compiled_stats.author),
+        header = row("th", &header),
+    )
+}
+
+/// The commits of every experience by day of the week and hour, as they're
+/// written by `--format punchcard-json`.
+#[derive(Serialize)]
+struct Punchcard<'a> {
+    timezone: &'a str,
+    commits: [[u64; 24]; 7],
+}
+
+fn punchcard<'a>(args: &'a Args, compiled_stats: &CompiledStats) -> Punchcard<'a> {
+    let mut commits = [[0; 24]; 7];
+
+    for stats in compiled_stats.experiences.values() {
+        for (weekday, hours) in stats.punchcard.iter().enumerate() {
+            for (hour, hour_commits) in hours.iter().enumerate() {
+                commits[weekday][hour] += hour_commits;
+            }
+        }
+    }
+
+    Punchcard {
+        timezone: args.timezone.name(),
+        commits,
+    }
+}
+
+/// Separates the thousands of `count` with commas, like 1,204.
+fn separate_thousands(count: u64) -> String {
+    let digits = count.to_string();
+    let mut separated = String::new();
+
+    for (i, digit) in digits.chars().enumerate() {
+        if i > 0 && (digits.len() - i).is_multiple_of(3) {
+            separated.push(',');
+        }
+
+        separated.push(digit);
+    }
+
+    separated
+}
+
+/// The `--top-languages` with the most lines across every experience.
+fn top_languages<'a>(args: &Args, compiled_stats: &'a CompiledStats) -> Vec<&'a str> {
+    let mut languages: Vec<_> = compiled_stats.by_language().into_iter().collect();
+
+    languages.sort_by_key(|(_, stats)| Reverse((stats.lines, stats.commits)));
+    languages
+        .into_iter()
+        .take(args.top_languages)
+        .map(|(language, _)| language)
+        .collect()
+}
+
+/// A single sentence of the totals across every experience and the
+/// `--top-languages` with the most lines, like "1,204 commits across 18 repos
+/// in Rust, Go, Python."
+fn oneline(args: &Args, compiled_stats: &CompiledStats) -> String {
+    let count = |count: u64, noun: &str| {
+        let plural = if count == 1 { "" } else { "s" };
+
+        if args.humanize {
+            format!("{} {noun}{plural}", humanize(count))
+        } else {
+            format!("{} {noun}{plural}", separate_thousands(count))
+        }
+    };
+    let commits = compiled_stats
+        .experiences
+        .values()
+        .map(|stats| stats.commits)
+        .sum();
+    let repositories = compiled_stats.repositories() as u64;
+    let languages = top_languages(args, compiled_stats);
+    let mut sentence = format!(
+        "{} across {}",
+        count(commits, "commit"),
+        count(repositories, "repo")
+    );
+
+    if !languages.is_empty() {
+        sentence += &format!(" in {}", languages.join(", "));
+    }
+
+    sentence + ".\n"
+}
+
+/// The totals across every experience as they're written by `--format card`,
+/// for filling in a social preview card template.
+#[derive(Serialize)]
+struct Card<'a> {
+    title: &'a str,
+    /// The years commits were made in, like "2019–2024".
+    subtitle: String,
+    numbers: [CardNumber; 3],
+    languages: Vec<&'a str>,
+}
+
+#[derive(Serialize)]
+struct CardNumber {
+    label: &'static str,
+    value: u64,
+    /// The value as it's shown, with `--humanize` if it's given.
+    text: String,
+}
+
+fn card<'a>(args: &Args, compiled_stats: &'a CompiledStats) -> Card<'a> {
+    let number = |label, value| CardNumber {
+        label,
+        value,
+        text: if args.humanize {
+            humanize(value)
+        } else {
+            separate_thousands(value)
+        },
+    };
+    let experiences = compiled_stats.experiences.values();
+    let first_year = experiences
+        .clone()
+        .filter_map(|stats| stats.earliest_commit)
+        .min()
+        .map(|date| date.year());
+    let last_year = experiences
+        .clone()
+        .filter_map(|stats| stats.latest_commit)
+        .max()
+        .map(|date| date.year());
+    let subtitle = match (first_year, last_year) {
+        (Some(first_year), Some(last_year)) if first_year != last_year => {
+            format!("{first_year}–{last_year}")
+        }
+        (Some(year), _) => year.to_string(),
+        _ => String::new(),
+    };
+    // Lines aren't counted with `--commits-only`, so the longest streak takes
+    // their place.
+    let lines = if args.commits_only {
+        number("Day streak", compiled_stats.streaks().longest)
+    } else {
+        number("Lines", experiences.clone().map(|stats| stats.lines).sum())
+    };
+
+    Card {
+        title: &compiled_stats.author,
+        subtitle,
+        numbers: [
+            number("Commits", experiences.map(|stats| stats.commits).sum()),
+            lines,
+            number("Repos", compiled_stats.repositories() as u64),
+        ],
+        languages: top_languages(args, compiled_stats),
+    }
+}
+
+/// Serializes `value` as a line of JSON, or indented over several with
+/// `pretty`.
+fn to_json(value: &impl Serialize, pretty: bool) -> serde_json::Result<String> {
+    let json = if pretty {
+        serde_json::to_string_pretty(value)?
+    } else {
+        serde_json::to_string(value)?
+    };
+
+    Ok(json + "\n")
+}
+
+/// Summarizes the stats of each experience for reading in the terminal.
+pub fn summary(args: &Args, compiled_stats: &CompiledStats) -> String {
+    let stats = &compiled_stats.experiences;
+    let mut summary = String::new();
+    let count = |count: u64| {
+        if args.humanize {
+            humanize(count)
+        } else {
+            count.to_string()
+        }
+    };
+    // Shows how close a count is to its target, if it has one.
+    let progress = |value: u64, target: Option<u64>| match target {
+        Some(0) | None => count(value),
+        Some(target) => format!(
+            "{}/{} ({:.0}%)",
+            count(value),
+            count(target),
+            value as f64 / target as f64 * 100.0,
+        ),
+    };
+    let active_since = stats
+        .values()
+        .filter_map(|stats| stats.earliest_commit)
+        .min();
+    let last_active = stats.values().filter_map(|stats| stats.latest_commit).max();
+
+    if let (Some(active_since), Some(last_active)) = (active_since, last_active) {
+        let Streaks { longest, current } = compiled_stats.streaks();
+
+        summary += &field_lines(
+            "",
+            &[
+                (
+                    "Active since:",
+                    active_since.format("%B %-d, %Y").to_string(),
+                ),
+                ("Last active:", last_active.format("%B %-d, %Y").to_string()),
+                ("Longest streak:", format!("{longest} days")),
+                ("Current streak:", format!("{current} days")),
+                ("Repositories:", compiled_stats.repositories().to_string()),
+            ],
+            None,
+        );
+        summary.push('\n');
+    }
+
+    let unavailable = style("unavailable with --commits-only").dim().to_string();
+    let experiences: Vec<_> = compiled_stats
+        .ordered()
+        .into_iter()
+        .map(|(experience, experience_stats)| {
+            let Stats {
+                earliest_commit,
+                latest_commit,
+                languages,
+                commits,
+                commits_without_files,
+                lines,
+                reviews,
+                releases,
+                branches_created,
+                branches_deleted,
+                assets,
+                owned_repositories,
+                external_repositories,
+                weekdays,
+                files,
+                ..
+            } = experience_stats;
+            let targets = compiled_stats
+                .targets
+                .get(experience)
+                .copied()
+                .unwrap_or_default();
+            let mut fields = Vec::new();
+
+            if let Some(ExperienceDetails {
+                role,
+                company,
+                start,
+                end,
+            }) = compiled_stats.details.get(experience)
+            {
+                let role = match (role, company) {
+                    (Some(role), Some(company)) => Some(format!("{role} at {company}")),
+                    (role, company) => role.as_ref().or(company.as_ref()).cloned(),
+                };
+
+                if let Some(role) = role {
+                    fields.push(("Role:", role));
+                }
+
+                if let Some(start) = start {
+                    fields.push((
+                        "Dates:",
+                        format!("{start}–{}", end.as_deref().unwrap_or("present")),
+                    ));
+                }
+            }
+
+            if let (Some(earliest_commit), Some(latest_commit)) = (earliest_commit, latest_commit) {
+                fields.push((
+                    "Timeline:",
+                    format!(
+                        "{}–{}",
+                        earliest_commit.format("%B %Y"),
+                        latest_commit.format("%B %Y"),
+                    ),
+                ));
+            }
+
+            if args.commits_only {
+                fields.push(("Languages:", unavailable.clone()));
+            } else {
+                let mut languages: Vec<&String> = languages.iter().collect();
+
+                languages.sort_by_key(|language| {
+                    (
+                        Reverse(experience_stats.language_lines.get(*language)),
+                        *language,
+                    )
+                });
+
+                // Only the languages with the most lines are named, so the
+                // summary of a polyglot experience stays on one line.
+                let more = languages.len().saturating_sub(args.max_languages.get());
+                let mut line = languages
+                    .into_iter()
+                    .take(args.max_languages.get())
+                    .cloned()
+                    .collect::<Vec<String>>()
+                    .join(", ");
+
+                if more > 0 {
+                    line += &format!(" … and {more} more");
+                }
+
+                fields.push(("Languages:", line));
+            }
+
+            fields.push(("Commits:", progress(*commits, targets.commits)));
+
+            if *commits_without_files > 0 {
+                fields.push((
+                    "Missing:",
+                    format!(
+                        "{} commits had no file data, so their lines and languages aren't counted",
+                        count(*commits_without_files),
+                    ),
+                ));
+            }
+
+            if !args.commits_only && !args.no_distinct_files {
+                fields.push(("Files:", format!("{} distinct", count(files.len() as u64))));
+            }
+
+            if args.with_share {
+                let share = experience_stats
+                    .share()
+                    .map_or("unknown".to_string(), |share| {
+                        format!(
+                            "{share:.0}% of {} commits",
+                            count(experience_stats.total_commits)
+                        )
+                    });
+
+                fields.push(("Share:", share));
+            }
+
+            // Everyone's repositories aren't told apart by who owns them.
+            fields.push((
+                "Repos:",
+                if compiled_stats.author == ALL_AUTHORS {
+                    experience_stats.repositories.len().to_string()
+                } else {
+                    format!(
+                        "{} ({owned_repositories} owned, {external_repositories} external)",
+                        experience_stats.repositories.len(),
+                    )
+                },
+            ));
+
+            if args.include_reviews {
+                fields.push(("Reviews:", reviews.to_string()));
+            }
+
+            if args.include_releases {
+                fields.push(("Releases:", releases.to_string()));
+            }
+
+            if args.include_branches {
+                fields.push((
+                    "Branches:",
+                    format!("{branches_created} created, {branches_deleted} deleted"),
+                ));
+            }
+
+            if args.commits_only {
+                fields.push(("Lines:", unavailable.clone()));
+            } else {
+                fields.push(("Lines:", progress(*lines, targets.lines)));
+            }
+
+            if *assets != 0 {
+                fields.push(("Assets:", assets.to_string()));
+            }
+
+            if args.weekday_breakdown {
+                fields.push((
+                    "Weekdays:",
+                    weekdays
+                        .iter()
+                        .enumerate()
+                        .map(|(i, commits)| {
+                            format!(
+                                "{} {commits}",
+                                Weekday::try_from(i as u8).expect("there should be 7 weekdays")
+                            )
+                        })
+                        .collect::<Vec<String>>()
+                        .join(", "),
+                ));
+            }
+
+            (experience, experience_stats, fields)
+        })
+        .collect();
+    // Every experience's labels are padded alike so their values line up,
+    // whichever of them each one shows.
+    let width = experiences
+        .iter()
+        .flat_map(|(_, _, fields)| fields)
+        .map(|(label, _)| label.chars().count())
+        .max();
+
+    for (i, (experience, Stats { years, .. }, fields)) in experiences.iter().enumerate() {
+        summary += &format!(
+            "{}\n",
+            style(format!("{}:", compiled_stats.name(experience)))
+                .green()
+                .bold()
+        );
+        summary += &field_lines("    ", fields, width);
+
+        if args.year_breakdown {
+            summary += &format!("    {}\n", style("Years:").cyan().bold());
+
+            for (year, YearStats { commits, lines }) in years {
+                if args.commits_only {
+                    summary += &format!("        {year}: {} commits\n", count(*commits));
+                } else {
+                    summary += &format!(
+                        "        {year}: {} commits, {} lines\n",
+                        count(*commits),
+                        count(*lines),
+                    );
+                }
+            }
+        }
+
+        if i + 1 != stats.len() {
+            summary.push('\n');
+        }
+    }
+
+    summary
+}
+
+/// Puts each label and its value on a line of its own after `indent`, with
+/// the labels padded to `width` or else the longest of them so the values line
+/// up.
+fn field_lines(indent: &str, fields: &[(&str, String)], width: Option<usize>) -> String {
+    let width = width
+        .or(fields.iter().map(|(label, _)| label.chars().count()).max())
+        .unwrap_or_default();
+
+    fields
+        .iter()
+        .map(|(label, value)| {
+            format!(
+                "{indent}{} {value}\n",
+                style(format!("{label:width$}")).cyan().bold(),
+            )
+        })
+        .collect()
+}
+
+/// Renders the stats in `format`, which can't be [`Format::Human`] since that
+/// is only ever printed.
+pub fn render(
+    format: Format,
+    args: &Args,
+    config_hash: &str,
+    compiled_stats: &CompiledStats,
+) -> anyhow::Result<String> {
+    Ok(match format {
+        Format::Human => bail!("the human-readable format can only be printed"),
+        Format::Json => to_json(
+            &Report::new(args, config_hash, compiled_stats),
+            args.json_pretty,
+        )?,
+        Format::Toml => toml::to_string(&Report::new(args, config_hash, compiled_stats))?,
+        Format::Markdown => markdown(args, compiled_stats),
+        Format::Csv => csv(args, compiled_stats),
+        Format::Html => html(args, compiled_stats),
+        Format::Badge => badge(
+            args.badge_metric.name(),
+            &compiled_stats
+                .experiences
+                .values()
+                .map(|stats| stats.metric(args.badge_metric))
+                .sum::<u64>()
+                .to_string(),
+        ),
+        Format::Oneline => oneline(args, compiled_stats),
+        Format::Card => to_json(&card(args, compiled_stats), args.json_pretty)?,
+        Format::PunchcardJson => to_json(&punchcard(args, compiled_stats), args.json_pretty)?,
+    })
+}
@@ -0,0 +1,247 @@
+use std::collections::{BTreeMap, BTreeSet};
+
+use chrono::{DateTime, NaiveDate, Utc};
+use serde::Serialize;
+use sha2::{Digest, Sha256};
+
+use crate::{
+    args::{Api, Args, AuthorMatch, DateField, GroupBy, LineMetric, MergeLines, Visibility},
+    config::{ExperienceDetails, NeededStats},
+    source::ErrorKind,
+    stats::{CompiledStats, LanguageStats, RepositoryContext, RepositoryError, Stats, Streaks},
+};
+
+/// Compiled stats in a form meant to be saved and read by other tools.
+#[derive(Serialize)]
+pub struct Report<'a> {
+    pub metadata: Metadata<'a>,
+    pub streaks: Streaks,
+    /// How many distinct repositories had a counted commit across every
+    /// experience.
+    pub repositories: usize,
+    /// The repositories that were skipped because they couldn't be fetched.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub errors: &'a [RepositoryError],
+    /// The repositories left out because compiling was interrupted while they
+    /// were being fetched.
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub interrupted: &'a [RepositoryContext],
+    /// The stats of each experience, unless they're grouped by language.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub experiences: BTreeMap<&'a str, ExperienceReport<'a>>,
+    /// The stats of each language with `--group-by language`.
+    #[serde(skip_serializing_if = "BTreeMap::is_empty")]
+    pub languages: BTreeMap<&'a str, LanguageStats<'a>>,
+}
+
+/// The stats of an experience along with the name it's shown with.
+#[derive(Serialize)]
+pub struct ExperienceReport<'a> {
+    pub name: &'a str,
+    #[serde(flatten)]
+    pub details: Option<&'a ExperienceDetails>,
+    /// How many distinct files were changed, unless `--no-distinct-files` was
+    /// passed.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub distinct_files: Option<usize>,
+    /// The stats without the files that were changed, which are only counted.
+    #[serde(flatten)]
+    pub stats: Stats,
+}
+
+/// An error printed with `--error-format json`.
+#[derive(Serialize)]
+pub struct ErrorReport<'a> {
+    pub kind: ErrorKind,
+    pub message: String,
+    /// The experience and repository being fetched when the error happened,
+    /// if it happened while fetching one.
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub experience: Option<&'a str>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub repository: Option<&'a str>,
+}
+
+impl<'a> ErrorReport<'a> {
+    pub fn new(e: &'a anyhow::Error) -> Self {
+        let context = e.downcast_ref::<RepositoryContext>();
+
+        Self {
+            kind: ErrorKind::of(e),
+            message: format!("{e:#}"),
+            experience: context.map(|context| context.experience.as_str()),
+            repository: context.map(|context| context.repository.as_str()),
+        }
+    }
+}
+
+impl<'a> From<&'a RepositoryError> for ErrorReport<'a> {
+    fn from(error: &'a RepositoryError) -> Self {
+        Self {
+            kind: error.kind,
+            message: error.error.clone(),
+            experience: Some(&error.experience),
+            repository: Some(&error.repository),
+        }
+    }
+}
+
+/// What produced a report, so it can be reproduced later.
+#[derive(Serialize)]
+pub struct Metadata<'a> {
+    pub version: &'static str,
+    /// SHA-256 hash of the config after merging every config file, along with
+    /// the options that affect what's counted.
+    pub config_hash: &'a str,
+    pub author: &'a str,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<NaiveDate>,
+    pub generated_at: DateTime<Utc>,
+    pub partial: bool,
+    /// Whether any stats were estimated from a sample of commits with
+    /// `--sample`.
+    pub estimated: bool,
+}
+
+/// The merged config along with the options that affect what's counted, for
+/// `--print-config`.
+#[derive(Serialize)]
+pub struct EffectiveConfig<'a> {
+    #[serde(flatten)]
+    pub needed_stats: &'a NeededStats,
+    pub options: Options<'a>,
+}
+
+#[derive(Serialize)]
+pub struct Options<'a> {
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub since: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub until: Option<NaiveDate>,
+    #[serde(skip_serializing_if = "<[_]>::is_empty")]
+    pub only: &'a [String],
+    pub author_match: AuthorMatch,
+    pub commits_only: bool,
+    pub include_reviews: bool,
+    pub include_releases: bool,
+    pub include_branches: bool,
+    pub merged_prs_only: bool,
+    pub languages_from_linguist_api: bool,
+    pub count_assets: bool,
+    pub with_share: bool,
+    pub weekdays_only: bool,
+    pub weekends_only: bool,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub skip_commits_over: Option<usize>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_commit_lines: Option<u64>,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub language: Option<&'a str>,
+    pub merge_lines: MergeLines,
+    pub line_metric: LineMetric,
+    pub skip_merges: bool,
+    pub first_parent: bool,
+    pub date_field: DateField,
+    pub visibility: Visibility,
+    pub only_present_files: bool,
+    pub api: Api,
+    #[serde(skip_serializing_if = "Option::is_none")]
+    pub min_language_lines: Option<u64>,
+    pub incremental: bool,
+}
+
+impl<'a> EffectiveConfig<'a> {
+    pub fn new(args: &'a Args, needed_stats: &'a NeededStats) -> Self {
+        Self {
+            needed_stats,
+            options: Options {
+                since: args.since,
+                until: args.until,
+                // The experiences are only known by their labels.
+                only: if args.anonymize { &[] } else { &args.only },
+                author_match: args.author_match,
+                commits_only: args.commits_only,
+                include_reviews: args.include_reviews,
+                include_releases: args.include_releases,
+                include_branches: args.include_branches,
+                merged_prs_only: args.merged_prs_only,
+                languages_from_linguist_api: args.languages_from_linguist_api,
+                count_assets: args.count_assets,
+                with_share: args.with_share,
+                weekdays_only: args.weekdays_only,
+                weekends_only: args.weekends_only,
+                skip_commits_over: args.skip_commits_over,
+                min_commit_lines: args.min_commit_lines,
+                language: args.language.as_deref(),
+                merge_lines: args.merge_lines,
+                line_metric: args.line_metric,
+                skip_merges: args.skip_merges,
+                first_parent: args.first_parent,
+                date_field: args.date_field,
+                visibility: args.visibility,
+                only_present_files: args.only_present_files,
+                api: args.api,
+                min_language_lines: args.min_language_lines,
+                incremental: args.incremental,
+            },
+        }
+    }
+}
+
+impl<'a> Report<'a> {
+    pub fn new(args: &Args, config_hash: &'a str, compiled_stats: &'a CompiledStats) -> Self {
+        Self {
+            metadata: Metadata {
+                version: env!("CARGO_PKG_VERSION"),
+                config_hash,
+                author: &compiled_stats.author,
+                since: args.since,
+                until: args.until,
+                generated_at: Utc::now(),
+                partial: compiled_stats.partial,
+                estimated: compiled_stats.estimated(),
+            },
+            streaks: compiled_stats.streaks(),
+            repositories: compiled_stats.repositories(),
+            errors: &compiled_stats.errors,
+            interrupted: &compiled_stats.interrupted,
+            experiences: compiled_stats
+                .experiences
+                .iter()
+                .filter(|_| args.group_by == GroupBy::Experience)
+                .map(|(experience, stats)| {
+                    (
+                        experience.as_str(),
+                        ExperienceReport {
+                            name: compiled_stats.name(experience),
+                            details: compiled_stats.details.get(experience),
+                            distinct_files: (!args.no_distinct_files).then_some(stats.files.len()),
+                            stats: Stats {
+                                files: BTreeSet::new(),
+                                ..stats.clone()
+                            },
+                        },
+                    )
+                })
+                .collect(),
+            languages: match args.group_by {
+                GroupBy::Experience => BTreeMap::new(),
+                GroupBy::Language => compiled_stats.by_language(),
+            },
+        }
+    }
+}
+
+/// Hashes the config along with the options that affect what's counted, so
+/// reports can tell whether either changed between runs.
+pub fn config_hash(args: &Args, needed_stats: &NeededStats) -> anyhow::Result<String> {
+    Ok(format!(
+        "{:x}",
+        Sha256::digest(serde_json::to_vec(&EffectiveConfig::new(
+            args,
+            needed_stats
+        ))?)
+    ))
+}
@@ -0,0 +1,484 @@
+mod bitbucket;
+mod github;
+mod gitlab;
+mod graphql;
+mod local;
+
+use std::{
+    collections::{BTreeMap, HashMap, HashSet},
+    error::Error,
+    fmt,
+    time::Duration,
+};
+
+use anyhow::bail;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::Serialize;
+use tokio::sync::OnceCell;
+
+pub use self::{bitbucket::Bitbucket, gitlab::GitLab, graphql::GitHubGraphql, local::Local};
+use crate::config::{Provider, RepositoryPath};
+
+/// A commit found in a repository, without what it changed.
+#[derive(Debug)]
+pub struct Commit {
+    pub sha: String,
+    /// When the commit was authored.
+    pub date: DateTime<Utc>,
+    /// When the commit was committed, which differs from when it was authored
+    /// if it was rebased or cherry-picked, for sources that know it.
+    pub committer_date: Option<DateTime<Utc>>,
+    /// The login of the account the provider linked the commit to.
+    pub author_login: Option<String>,
+    pub author_name: Option<String>,
+    pub author_email: Option<String>,
+    /// Whether the commit has more than one parent.
+    pub merge: bool,
+    /// The SHA of the commit's first parent, which is the commit the branch
+    /// it was made on was at before it.
+    pub first_parent: Option<String>,
+    /// Lines added by the commit, for sources that list them without the
+    /// commit's files.
+    pub additions: Option<u64>,
+    /// Lines removed by the commit, for sources that list them without the
+    /// commit's files.
+    pub deletions: Option<u64>,
+}
+
+/// A file changed by a commit.
+pub struct ChangedFile {
+    pub filename: String,
+    pub additions: u64,
+    pub deletions: u64,
+    /// Whether the file looks like a binary file, since providers don't count
+    /// lines for those.
+    pub binary: bool,
+    /// Whether the change only moves a submodule to another commit, which
+    /// providers show as a one line diff of the commit it points to.
+    pub submodule: bool,
+}
+
+/// Whether a diff only changes the commit a submodule points to, which looks
+/// like `+Subproject commit <sha>`.
+fn is_submodule_diff(diff: &str) -> bool {
+    let mut changes = diff
+        .lines()
+        .filter(|line| {
+            line.starts_with(['+', '-']) && !line.starts_with("+++") && !line.starts_with("---")
+        })
+        .peekable();
+
+    changes.peek().is_some() && changes.all(|line| line[1..].starts_with("Subproject commit "))
+}
+
+/// The error for a listed commit that can't be found anymore, usually because
+/// the history it was in has been rewritten with a force-push.
+#[derive(Debug)]
+pub struct CommitNotFound;
+
+impl fmt::Display for CommitNotFound {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "commit not found")
+    }
+}
+
+impl Error for CommitNotFound {}
+
+/// The error for listing the commits of a repository that doesn't have any
+/// yet, like one that was just created.
+#[derive(Debug)]
+pub struct EmptyRepository;
+
+impl fmt::Display for EmptyRepository {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "repository is empty")
+    }
+}
+
+impl Error for EmptyRepository {}
+
+/// Turns an HTTP 404 error from fetching a commit into [`CommitNotFound`].
+fn commit_not_found(e: anyhow::Error) -> anyhow::Error {
+    match e.downcast_ref::<reqwest::Error>() {
+        Some(reqwest_error) if reqwest_error.status() == Some(reqwest::StatusCode::NOT_FOUND) => {
+            CommitNotFound.into()
+        }
+        _ => e,
+    }
+}
+
+/// The error for a listing of commits that failed partway through, with the
+/// commits that were listed before it failed.
+#[derive(Debug)]
+pub struct PartialCommits {
+    pub commits: Vec<Commit>,
+    pub error: anyhow::Error,
+}
+
+impl fmt::Display for PartialCommits {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(
+            f,
+            "only {} commits could be listed: {:#}",
+            self.commits.len(),
+            self.error
+        )
+    }
+}
+
+impl Error for PartialCommits {}
+
+/// Fails with [`PartialCommits`] if listing commits failed with `result` after
+/// some were already listed.
+fn partial_commits(
+    commits: Vec<Commit>,
+    result: anyhow::Result<()>,
+) -> anyhow::Result<Vec<Commit>> {
+    match result {
+        Ok(()) => Ok(commits),
+        Err(error) if commits.is_empty() => Err(error),
+        Err(error) => Err(PartialCommits { commits, error }.into()),
+    }
+}
+
+/// How many times a page is requested before giving up on it.
+const PAGE_ATTEMPTS: u64 = 3;
+
+/// Whether a failed request could succeed if it's made again, which is
+/// anything but an HTTP client error other than being rate limited.
+fn is_retryable(e: &anyhow::Error) -> bool {
+    let status = if let Some(octocrab::Error::GitHub { source, .. }) = e.downcast_ref() {
+        Some(source.status_code.as_u16())
+    } else {
+        e.downcast_ref::<reqwest::Error>()
+            .and_then(reqwest::Error::status)
+            .map(|status| status.as_u16())
+    };
+
+    status.is_none_or(|status| !(400..500).contains(&status) || status == 429)
+}
+
+/// What went wrong, for tools that react to some failures differently.
+#[derive(Clone, Copy, PartialEq, Eq, Debug, Serialize)]
+#[serde(rename_all = "snake_case")]
+pub enum ErrorKind {
+    /// The provider's rate limit was used up.
+    RateLimit,
+    /// A token is missing, invalid or lacks access.
+    Auth,
+    /// A repository or commit doesn't exist, or the token can't see it.
+    NotFound,
+    /// The provider couldn't be reached or took too long to respond.
+    Network,
+    Other,
+}
+
+impl ErrorKind {
+    /// Works out the kind of error from the first failed request in `e`'s
+    /// chain.
+    pub fn of(e: &anyhow::Error) -> Self {
+        for cause in e.chain() {
+            let status = if let Some(octocrab::Error::GitHub { source, .. }) = cause.downcast_ref()
+            {
+                // GitHub responds to a used up rate limit with a 403 too.
+                if source.message.to_lowercase().contains("rate limit") {
+                    return ErrorKind::RateLimit;
+                }
+
+                source.status_code.as_u16()
+            } else if let Some(octocrab::Error::Hyper { .. } | octocrab::Error::Service { .. }) =
+                cause.downcast_ref()
+            {
+                return ErrorKind::Network;
+            } else if let Some(e) = cause.downcast_ref::<reqwest::Error>() {
+                match e.status() {
+                    Some(status) => status.as_u16(),
+                    None if e.is_connect() || e.is_timeout() => return ErrorKind::Network,
+                    None => continue,
+                }
+            } else if cause.is::<CommitNotFound>() {
+                return ErrorKind::NotFound;
+            } else {
+                continue;
+            };
+
+            return match status {
+                429 => ErrorKind::RateLimit,
+                401 | 403 => ErrorKind::Auth,
+                404 => ErrorKind::NotFound,
+                _ => ErrorKind::Other,
+            };
+        }
+
+        ErrorKind::Other
+    }
+
+    /// Says what went wrong without naming anything that was being fetched.
+    pub fn description(self) -> &'static str {
+        match self {
+            ErrorKind::RateLimit => "the rate limit was used up",
+            ErrorKind::Auth => "the token is missing, invalid or lacks access",
+            ErrorKind::NotFound => "the repository or a commit couldn't be found",
+            ErrorKind::Network => "the provider couldn't be reached",
+            ErrorKind::Other => "the repository couldn't be fetched",
+        }
+    }
+}
+
+/// Makes a request for a page again, waiting a little longer each time, until
+/// it succeeds or has been attempted [`PAGE_ATTEMPTS`] times.
+async fn retry<T, F: Future<Output = anyhow::Result<T>>>(
+    mut request: impl FnMut() -> F,
+) -> anyhow::Result<T> {
+    let mut attempt = 1;
+
+    loop {
+        match request().await {
+            Err(e) if attempt < PAGE_ATTEMPTS && is_retryable(&e) => {
+                tokio::time::sleep(Duration::from_secs(attempt)).await;
+                attempt += 1;
+            }
+            result => return result,
+        }
+    }
+}
+
+/// A pull request, without its reviews.
+pub struct PullRequest {
+    pub number: u64,
+    pub updated_at: Option<DateTime<Utc>>,
+}
+
+/// A review of a pull request.
+pub struct Review {
+    /// The login of whoever submitted the review.
+    pub author: Option<String>,
+    pub submitted_at: Option<DateTime<Utc>>,
+}
+
+/// A published release.
+pub struct Release {
+    /// The login of whoever published the release.
+    pub author: Option<String>,
+    pub published_at: Option<DateTime<Utc>>,
+}
+
+/// A branch being created or deleted.
+pub struct BranchEvent {
+    /// The login of whoever created or deleted the branch.
+    pub author: Option<String>,
+    pub created_at: Option<DateTime<Utc>>,
+    /// Whether the branch was deleted rather than created.
+    pub deleted: bool,
+}
+
+/// Somewhere repositories are hosted that commits can be fetched from.
+#[async_trait]
+pub trait Source: Send + Sync {
+    /// Gets where the repository at `path` is now if it's been renamed or
+    /// moved, for providers that keep redirecting old paths.
+    async fn moved_to(&self, _path: &RepositoryPath) -> anyhow::Result<Option<RepositoryPath>> {
+        Ok(None)
+    }
+
+    /// Lists the commits by `author`, or by anyone without one, in the
+    /// repository at `path`, optionally only those made within `since` and
+    /// `until`.
+    async fn list_commits(
+        &self,
+        path: &RepositoryPath,
+        author: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<Commit>>;
+
+    /// Estimates how many commits [`Source::list_commits`] would list with the
+    /// same arguments, for providers that can tell without listing them.
+    async fn estimate_commits(
+        &self,
+        _path: &RepositoryPath,
+        _author: Option<&str>,
+        _since: Option<DateTime<Utc>>,
+        _until: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Gets how many more requests can be made before the rate limit resets,
+    /// for providers with one.
+    async fn remaining_requests(&self) -> anyhow::Result<Option<u64>> {
+        Ok(None)
+    }
+
+    /// Gets the files changed by the commit `sha`, if they're available.
+    /// Fails with [`CommitNotFound`] if the commit doesn't exist anymore.
+    async fn get_changed_files(
+        &self,
+        path: &RepositoryPath,
+        sha: &str,
+    ) -> anyhow::Result<Option<Vec<ChangedFile>>>;
+
+    /// Lists the path of every file at the tip of the default branch of the
+    /// repository at `path`.
+    async fn list_present_files(&self, _path: &RepositoryPath) -> anyhow::Result<HashSet<String>> {
+        bail!("this provider can't list the files in a repository")
+    }
+
+    /// Gets whether the repository at `path` is hidden from the public, which
+    /// includes repositories only visible within an organization.
+    async fn is_private(&self, _path: &RepositoryPath) -> anyhow::Result<bool> {
+        bail!("this provider can't tell whether a repository is private")
+    }
+
+    /// Lists every pull request of the repository at `path`. Providers without
+    /// reviews have no pull requests to list.
+    async fn list_pull_requests(&self, _path: &RepositoryPath) -> anyhow::Result<Vec<PullRequest>> {
+        Ok(Vec::new())
+    }
+
+    /// Lists the reviews of pull request `number`.
+    async fn list_reviews(
+        &self,
+        _path: &RepositoryPath,
+        _number: u64,
+    ) -> anyhow::Result<Vec<Review>> {
+        Ok(Vec::new())
+    }
+
+    /// Lists every release of the repository at `path`. Providers without
+    /// releases have none to list.
+    async fn list_releases(&self, _path: &RepositoryPath) -> anyhow::Result<Vec<Release>> {
+        Ok(Vec::new())
+    }
+
+    /// Gets whether the commit `sha` is part of a pull request that was
+    /// merged.
+    async fn is_in_merged_pull_request(
+        &self,
+        _path: &RepositoryPath,
+        _sha: &str,
+    ) -> anyhow::Result<bool> {
+        bail!("this provider can't tell which pull requests a commit is part of")
+    }
+
+    /// Lists the branches created and deleted in the repository at `path`,
+    /// as far back as the provider remembers. Providers without events have
+    /// none to list.
+    async fn list_branch_events(&self, _path: &RepositoryPath) -> anyhow::Result<Vec<BranchEvent>> {
+        Ok(Vec::new())
+    }
+
+    /// Gets the bytes of code in each language that the provider detects in
+    /// the repository at `path`.
+    async fn list_languages(
+        &self,
+        _path: &RepositoryPath,
+    ) -> anyhow::Result<BTreeMap<String, u64>> {
+        bail!("this provider doesn't detect the languages of repositories")
+    }
+
+    /// Gets the login of the user the source is authenticated as.
+    async fn authenticated_login(&self) -> anyhow::Result<String> {
+        bail!("can't tell who the source is authenticated as, set `author` in the config")
+    }
+}
+
+/// The sources to fetch repositories from, one for each provider.
+pub struct Sources {
+    pub github: Box<dyn Source>,
+    /// GitHub authenticated as other accounts, by their logins.
+    pub github_accounts: HashMap<String, Box<dyn Source>>,
+    pub bitbucket: Option<Bitbucket>,
+    pub gitlab: Option<GitLab>,
+    pub local: Option<Local>,
+    /// The login `github` is authenticated as, once it's been requested.
+    authenticated_login: OnceCell<String>,
+}
+
+impl Sources {
+    pub fn new(github: impl Source + 'static) -> Self {
+        Self {
+            github: Box::new(github),
+            github_accounts: HashMap::new(),
+            bitbucket: None,
+            gitlab: None,
+            local: None,
+            authenticated_login: OnceCell::new(),
+        }
+    }
+
+    pub fn with_github_account(
+        mut self,
+        account: impl Into<String>,
+        github: impl Source + 'static,
+    ) -> Self {
+        self.github_accounts
+            .insert(account.into(), Box::new(github));
+        self
+    }
+
+    pub fn with_bitbucket(mut self, bitbucket: Bitbucket) -> Self {
+        self.bitbucket = Some(bitbucket);
+        self
+    }
+
+    pub fn with_gitlab(mut self, gitlab: GitLab) -> Self {
+        self.gitlab = Some(gitlab);
+        self
+    }
+
+    pub fn with_local(mut self, local: Local) -> Self {
+        self.local = Some(local);
+        self
+    }
+
+    /// Gets the login of the user GitHub is authenticated as. It's only
+    /// requested the first time, after which the same login is returned.
+    pub async fn authenticated_login(&self) -> anyhow::Result<&str> {
+        let login = self
+            .authenticated_login
+            .get_or_try_init(|| self.github.authenticated_login())
+            .await?;
+
+        Ok(login)
+    }
+
+    pub fn get(&self, provider: Provider) -> anyhow::Result<&dyn Source> {
+        match provider {
+            Provider::GitHub => Ok(self.github.as_ref()),
+            Provider::Bitbucket => Ok(self
+                .bitbucket
+                .as_ref()
+                .ok_or(anyhow::anyhow!("no Bitbucket access token was given"))?),
+            Provider::GitLab => Ok(self
+                .gitlab
+                .as_ref()
+                .ok_or(anyhow::anyhow!("no GitLab access token was given"))?),
+            Provider::Local => Ok(self
+                .local
+                .as_ref()
+                .ok_or(anyhow::anyhow!("no --local-repos were given"))?),
+        }
+    }
+
+    /// Gets the source of `provider` authenticated as `account`, or as the
+    /// default account if there isn't one.
+    pub fn get_account(
+        &self,
+        provider: Provider,
+        account: Option<&str>,
+    ) -> anyhow::Result<&dyn Source> {
+        match (provider, account) {
+            (_, None) => self.get(provider),
+            (Provider::GitHub, Some(account)) => Ok(self
+                .github_accounts
+                .get(account)
+                .ok_or(anyhow::anyhow!(
+                    "no GitHub PAT was given for the account {account:?}"
+                ))?
+                .as_ref()),
+            (provider, Some(_)) => bail!("accounts can only be given for GitHub, not {provider}"),
+        }
+    }
+}
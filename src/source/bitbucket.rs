@@ -0,0 +1,251 @@
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, de::DeserializeOwned};
+
+use super::{ChangedFile, Commit, Source, commit_not_found, partial_commits, retry};
+use crate::config::RepositoryPath;
+
+/// A client for the Bitbucket Cloud REST API, authenticated with an access
+/// token.
+pub struct Bitbucket {
+    client: reqwest::Client,
+    base_url: String,
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct Page<T> {
+    values: Vec<T>,
+    next: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BitbucketCommit {
+    hash: String,
+    date: DateTime<Utc>,
+    author: BitbucketAuthor,
+    #[serde(default)]
+    parents: Vec<BitbucketParent>,
+}
+
+#[derive(Deserialize)]
+struct BitbucketParent {
+    hash: String,
+}
+
+#[derive(Deserialize)]
+struct BitbucketAuthor {
+    /// The author as written in the commit, like `Name <email>`.
+    raw: String,
+    user: Option<BitbucketUser>,
+}
+
+#[derive(Deserialize)]
+struct BitbucketUser {
+    nickname: Option<String>,
+}
+
+#[derive(Deserialize)]
+struct BitbucketRepository {
+    is_private: bool,
+}
+
+#[derive(Deserialize)]
+struct DiffStat {
+    status: String,
+    lines_added: u64,
+    lines_removed: u64,
+    old: Option<DiffStatFile>,
+    new: Option<DiffStatFile>,
+}
+
+#[derive(Deserialize)]
+struct DiffStatFile {
+    path: String,
+}
+
+impl BitbucketAuthor {
+    fn nickname(&self) -> Option<&str> {
+        self.user.as_ref()?.nickname.as_deref()
+    }
+
+    fn name(&self) -> &str {
+        self.raw
+            .rsplit_once('<')
+            .map_or(self.raw.as_str(), |(name, _)| name)
+            .trim()
+    }
+
+    fn email(&self) -> Option<&str> {
+        self.raw.rsplit_once('<')?.1.strip_suffix('>')
+    }
+
+    /// Whether this is `author`, going by either their Bitbucket nickname or
+    /// the email they committed with.
+    fn is(&self, author: &str) -> bool {
+        self.nickname()
+            .is_some_and(|nickname| nickname.eq_ignore_ascii_case(author))
+            || self
+                .email()
+                .is_some_and(|email| email.eq_ignore_ascii_case(author))
+    }
+}
+
+impl Bitbucket {
+    pub fn new(token: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: "https://api.bitbucket.org/2.0".to_string(),
+            token,
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Uses `client` for requests, such as one with timeouts or extra root
+    /// certificates.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// Gets every value of a paginated endpoint by following its `next` links.
+    async fn get_all<T: DeserializeOwned>(&self, url: String) -> anyhow::Result<Vec<T>> {
+        let (values, result) = self.get_pages(url).await;
+
+        result.map(|()| values)
+    }
+
+    /// Gets the values of a paginated endpoint until the last page or a page
+    /// that fails even after being retried, along with why it failed.
+    async fn get_pages<T: DeserializeOwned>(&self, url: String) -> (Vec<T>, anyhow::Result<()>) {
+        let mut values = Vec::new();
+        let mut next = Some(url);
+
+        while let Some(url) = next {
+            let result = retry(|| async {
+                let page: Page<T> = self
+                    .client
+                    .get(&url)
+                    .bearer_auth(&self.token)
+                    .send()
+                    .await?
+                    .error_for_status()?
+                    .json()
+                    .await
+                    .with_context(|| format!("failed to parse response from {url}"))?;
+
+                Ok(page)
+            })
+            .await;
+
+            match result {
+                Ok(page) => {
+                    values.extend(page.values);
+                    next = page.next;
+                }
+                Err(e) => return (values, Err(e)),
+            }
+        }
+
+        (values, Ok(()))
+    }
+}
+
+#[async_trait]
+impl Source for Bitbucket {
+    async fn list_commits(
+        &self,
+        path: &RepositoryPath,
+        author: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<Commit>> {
+        let (commits, result) = self
+            .get_pages::<BitbucketCommit>(format!(
+                "{}/repositories/{}/{}/commits?pagelen=100",
+                self.base_url, path.owner, path.repository,
+            ))
+            .await;
+        // Bitbucket can't filter commits by author or date itself.
+        let commits = commits
+            .into_iter()
+            .filter(|commit| {
+                author.is_none_or(|author| commit.author.is(author))
+                    && since.is_none_or(|since| commit.date >= since)
+                    && until.is_none_or(|until| commit.date <= until)
+            })
+            .map(|commit| Commit {
+                author_login: commit.author.nickname().map(str::to_string),
+                author_name: Some(commit.author.name().to_string()),
+                author_email: commit.author.email().map(str::to_string),
+                merge: commit.parents.len() > 1,
+                first_parent: commit.parents.into_iter().next().map(|parent| parent.hash),
+                additions: None,
+                deletions: None,
+                sha: commit.hash,
+                date: commit.date,
+                // Bitbucket only lists when a commit was authored.
+                committer_date: None,
+            })
+            .collect();
+
+        partial_commits(commits, result)
+    }
+
+    async fn is_private(&self, path: &RepositoryPath) -> anyhow::Result<bool> {
+        let url = format!(
+            "{}/repositories/{}/{}",
+            self.base_url, path.owner, path.repository,
+        );
+        let BitbucketRepository { is_private } = retry(|| async {
+            self.client
+                .get(&url)
+                .bearer_auth(&self.token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .with_context(|| format!("failed to parse response from {url}"))
+        })
+        .await?;
+
+        Ok(is_private)
+    }
+
+    async fn get_changed_files(
+        &self,
+        path: &RepositoryPath,
+        sha: &str,
+    ) -> anyhow::Result<Option<Vec<ChangedFile>>> {
+        Ok(Some(
+            self.get_all::<DiffStat>(format!(
+                "{}/repositories/{}/{}/diffstat/{sha}?pagelen=100",
+                self.base_url, path.owner, path.repository,
+            ))
+            .await
+            .map_err(commit_not_found)?
+            .into_iter()
+            .filter_map(|diff_stat| {
+                Some(ChangedFile {
+                    // Like GitHub, Bitbucket doesn't count lines of binary
+                    // files.
+                    binary: diff_stat.lines_added == 0
+                        && diff_stat.lines_removed == 0
+                        && matches!(diff_stat.status.as_str(), "added" | "modified"),
+                    // Bitbucket's diffstat doesn't tell submodules apart.
+                    submodule: false,
+                    filename: diff_stat.new.or(diff_stat.old)?.path,
+                    additions: diff_stat.lines_added,
+                    deletions: diff_stat.lines_removed,
+                })
+            })
+            .collect(),
+        ))
+    }
+}
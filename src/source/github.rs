@@ -0,0 +1,391 @@
+use std::collections::{BTreeMap, HashSet};
+
+use anyhow::{anyhow, bail};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use futures_util::TryStreamExt;
+use octocrab::{Octocrab, models::repos::DiffEntryStatus, params};
+use serde::Deserialize;
+
+use super::{
+    BranchEvent, ChangedFile, Commit, CommitNotFound, EmptyRepository, PullRequest, Release,
+    Review, Source, is_submodule_diff, partial_commits, retry,
+};
+use crate::{auth, config::RepositoryPath};
+
+#[derive(Deserialize)]
+struct RepositoryName {
+    full_name: String,
+}
+
+#[derive(Deserialize)]
+struct RepositoryVisibility {
+    private: bool,
+}
+
+#[derive(Deserialize)]
+struct Tree {
+    tree: Vec<TreeEntry>,
+    /// Whether the tree had too many entries to respond with all of them.
+    truncated: bool,
+}
+
+#[derive(Deserialize)]
+struct TreeEntry {
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Deserialize)]
+struct PullRequestSummary {
+    merged_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct Event {
+    #[serde(rename = "type")]
+    kind: String,
+    actor: Option<EventActor>,
+    #[serde(default)]
+    payload: EventPayload,
+    created_at: Option<DateTime<Utc>>,
+}
+
+#[derive(Deserialize)]
+struct EventActor {
+    login: String,
+}
+
+#[derive(Deserialize, Default)]
+struct EventPayload {
+    ref_type: Option<String>,
+}
+
+/// Turns the HTTP 409 error GitHub responds with when listing the commits of a
+/// repository without any into [`EmptyRepository`].
+fn empty_repository(e: anyhow::Error) -> anyhow::Error {
+    match e.downcast_ref() {
+        Some(octocrab::Error::GitHub { source, .. }) if source.status_code.as_u16() == 409 => {
+            EmptyRepository.into()
+        }
+        _ => e,
+    }
+}
+
+/// How many events GitHub lists for a repository at most, across every page.
+const MAX_EVENTS: usize = 300;
+
+#[async_trait]
+impl Source for Octocrab {
+    async fn moved_to(&self, path: &RepositoryPath) -> anyhow::Result<Option<RepositoryPath>> {
+        // GitHub redirects requests for renamed or transferred repositories,
+        // which is only noticeable by the name it responds with.
+        let RepositoryName { full_name } = self.get(format!("/repos/{path}"), None::<&()>).await?;
+
+        if full_name.eq_ignore_ascii_case(&path.to_string()) {
+            Ok(None)
+        } else {
+            Ok(Some(full_name.try_into()?))
+        }
+    }
+
+    async fn list_commits(
+        &self,
+        path: &RepositoryPath,
+        author: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<Commit>> {
+        let repo_handler = self.repos(path.owner.clone(), path.repository.clone());
+        let mut commits = Vec::new();
+        let mut page = retry(|| async {
+            let mut list_commits = repo_handler.list_commits().per_page(100);
+
+            if let Some(author) = author {
+                list_commits = list_commits.author(author);
+            }
+
+            if let Some(since) = since {
+                list_commits = list_commits.since(since);
+            }
+
+            if let Some(until) = until {
+                list_commits = list_commits.until(until);
+            }
+
+            Ok(list_commits.send().await?)
+        })
+        .await
+        .map_err(empty_repository)?;
+
+        // Pages are requested one at a time so the commits listed before one
+        // fails aren't lost.
+        let result = loop {
+            for commit in page.take_items() {
+                let commit_author = commit
+                    .commit
+                    .author
+                    .ok_or(anyhow!("commit is missing author"))?;
+
+                commits.push(Commit {
+                    date: commit_author
+                        .date
+                        .ok_or(anyhow!("commit is missing date"))?,
+                    committer_date: commit.commit.committer.and_then(|committer| committer.date),
+                    author_login: commit.author.map(|author| author.login),
+                    author_name: Some(commit_author.name),
+                    author_email: Some(commit_author.email),
+                    merge: commit.parents.len() > 1,
+                    first_parent: commit.parents.first().and_then(|parent| parent.sha.clone()),
+                    additions: None,
+                    deletions: None,
+                    sha: commit.sha,
+                });
+            }
+
+            match retry(|| async { Ok(self.get_page(&page.next).await?) }).await {
+                Ok(Some(next_page)) => page = next_page,
+                Ok(None) => break Ok(()),
+                Err(e) => break Err(e),
+            }
+        };
+
+        partial_commits(commits, result)
+    }
+
+    async fn estimate_commits(
+        &self,
+        path: &RepositoryPath,
+        author: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Option<u64>> {
+        let repo_handler = self.repos(path.owner.clone(), path.repository.clone());
+        let mut list_commits = repo_handler.list_commits().per_page(1);
+
+        if let Some(author) = author {
+            list_commits = list_commits.author(author);
+        }
+
+        if let Some(since) = since {
+            list_commits = list_commits.since(since);
+        }
+
+        if let Some(until) = until {
+            list_commits = list_commits.until(until);
+        }
+
+        let page = list_commits.send().await?;
+
+        // With one commit per page, there are as many pages as commits.
+        Ok(Some(
+            page.number_of_pages()
+                .map_or(page.items.len() as u64, u64::from),
+        ))
+    }
+
+    async fn remaining_requests(&self) -> anyhow::Result<Option<u64>> {
+        Ok(Some(
+            self.ratelimit().get().await?.resources.core.remaining as u64,
+        ))
+    }
+
+    async fn get_changed_files(
+        &self,
+        path: &RepositoryPath,
+        sha: &str,
+    ) -> anyhow::Result<Option<Vec<ChangedFile>>> {
+        let commit = match self
+            .commits(path.owner.clone(), path.repository.clone())
+            .get(sha)
+            .await
+        {
+            Ok(commit) => commit,
+            Err(octocrab::Error::GitHub { source, .. })
+                if source.status_code == reqwest::StatusCode::NOT_FOUND =>
+            {
+                bail!(CommitNotFound)
+            }
+            Err(e) => bail!(e),
+        };
+
+        Ok(commit.files.map(|files| {
+            files
+                .into_iter()
+                .map(|file| ChangedFile {
+                    // GitHub doesn't give binary files a patch or any changed
+                    // lines, which is the only way to tell them apart from
+                    // text files.
+                    binary: file.changes == 0
+                        && file.patch.is_none()
+                        && matches!(
+                            file.status,
+                            DiffEntryStatus::Added | DiffEntryStatus::Modified
+                        ),
+                    submodule: file.patch.as_deref().is_some_and(is_submodule_diff),
+                    filename: file.filename,
+                    additions: file.additions,
+                    deletions: file.deletions,
+                })
+                .collect()
+        }))
+    }
+
+    async fn is_private(&self, path: &RepositoryPath) -> anyhow::Result<bool> {
+        let RepositoryVisibility { private } =
+            self.get(format!("/repos/{path}"), None::<&()>).await?;
+
+        Ok(private)
+    }
+
+    async fn list_pull_requests(&self, path: &RepositoryPath) -> anyhow::Result<Vec<PullRequest>> {
+        self.pulls(path.owner.clone(), path.repository.clone())
+            .list()
+            .state(params::State::All)
+            .per_page(100)
+            .send()
+            .await?
+            .into_stream(self)
+            .map_ok(|pull| PullRequest {
+                number: pull.number,
+                updated_at: pull.updated_at,
+            })
+            .map_err(anyhow::Error::from)
+            .try_collect()
+            .await
+    }
+
+    async fn list_reviews(
+        &self,
+        path: &RepositoryPath,
+        number: u64,
+    ) -> anyhow::Result<Vec<Review>> {
+        self.pulls(path.owner.clone(), path.repository.clone())
+            .list_reviews(number)
+            .per_page(100)
+            .send()
+            .await?
+            .into_stream(self)
+            .map_ok(|review| Review {
+                author: review.user.map(|user| user.login),
+                submitted_at: review.submitted_at,
+            })
+            .map_err(anyhow::Error::from)
+            .try_collect()
+            .await
+    }
+
+    async fn list_releases(&self, path: &RepositoryPath) -> anyhow::Result<Vec<Release>> {
+        self.repos(path.owner.clone(), path.repository.clone())
+            .releases()
+            .list()
+            .per_page(100)
+            .send()
+            .await?
+            .into_stream(self)
+            .map_ok(|release| Release {
+                author: release.author.map(|author| author.login),
+                published_at: release.published_at,
+            })
+            .map_err(anyhow::Error::from)
+            .try_collect()
+            .await
+    }
+
+    async fn is_in_merged_pull_request(
+        &self,
+        path: &RepositoryPath,
+        sha: &str,
+    ) -> anyhow::Result<bool> {
+        let pulls: Vec<PullRequestSummary> = retry(|| async {
+            Ok(self
+                .get(format!("/repos/{path}/commits/{sha}/pulls"), None::<&()>)
+                .await?)
+        })
+        .await?;
+
+        Ok(pulls.iter().any(|pull| pull.merged_at.is_some()))
+    }
+
+    async fn list_languages(&self, path: &RepositoryPath) -> anyhow::Result<BTreeMap<String, u64>> {
+        let languages = retry(|| async {
+            Ok(self
+                .repos(path.owner.clone(), path.repository.clone())
+                .list_languages()
+                .await?)
+        })
+        .await?;
+
+        Ok(languages
+            .into_iter()
+            .map(|(language, bytes)| (language, bytes.max(0) as u64))
+            .collect())
+    }
+
+    async fn list_branch_events(&self, path: &RepositoryPath) -> anyhow::Result<Vec<BranchEvent>> {
+        let mut events = Vec::new();
+
+        // GitHub only lists the last 300 events of the past 90 days, and
+        // responds with an error for pages past them.
+        for page in 1..=MAX_EVENTS / 100 {
+            let page_events: Vec<Event> = retry(|| async {
+                Ok(self
+                    .get(
+                        format!("/repos/{path}/events"),
+                        Some(&[("per_page", "100".to_string()), ("page", page.to_string())]),
+                    )
+                    .await?)
+            })
+            .await?;
+            let last_page = page_events.len() < 100;
+
+            events.extend(page_events.into_iter().filter_map(|event| {
+                let deleted = match event.kind.as_str() {
+                    "CreateEvent" => false,
+                    "DeleteEvent" => true,
+                    _ => return None,
+                };
+
+                (event.payload.ref_type.as_deref() == Some("branch")).then(|| BranchEvent {
+                    author: event.actor.map(|actor| actor.login),
+                    created_at: event.created_at,
+                    deleted,
+                })
+            }));
+
+            if last_page {
+                break;
+            }
+        }
+
+        Ok(events)
+    }
+
+    async fn list_present_files(&self, path: &RepositoryPath) -> anyhow::Result<HashSet<String>> {
+        // `HEAD` is the tip of the default branch.
+        let Tree { tree, truncated } = retry(|| async {
+            Ok(self
+                .get(
+                    format!("/repos/{path}/git/trees/HEAD"),
+                    Some(&[("recursive", "1")]),
+                )
+                .await?)
+        })
+        .await?;
+
+        if truncated {
+            bail!("{path} has too many files for GitHub to list them all");
+        }
+
+        Ok(tree
+            .into_iter()
+            .filter(|entry| entry.kind == "blob")
+            .map(|entry| entry.path)
+            .collect())
+    }
+
+    async fn authenticated_login(&self) -> anyhow::Result<String> {
+        auth::authenticated_login(self).await
+    }
+}
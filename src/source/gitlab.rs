@@ -0,0 +1,291 @@
+use std::collections::HashSet;
+
+use anyhow::Context;
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use serde::{Deserialize, de::DeserializeOwned};
+
+use super::{
+    ChangedFile, Commit, Source, commit_not_found, is_submodule_diff, partial_commits, retry,
+};
+use crate::config::RepositoryPath;
+
+/// A client for the GitLab REST API, authenticated with a personal access
+/// token.
+pub struct GitLab {
+    client: reqwest::Client,
+    base_url: String,
+    token: String,
+}
+
+#[derive(Deserialize)]
+struct GitLabCommit {
+    id: String,
+    authored_date: DateTime<Utc>,
+    committed_date: Option<DateTime<Utc>>,
+    author_name: String,
+    author_email: String,
+    #[serde(default)]
+    parent_ids: Vec<String>,
+}
+
+#[derive(Deserialize)]
+struct MergeRequest {
+    /// `opened`, `closed`, `locked` or `merged`.
+    state: String,
+}
+
+#[derive(Deserialize)]
+struct Project {
+    /// `public`, `internal` or `private`.
+    visibility: String,
+}
+
+#[derive(Deserialize)]
+struct TreeEntry {
+    path: String,
+    #[serde(rename = "type")]
+    kind: String,
+}
+
+#[derive(Deserialize)]
+struct Diff {
+    old_path: String,
+    new_path: String,
+    deleted_file: bool,
+    diff: String,
+}
+
+impl GitLab {
+    pub fn new(token: String) -> Self {
+        Self {
+            client: reqwest::Client::new(),
+            base_url: "https://gitlab.com/api/v4".to_string(),
+            token,
+        }
+    }
+
+    pub fn with_base_url(mut self, base_url: impl Into<String>) -> Self {
+        self.base_url = base_url.into();
+        self
+    }
+
+    /// Uses `client` for requests, such as one with timeouts or extra root
+    /// certificates.
+    pub fn with_client(mut self, client: reqwest::Client) -> Self {
+        self.client = client;
+        self
+    }
+
+    /// The URL of a project's API, which is identified by its URL-encoded path.
+    fn project_url(&self, path: &RepositoryPath) -> String {
+        format!(
+            "{}/projects/{}",
+            self.base_url,
+            path.to_string().replace('/', "%2F"),
+        )
+    }
+
+    /// Gets every value of a paginated endpoint by following its
+    /// `x-next-page` headers.
+    async fn get_all<T: DeserializeOwned>(
+        &self,
+        url: String,
+        query: &[(&str, String)],
+    ) -> anyhow::Result<Vec<T>> {
+        let (values, result) = self.get_pages(url, query).await;
+
+        result.map(|()| values)
+    }
+
+    /// Gets the values of a paginated endpoint until the last page or a page
+    /// that fails even after being retried, along with why it failed.
+    async fn get_pages<T: DeserializeOwned>(
+        &self,
+        url: String,
+        query: &[(&str, String)],
+    ) -> (Vec<T>, anyhow::Result<()>) {
+        let mut values = Vec::new();
+        let mut page = "1".to_string();
+
+        loop {
+            let result = retry(|| async {
+                let response = self
+                    .client
+                    .get(&url)
+                    .header("PRIVATE-TOKEN", &self.token)
+                    .query(query)
+                    .query(&[("per_page", "100"), ("page", &page)])
+                    .send()
+                    .await?
+                    .error_for_status()?;
+                let next_page = response
+                    .headers()
+                    .get("x-next-page")
+                    .and_then(|next_page| next_page.to_str().ok())
+                    .filter(|next_page| !next_page.is_empty())
+                    .map(str::to_string);
+                let page_values = response
+                    .json::<Vec<T>>()
+                    .await
+                    .with_context(|| format!("failed to parse response from {url}"))?;
+
+                Ok((page_values, next_page))
+            })
+            .await;
+
+            match result {
+                Ok((page_values, next_page)) => {
+                    values.extend(page_values);
+
+                    match next_page {
+                        Some(next_page) => page = next_page,
+                        None => return (values, Ok(())),
+                    }
+                }
+                Err(e) => return (values, Err(e)),
+            }
+        }
+    }
+}
+
+#[async_trait]
+impl Source for GitLab {
+    async fn list_commits(
+        &self,
+        path: &RepositoryPath,
+        author: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<Commit>> {
+        let mut query = Vec::new();
+
+        // GitLab matches `author` against commit author names and emails.
+        if let Some(author) = author {
+            query.push(("author", author.to_string()));
+        }
+
+        if let Some(since) = since {
+            query.push(("since", since.to_rfc3339()));
+        }
+
+        if let Some(until) = until {
+            query.push(("until", until.to_rfc3339()));
+        }
+
+        let (commits, result) = self
+            .get_pages::<GitLabCommit>(
+                format!("{}/repository/commits", self.project_url(path)),
+                &query,
+            )
+            .await;
+        let commits = commits
+            .into_iter()
+            .map(|commit| Commit {
+                sha: commit.id,
+                date: commit.authored_date,
+                committer_date: commit.committed_date,
+                // GitLab doesn't link commits to accounts.
+                author_login: None,
+                author_name: Some(commit.author_name),
+                author_email: Some(commit.author_email),
+                merge: commit.parent_ids.len() > 1,
+                first_parent: commit.parent_ids.first().cloned(),
+                additions: None,
+                deletions: None,
+            })
+            .collect();
+
+        partial_commits(commits, result)
+    }
+
+    async fn get_changed_files(
+        &self,
+        path: &RepositoryPath,
+        sha: &str,
+    ) -> anyhow::Result<Option<Vec<ChangedFile>>> {
+        Ok(Some(
+            self.get_all::<Diff>(
+                format!("{}/repository/commits/{sha}/diff", self.project_url(path)),
+                &[],
+            )
+            .await
+            .map_err(commit_not_found)?
+            .into_iter()
+            .map(|diff| ChangedFile {
+                // GitLab doesn't count lines itself, so added lines are
+                // counted from the diff, which is empty for binary files.
+                additions: diff
+                    .diff
+                    .lines()
+                    .filter(|line| line.starts_with('+') && !line.starts_with("+++"))
+                    .count() as u64,
+                deletions: diff
+                    .diff
+                    .lines()
+                    .filter(|line| line.starts_with('-') && !line.starts_with("---"))
+                    .count() as u64,
+                binary: diff.diff.is_empty() && !diff.deleted_file,
+                submodule: is_submodule_diff(&diff.diff),
+                filename: if diff.deleted_file {
+                    diff.old_path
+                } else {
+                    diff.new_path
+                },
+            })
+            .collect(),
+        ))
+    }
+
+    async fn is_private(&self, path: &RepositoryPath) -> anyhow::Result<bool> {
+        let url = self.project_url(path);
+        let Project { visibility } = retry(|| async {
+            self.client
+                .get(&url)
+                .header("PRIVATE-TOKEN", &self.token)
+                .send()
+                .await?
+                .error_for_status()?
+                .json()
+                .await
+                .with_context(|| format!("failed to parse response from {url}"))
+        })
+        .await?;
+
+        // Internal projects are only visible to signed in users.
+        Ok(visibility != "public")
+    }
+
+    async fn is_in_merged_pull_request(
+        &self,
+        path: &RepositoryPath,
+        sha: &str,
+    ) -> anyhow::Result<bool> {
+        // GitLab calls pull requests merge requests.
+        Ok(self
+            .get_all::<MergeRequest>(
+                format!(
+                    "{}/repository/commits/{sha}/merge_requests",
+                    self.project_url(path)
+                ),
+                &[],
+            )
+            .await?
+            .iter()
+            .any(|merge_request| merge_request.state == "merged"))
+    }
+
+    async fn list_present_files(&self, path: &RepositoryPath) -> anyhow::Result<HashSet<String>> {
+        // Without a `ref`, the tree is listed at the tip of the default branch.
+        Ok(self
+            .get_all::<TreeEntry>(
+                format!("{}/repository/tree", self.project_url(path)),
+                &[("recursive", "true".to_string())],
+            )
+            .await?
+            .into_iter()
+            .filter(|entry| entry.kind == "blob")
+            .map(|entry| entry.path)
+            .collect())
+    }
+}
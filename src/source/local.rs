@@ -0,0 +1,250 @@
+use std::{
+    collections::{HashMap, HashSet},
+    path::PathBuf,
+};
+
+use anyhow::{Context, anyhow};
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use console::style;
+use git2::{
+    Delta, ErrorCode, FileMode, ObjectType, Oid, Patch, Repository, Sort, TreeWalkMode,
+    TreeWalkResult,
+};
+
+use super::{ChangedFile, Commit, CommitNotFound, EmptyRepository, Source};
+use crate::config::{Provider, RepositoryPath};
+
+/// Clones of repositories on this machine, which are read with libgit2 without
+/// making any requests.
+pub struct Local {
+    /// The directory of each clone, by the path of the repository it's a clone
+    /// of.
+    directories: HashMap<String, PathBuf>,
+}
+
+/// Gets the path of the repository a remote `url` points to, like
+/// `owner/repository` from `git@github.com:owner/repository.git`.
+fn remote_path(url: &str) -> Option<RepositoryPath> {
+    let path = match url.split_once("://") {
+        Some((_, rest)) => rest.split_once('/')?.1,
+        None => url.split_once(':')?.1,
+    };
+    let path = path.trim_end_matches('/');
+
+    RepositoryPath::parse(path.strip_suffix(".git").unwrap_or(path), Provider::Local).ok()
+}
+
+/// Expands a leading `~` to the home directory, since globs given in quotes
+/// aren't expanded by the shell.
+fn expand_home(pattern: &str) -> String {
+    match (pattern.strip_prefix('~'), std::env::var("HOME")) {
+        (Some(rest), Ok(home)) if rest.is_empty() || rest.starts_with('/') => home + rest,
+        _ => pattern.to_string(),
+    }
+}
+
+impl Local {
+    /// Finds every clone of a repository matching the glob `pattern`, naming
+    /// each by the URL of its `origin` remote, or its first remote if it has
+    /// no `origin`. Clones without any remote are skipped with a warning.
+    pub fn discover(pattern: &str) -> anyhow::Result<Self> {
+        let mut directories = HashMap::new();
+
+        for directory in glob::glob(&expand_home(pattern))
+            .with_context(|| format!("failed to parse glob {pattern:?}"))?
+        {
+            let directory = directory?;
+            let Ok(repository) = Repository::open(&directory) else {
+                continue;
+            };
+            let remotes = repository.remotes()?;
+            let remote = match remotes.iter().flatten().find(|remote| *remote == "origin") {
+                Some(remote) => Some(remote),
+                None => remotes.iter().flatten().next(),
+            };
+            let path = remote
+                .and_then(|remote| repository.find_remote(remote).ok())
+                .and_then(|remote| remote.url().and_then(remote_path));
+
+            match path {
+                Some(path) => {
+                    directories.insert(path.to_string(), directory);
+                }
+                None => eprintln!(
+                    "{} {}, it has no remote to tell which repository it's a clone of",
+                    style(format!("{:>12}", "Skipped")).yellow().bold(),
+                    directory.display(),
+                ),
+            }
+        }
+
+        Ok(Self { directories })
+    }
+
+    /// The paths of the repositories that clones were found of.
+    pub fn paths(&self) -> impl Iterator<Item = RepositoryPath> {
+        self.directories.keys().map(|path| {
+            RepositoryPath::parse(path, Provider::Local).expect("discovered paths should be valid")
+        })
+    }
+
+    fn directory(&self, path: &RepositoryPath) -> anyhow::Result<PathBuf> {
+        self.directories
+            .get(&path.to_string())
+            .cloned()
+            .ok_or(anyhow!("no clone of {path} was found with --local-repos"))
+    }
+}
+
+#[async_trait]
+impl Source for Local {
+    async fn list_commits(
+        &self,
+        path: &RepositoryPath,
+        author: Option<&str>,
+        since: Option<DateTime<Utc>>,
+        until: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<Commit>> {
+        let directory = self.directory(path)?;
+        let author = author.map(str::to_string);
+
+        // libgit2 blocks, so it's kept off of the async runtime's threads.
+        tokio::task::spawn_blocking(move || {
+            let repository = Repository::open(directory)?;
+            let mut revwalk = repository.revwalk()?;
+            let mut commits = Vec::new();
+
+            // A repository without commits has a `HEAD` pointing to a branch
+            // that doesn't exist yet.
+            match revwalk.push_head() {
+                Err(e) if e.code() == ErrorCode::UnbornBranch => return Err(EmptyRepository.into()),
+                result => result?,
+            }
+
+            revwalk.set_sorting(Sort::TIME)?;
+
+            for oid in revwalk {
+                let commit = repository.find_commit(oid?)?;
+                let signature = commit.author();
+                let date = DateTime::from_timestamp(signature.when().seconds(), 0)
+                    .ok_or(anyhow!("commit has an invalid date"))?;
+                let committer_date = DateTime::from_timestamp(commit.time().seconds(), 0)
+                    .ok_or(anyhow!("commit has an invalid date"))?;
+                let author_name = signature.name().map(str::to_string);
+                let author_email = signature.email().map(str::to_string);
+
+                // Clones don't know which account made a commit, so `author`
+                // is matched against the name and email it was made with. Like
+                // `git log`, `since` and `until` go by when commits were
+                // committed.
+                if author.as_ref().is_some_and(|author| {
+                    [&author_name, &author_email].into_iter().all(|field| {
+                        field
+                            .as_ref()
+                            .is_none_or(|field| !field.eq_ignore_ascii_case(author))
+                    })
+                }) || since.is_some_and(|since| committer_date < since)
+                    || until.is_some_and(|until| committer_date > until)
+                {
+                    continue;
+                }
+
+                commits.push(Commit {
+                    sha: commit.id().to_string(),
+                    date,
+                    committer_date: Some(committer_date),
+                    author_login: None,
+                    author_name,
+                    author_email,
+                    merge: commit.parent_count() > 1,
+                    first_parent: commit.parent_id(0).ok().map(|oid| oid.to_string()),
+                    additions: None,
+                    deletions: None,
+                });
+            }
+
+            Ok(commits)
+        })
+        .await?
+    }
+
+    async fn get_changed_files(
+        &self,
+        path: &RepositoryPath,
+        sha: &str,
+    ) -> anyhow::Result<Option<Vec<ChangedFile>>> {
+        let directory = self.directory(path)?;
+        let sha = sha.to_string();
+
+        tokio::task::spawn_blocking(move || {
+            let repository = Repository::open(directory)?;
+            let commit = repository
+                .find_commit(Oid::from_str(&sha)?)
+                .map_err(|_| CommitNotFound)?;
+            // Like the providers, merges are compared to their first parent.
+            let parent_tree = match commit.parent(0) {
+                Ok(parent) => Some(parent.tree()?),
+                Err(_) => None,
+            };
+            let diff =
+                repository.diff_tree_to_tree(parent_tree.as_ref(), Some(&commit.tree()?), None)?;
+            let mut files = Vec::new();
+
+            for (index, delta) in diff.deltas().enumerate() {
+                let file = match delta.status() {
+                    Delta::Deleted => delta.old_file(),
+                    _ => delta.new_file(),
+                };
+                let Some(filename) = file.path() else {
+                    continue;
+                };
+                let patch = Patch::from_diff(&diff, index)?;
+                let (additions, deletions) = match &patch {
+                    Some(patch) => {
+                        let (_, additions, deletions) = patch.line_stats()?;
+
+                        (additions as u64, deletions as u64)
+                    }
+                    None => (0, 0),
+                };
+
+                files.push(ChangedFile {
+                    filename: filename.to_string_lossy().to_string(),
+                    additions,
+                    deletions,
+                    binary: patch
+                        .as_ref()
+                        .is_none_or(|patch| patch.delta().flags().is_binary()),
+                    submodule: file.mode() == FileMode::Commit,
+                });
+            }
+
+            Ok(Some(files))
+        })
+        .await?
+    }
+
+    async fn list_present_files(&self, path: &RepositoryPath) -> anyhow::Result<HashSet<String>> {
+        let directory = self.directory(path)?;
+
+        tokio::task::spawn_blocking(move || {
+            let repository = Repository::open(directory)?;
+            let tree = repository.head()?.peel_to_tree()?;
+            let mut files = HashSet::new();
+
+            tree.walk(TreeWalkMode::PreOrder, |root, entry| {
+                if entry.kind() == Some(ObjectType::Blob)
+                    && let Some(name) = entry.name()
+                {
+                    files.insert(format!("{root}{name}"));
+                }
+
+                TreeWalkResult::Ok
+            })?;
+
+            Ok(files)
+        })
+        .await?
+    }
+}
@@ -0,0 +1,2463 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    fmt,
+    io::ErrorKind,
+    path::PathBuf,
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use anyhow::{Context, bail};
+use chrono::{DateTime, Datelike, NaiveDate, Timelike, Utc};
+use console::{Term, style};
+use futures_util::{Stream, StreamExt, stream};
+use indicatif::{MultiProgress, ProgressBar, ProgressStyle};
+use serde::{Deserialize, Serialize, Serializer};
+use tokio::{
+    io::{AsyncWriteExt, BufWriter},
+    sync::{Mutex, Semaphore, mpsc},
+    task::JoinSet,
+};
+
+use crate::{
+    args::{Api, Args, AuthorMatch, Command, DateField, MergeLines, Metric, Visibility},
+    cache::{Cache, CachedRepository, settings_hash},
+    checkpoint::{Checkpoint, experience_hash},
+    config::{
+        ALL_AUTHORS, Experience, ExperienceDetails, NeededStats, ProgressConfig, Provider,
+        Repository, RepositoryPath, Targets,
+    },
+    linguist::Linguist,
+    source::{self, Commit, CommitNotFound, EmptyRepository, PartialCommits, Sources},
+};
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct Stats {
+    pub earliest_commit: Option<DateTime<Utc>>,
+    pub latest_commit: Option<DateTime<Utc>>,
+    pub languages: HashSet<String>,
+    /// Lines written in each of the languages.
+    #[serde(default)]
+    pub language_lines: BTreeMap<String, u64>,
+    /// Commits that changed a file in each of the languages.
+    #[serde(default)]
+    pub language_commits: BTreeMap<String, u64>,
+    pub commits: u64,
+    /// Commits the provider had no files for, such as ones too large for it
+    /// to list, which are counted without any lines or languages.
+    #[serde(default)]
+    pub commits_without_files: u64,
+    /// Commits made by anyone to the repositories, which are only counted with
+    /// `--with-share`.
+    #[serde(default)]
+    pub total_commits: u64,
+    pub lines: u64,
+    pub reviews: u64,
+    pub releases: u64,
+    /// Branches created by the author, which are only counted with
+    /// `--include-branches`.
+    #[serde(default)]
+    pub branches_created: u64,
+    /// Branches deleted by the author, which are only counted with
+    /// `--include-branches`.
+    #[serde(default)]
+    pub branches_deleted: u64,
+    pub assets: u64,
+    /// Repositories contributed to that the author owns.
+    #[serde(default)]
+    pub owned_repositories: u64,
+    /// Repositories contributed to that someone else owns.
+    #[serde(default)]
+    pub external_repositories: u64,
+    /// Commits made on each day of the week in UTC, starting with Monday.
+    pub weekdays: [u64; 7],
+    /// Commits made in each hour of each day of the week in `--timezone`,
+    /// starting with Monday.
+    #[serde(default)]
+    pub punchcard: [[u64; 24]; 7],
+    /// Contributions made in each calendar year in UTC.
+    #[serde(serialize_with = "serialize_years")]
+    pub years: BTreeMap<i32, YearStats>,
+    /// Every day in UTC that a commit was made on.
+    #[serde(default)]
+    pub days: BTreeSet<NaiveDate>,
+    /// Every file extension that was changed, whether it's a needed language or
+    /// not. Only compiled for the `languages` command.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub extensions: BTreeMap<String, ExtensionStats>,
+    /// Every file that was changed, prefixed with the path of its repository
+    /// so files with the same path in different repositories aren't confused.
+    /// Not compiled with `--no-distinct-files`.
+    #[serde(default, skip_serializing_if = "BTreeSet::is_empty")]
+    pub files: BTreeSet<String>,
+    /// Every repository with a counted commit, by the path it's configured
+    /// with or its upstream's.
+    #[serde(default)]
+    pub repositories: BTreeSet<String>,
+    /// Bytes of code in each language GitHub detects in the repositories,
+    /// which are only fetched with `--languages-from-linguist-api`.
+    #[serde(default, skip_serializing_if = "BTreeMap::is_empty")]
+    pub repository_languages: BTreeMap<String, u64>,
+    /// Whether any repository was only sampled with `--sample`, making these
+    /// stats estimates.
+    #[serde(default)]
+    pub estimated: bool,
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct ExtensionStats {
+    /// Files changed with the extension, counted once for every commit that
+    /// changed them.
+    pub files: u64,
+    pub lines: u64,
+}
+
+/// What a single commit adds to the stats.
+#[derive(Default)]
+struct CommitStats {
+    languages: HashSet<String>,
+    language_lines: BTreeMap<String, u64>,
+    /// The lines counted by `--line-metric`.
+    lines: u64,
+    /// Lines added, which `--min-commit-lines` and `--dump-commits` go by
+    /// whatever `--line-metric` is.
+    additions: u64,
+    /// Lines removed, which are written by `--dump-commits` and sent with each
+    /// counted commit.
+    deletions: u64,
+    assets: u64,
+    extensions: BTreeMap<String, ExtensionStats>,
+    files: Vec<String>,
+    /// Whether the provider had no files or lines for the commit, so only the
+    /// commit itself could be counted.
+    without_files: bool,
+    /// The day of the week, starting with Monday, and hour the commit was made
+    /// in `--timezone`.
+    weekday_hour: (usize, usize),
+}
+
+/// Something counted while compiling stats, yielded by
+/// [`compile_stats_stream`] as soon as it's counted.
+pub enum StatsEvent {
+    /// A commit was counted toward an experience.
+    Commit {
+        experience: String,
+        repository: String,
+        sha: String,
+        date: DateTime<Utc>,
+        lines: u64,
+        deletions: u64,
+        languages: BTreeSet<String>,
+    },
+    /// Every commit of a repository was counted toward an experience, adding
+    /// up to `stats`. Reviews, releases and branches are only added to the
+    /// experience.
+    Repository {
+        experience: String,
+        repository: String,
+        stats: Box<Stats>,
+    },
+    /// Compiling finished with these stats, which is always the last event.
+    Finished(Box<CompiledStats>),
+}
+
+/// A counted commit as it's written to `--dump-commits`.
+#[derive(Serialize)]
+struct CommitRecord<'a> {
+    sha: &'a str,
+    repository: &'a str,
+    experience: &'a str,
+    date: DateTime<Utc>,
+    additions: u64,
+    deletions: u64,
+    languages: BTreeSet<&'a str>,
+}
+
+/// Serializes years with string keys, since TOML doesn't allow any others.
+fn serialize_years<S: Serializer>(
+    years: &BTreeMap<i32, YearStats>,
+    serializer: S,
+) -> Result<S::Ok, S::Error> {
+    serializer.collect_map(years.iter().map(|(year, stats)| (year.to_string(), stats)))
+}
+
+#[derive(Serialize, Deserialize, Default, Clone)]
+pub struct YearStats {
+    pub commits: u64,
+    pub lines: u64,
+}
+
+/// Why a repository couldn't be fetched.
+#[derive(Serialize)]
+pub struct RepositoryError {
+    pub experience: String,
+    pub repository: String,
+    pub kind: source::ErrorKind,
+    pub error: String,
+}
+
+/// Context added to an error that stopped the run while fetching a
+/// repository, which can be found in the error's chain to tell where it
+/// happened. It's also the repository an experience was fetching when
+/// compiling was interrupted.
+#[derive(Serialize, Debug)]
+pub struct RepositoryContext {
+    pub experience: String,
+    pub repository: String,
+}
+
+impl fmt::Display for RepositoryContext {
+    fn fmt(&self, f: &mut fmt::Formatter<'_>) -> fmt::Result {
+        write!(f, "failed to fetch {}", self.repository)
+    }
+}
+
+/// Runs of consecutive days with at least one commit.
+#[derive(Serialize, Default, PartialEq, Eq, Debug)]
+pub struct Streaks {
+    /// The most consecutive days ever committed on.
+    pub longest: u64,
+    /// The consecutive days committed on up to today, or up to yesterday so
+    /// the streak isn't lost before committing today.
+    pub current: u64,
+}
+
+impl Streaks {
+    pub fn new(days: &BTreeSet<NaiveDate>, today: NaiveDate) -> Self {
+        let mut streaks = Streaks::default();
+        let mut streak = 0;
+        let mut previous_day: Option<NaiveDate> = None;
+
+        for day in days {
+            if previous_day.is_some_and(|previous_day| previous_day.succ_opt() == Some(*day)) {
+                streak += 1;
+            } else {
+                streak = 1;
+            }
+
+            streaks.longest = streaks.longest.max(streak);
+            previous_day = Some(*day);
+        }
+
+        if previous_day.is_some_and(|previous_day| {
+            previous_day == today || previous_day.succ_opt() == Some(today)
+        }) {
+            streaks.current = streak;
+        }
+
+        streaks
+    }
+}
+
+impl CompiledStats {
+    /// Whether any experience was estimated from a sample with `--sample`.
+    pub fn estimated(&self) -> bool {
+        self.experiences.values().any(|stats| stats.estimated)
+    }
+
+    /// How many distinct repositories had a counted commit across every
+    /// experience.
+    pub fn repositories(&self) -> usize {
+        self.experiences
+            .values()
+            .flat_map(|stats| &stats.repositories)
+            .collect::<HashSet<_>>()
+            .len()
+    }
+
+    /// The streaks of every experience combined, as of today in UTC.
+    pub fn streaks(&self) -> Streaks {
+        let days = self
+            .experiences
+            .values()
+            .flat_map(|stats| &stats.days)
+            .copied()
+            .collect();
+
+        Streaks::new(&days, Utc::now().date_naive())
+    }
+
+    /// The languages of `languages` that no experience changed a file of, for
+    /// `--report-unused-languages`.
+    pub fn unused_languages<'a>(&self, languages: &'a BTreeSet<String>) -> Vec<&'a str> {
+        languages
+            .iter()
+            .filter(|language| {
+                self.experiences
+                    .values()
+                    .all(|stats| !stats.language_lines.contains_key(*language))
+            })
+            .map(String::as_str)
+            .collect()
+    }
+
+    /// The name to show for `experience`, which is its key unless it was given
+    /// a name.
+    pub fn name<'a>(&'a self, experience: &'a str) -> &'a str {
+        self.names
+            .get(experience)
+            .map_or(experience, String::as_str)
+    }
+
+    /// The stats of every language used in any experience, which are the
+    /// stats of each experience added up by language instead.
+    pub fn by_language(&self) -> BTreeMap<&str, LanguageStats<'_>> {
+        let mut languages: BTreeMap<&str, LanguageStats> = BTreeMap::new();
+
+        for (experience, stats) in self.ordered() {
+            for language in &stats.languages {
+                let language_stats = languages.entry(language).or_default();
+
+                language_stats.experiences.push(self.name(experience));
+                language_stats.commits += stats.language_commits.get(language).unwrap_or(&0);
+                language_stats.lines += stats.language_lines.get(language).unwrap_or(&0);
+            }
+        }
+
+        languages
+    }
+
+    /// Every experience in the order they're shown, which is the configured
+    /// order followed by the rest in alphabetical order.
+    pub fn ordered(&self) -> Vec<(&str, &Stats)> {
+        let mut experiences: Vec<_> = self
+            .experiences
+            .iter()
+            .map(|(experience, stats)| (experience.as_str(), stats))
+            .collect();
+
+        experiences.sort_unstable_by_key(|(experience, _)| {
+            (
+                self.order
+                    .iter()
+                    .position(|ordered| ordered == experience)
+                    .unwrap_or(usize::MAX),
+                *experience,
+            )
+        });
+
+        experiences
+    }
+
+    /// Replaces every experience with a generic label like "Experience A", in
+    /// the order they're shown, and every repository with one like "Repo 1",
+    /// keeping every number as it is. Companies are left out of the details
+    /// and files are numbered, since either could give a project away. Errors
+    /// only keep a description of their kind.
+    pub fn anonymize(&mut self) -> Anonymized {
+        let mut anonymized = Anonymized::default();
+        let mut ordered: Vec<String> = self
+            .ordered()
+            .into_iter()
+            .map(|(experience, _)| experience.to_string())
+            .collect();
+
+        // Experiences whose every repository failed or was interrupted only
+        // appear in errors or interruptions.
+        for experience in self
+            .errors
+            .iter()
+            .map(|error| &error.experience)
+            .chain(self.interrupted.iter().map(|context| &context.experience))
+        {
+            if !ordered.contains(experience) {
+                ordered.push(experience.clone());
+            }
+        }
+
+        let experiences: HashMap<String, String> = ordered
+            .iter()
+            .enumerate()
+            .map(|(i, experience)| (experience.clone(), format!("Experience {}", letters(i))))
+            .collect();
+        let repositories: BTreeSet<&String> = self
+            .experiences
+            .values()
+            .flat_map(|stats| &stats.repositories)
+            .chain(self.errors.iter().map(|error| &error.repository))
+            .chain(self.interrupted.iter().map(|context| &context.repository))
+            .collect();
+        let repositories: HashMap<String, String> = repositories
+            .into_iter()
+            .enumerate()
+            .map(|(i, repository)| (repository.clone(), format!("Repo {}", i + 1)))
+            .collect();
+        let label = |labels: &HashMap<String, String>, key: &str| {
+            labels.get(key).cloned().unwrap_or_else(|| key.to_string())
+        };
+
+        self.experiences = std::mem::take(&mut self.experiences)
+            .into_iter()
+            .map(|(experience, mut stats)| {
+                stats.repositories = stats
+                    .repositories
+                    .iter()
+                    .map(|repository| label(&repositories, repository))
+                    .collect();
+                stats.files = (1..=stats.files.len()).map(|i| i.to_string()).collect();
+
+                (label(&experiences, &experience), stats)
+            })
+            .collect();
+        self.details = std::mem::take(&mut self.details)
+            .into_iter()
+            .map(|(experience, details)| {
+                let details = ExperienceDetails {
+                    company: None,
+                    ..details
+                };
+
+                (label(&experiences, &experience), details)
+            })
+            .collect();
+        self.targets = std::mem::take(&mut self.targets)
+            .into_iter()
+            .map(|(experience, targets)| (label(&experiences, &experience), targets))
+            .collect();
+        self.profiles = std::mem::take(&mut self.profiles)
+            .into_iter()
+            .map(|(experience, profile)| (label(&experiences, &experience), profile))
+            .collect();
+        // Labels past Z would sort before the ones after A otherwise.
+        self.order = ordered
+            .iter()
+            .map(|experience| label(&experiences, experience))
+            .collect();
+        self.names.clear();
+
+        for error in &mut self.errors {
+            error.experience = label(&experiences, &error.experience);
+            error.repository = label(&repositories, &error.repository);
+            // Messages can carry paths, URLs and names that the labels hide.
+            error.error = error.kind.description().to_string();
+        }
+
+        for context in &mut self.interrupted {
+            context.experience = label(&experiences, &context.experience);
+            context.repository = label(&repositories, &context.repository);
+        }
+
+        for (original, label) in experiences {
+            anonymized.experiences.insert(label, original);
+        }
+
+        for (original, label) in repositories {
+            anonymized.repositories.insert(label, original);
+        }
+
+        anonymized
+    }
+}
+
+/// What `--anonymize` replaced, as the original experience or repository of
+/// each label.
+#[derive(Serialize, Default)]
+pub struct Anonymized {
+    pub experiences: BTreeMap<String, String>,
+    pub repositories: BTreeMap<String, String>,
+}
+
+/// Labels the `i`th experience with letters like a spreadsheet column, from A
+/// to Z and then AA.
+fn letters(i: usize) -> String {
+    let mut i = i + 1;
+    let mut letters = Vec::new();
+
+    while i > 0 {
+        i -= 1;
+        letters.push(b'A' + (i % 26) as u8);
+        i /= 26;
+    }
+
+    letters.reverse();
+    String::from_utf8(letters).expect("letters should be ASCII")
+}
+
+impl Stats {
+    /// Counts a commit made at `date`.
+    fn add_commit(&mut self, date: DateTime<Utc>, commit: &CommitStats) {
+        if self.earliest_commit.is_none_or(|earliest| date < earliest) {
+            self.earliest_commit = Some(date);
+        }
+
+        if self.latest_commit.is_none_or(|latest| date > latest) {
+            self.latest_commit = Some(date);
+        }
+
+        self.languages.extend(commit.languages.iter().cloned());
+        self.add_language_lines(&commit.language_lines);
+
+        for language in &commit.languages {
+            *self.language_commits.entry(language.clone()).or_default() += 1;
+        }
+
+        self.commits += 1;
+        self.commits_without_files += commit.without_files as u64;
+        self.weekdays[date.weekday().num_days_from_monday() as usize] += 1;
+        self.punchcard[commit.weekday_hour.0][commit.weekday_hour.1] += 1;
+        self.lines += commit.lines;
+        self.assets += commit.assets;
+
+        let year = self.years.entry(date.year()).or_default();
+
+        year.commits += 1;
+        year.lines += commit.lines;
+        self.days.insert(date.date_naive());
+        self.add_extensions(&commit.extensions);
+        self.files.extend(commit.files.iter().cloned());
+    }
+
+    fn add_language_lines(&mut self, language_lines: &BTreeMap<String, u64>) {
+        for (language, lines) in language_lines {
+            *self.language_lines.entry(language.clone()).or_default() += lines;
+        }
+    }
+
+    fn add_extensions(&mut self, extensions: &BTreeMap<String, ExtensionStats>) {
+        for (extension, other_extension) in extensions {
+            let extension = self.extensions.entry(extension.clone()).or_default();
+
+            extension.files += other_extension.files;
+            extension.lines += other_extension.lines;
+        }
+    }
+
+    /// Adds the stats of `other` to these.
+    pub fn merge(&mut self, other: &Stats) {
+        self.earliest_commit = self
+            .earliest_commit
+            .into_iter()
+            .chain(other.earliest_commit)
+            .min();
+        self.latest_commit = self.latest_commit.max(other.latest_commit);
+        self.languages.extend(other.languages.iter().cloned());
+        self.add_language_lines(&other.language_lines);
+
+        for (language, commits) in &other.language_commits {
+            *self.language_commits.entry(language.clone()).or_default() += commits;
+        }
+
+        self.commits += other.commits;
+        self.commits_without_files += other.commits_without_files;
+        self.total_commits += other.total_commits;
+        self.lines += other.lines;
+        self.reviews += other.reviews;
+        self.releases += other.releases;
+        self.branches_created += other.branches_created;
+        self.branches_deleted += other.branches_deleted;
+        self.assets += other.assets;
+        self.owned_repositories += other.owned_repositories;
+        self.external_repositories += other.external_repositories;
+
+        for (weekday, commits) in other.weekdays.iter().enumerate() {
+            self.weekdays[weekday] += commits;
+        }
+
+        for (weekday, hours) in other.punchcard.iter().enumerate() {
+            for (hour, commits) in hours.iter().enumerate() {
+                self.punchcard[weekday][hour] += commits;
+            }
+        }
+
+        for (year, other_year) in &other.years {
+            let year = self.years.entry(*year).or_default();
+
+            year.commits += other_year.commits;
+            year.lines += other_year.lines;
+        }
+
+        self.days.extend(&other.days);
+        self.add_extensions(&other.extensions);
+        self.files.extend(other.files.iter().cloned());
+        self.repositories.extend(other.repositories.iter().cloned());
+
+        for (language, bytes) in &other.repository_languages {
+            *self
+                .repository_languages
+                .entry(language.clone())
+                .or_default() += bytes;
+        }
+
+        self.estimated |= other.estimated;
+    }
+
+    /// Scales everything counted per commit by `ratio`, to estimate the stats
+    /// of every commit from those of a sample.
+    fn extrapolate(&mut self, ratio: f64) {
+        let scale = |count: &mut u64| *count = (*count as f64 * ratio).round() as u64;
+
+        scale(&mut self.commits);
+        scale(&mut self.commits_without_files);
+        scale(&mut self.lines);
+        scale(&mut self.assets);
+        self.language_lines.values_mut().for_each(scale);
+        self.language_commits.values_mut().for_each(scale);
+        self.weekdays.iter_mut().for_each(scale);
+        self.punchcard.iter_mut().flatten().for_each(scale);
+
+        for year in self.years.values_mut() {
+            scale(&mut year.commits);
+            scale(&mut year.lines);
+        }
+
+        for extension in self.extensions.values_mut() {
+            scale(&mut extension.files);
+            scale(&mut extension.lines);
+        }
+
+        self.estimated = true;
+    }
+
+    /// The percentage of every commit that was made by the author, if any
+    /// commits were counted by anyone.
+    pub fn share(&self) -> Option<f64> {
+        (self.total_commits != 0).then(|| self.commits as f64 / self.total_commits as f64 * 100.0)
+    }
+
+    /// The value of `metric` in these stats.
+    pub fn metric(&self, metric: Metric) -> u64 {
+        match metric {
+            Metric::Commits => self.commits,
+            Metric::Lines => self.lines,
+            Metric::Reviews => self.reviews,
+            Metric::Releases => self.releases,
+            Metric::Assets => self.assets,
+        }
+    }
+}
+
+/// How long each phase of compiling an experience took, for `--profile`.
+#[derive(Clone, Copy, Default)]
+pub struct Profile {
+    /// Time spent listing commits, including checking for moved repositories.
+    pub listing: Duration,
+    /// Time spent fetching the files changed by each commit.
+    pub fetching: Duration,
+    /// Time spent counting the languages and lines of commits.
+    pub counting: Duration,
+    /// Time spent counting reviews, releases and branches.
+    pub reviews_and_releases: Duration,
+}
+
+/// The stats of a language across every experience, for `--group-by
+/// language`.
+#[derive(Serialize, Default)]
+pub struct LanguageStats<'a> {
+    /// The names of the experiences the language was used in, in the order
+    /// they're shown.
+    pub experiences: Vec<&'a str>,
+    pub commits: u64,
+    pub lines: u64,
+}
+
+pub struct CompiledStats {
+    pub experiences: HashMap<String, Stats>,
+    /// The names experiences were given to show instead of their keys.
+    pub names: HashMap<String, String>,
+    /// How each experience is described on a resume.
+    pub details: HashMap<String, ExperienceDetails>,
+    /// The goals set for each experience.
+    pub targets: HashMap<String, Targets>,
+    /// The keys of experiences to show first, in this order.
+    pub order: Vec<String>,
+    /// How long compiling each experience took, leaving out experiences that
+    /// were resumed from a checkpoint.
+    pub profiles: HashMap<String, Profile>,
+    /// The author whose contributions were counted.
+    pub author: String,
+    /// The repositories skipped with `--keep-going` because fetching them
+    /// failed.
+    pub errors: Vec<RepositoryError>,
+    /// Whether compiling was interrupted with Ctrl-C, leaving some experiences
+    /// or repositories out.
+    pub partial: bool,
+    /// The repositories that were still being fetched when compiling was
+    /// interrupted with Ctrl-C or long past `--max-duration`, whose commits
+    /// counted so far are left out.
+    pub interrupted: Vec<RepositoryContext>,
+}
+
+/// The language `name` is counted as, which is itself if it's the name of a
+/// language linguist knows, or else the extension it's an alias of or the
+/// language linguist says the extension is in.
+fn language_named(
+    name: &str,
+    extension_aliases: &BTreeMap<String, String>,
+    linguist_extensions: &Linguist,
+) -> String {
+    let extension = name.to_ascii_lowercase();
+    let extension = extension_aliases
+        .get(&extension)
+        .cloned()
+        .unwrap_or(extension);
+
+    match linguist_extensions.language(&extension) {
+        Some(language) => language.to_string(),
+        None if linguist_extensions.knows(name) => name.to_string(),
+        None => extension,
+    }
+}
+
+/// How long experiences are given to finish what they were fetching once
+/// `--max-duration` is reached, before they're stopped where they are.
+const DEADLINE_GRACE: Duration = Duration::from_secs(30);
+
+/// Whether the `--max-duration` deadline, if there is one, has passed.
+fn is_past(deadline: Option<Instant>) -> bool {
+    deadline.is_some_and(|deadline| Instant::now() >= deadline)
+}
+
+/// Whether `filename` ends with any of `exclude_extensions`, which can have
+/// multiple parts like `min.js`, ignoring case.
+fn is_excluded(filename: &str, exclude_extensions: &BTreeSet<String>) -> bool {
+    let filename = filename
+        .rsplit('/')
+        .next()
+        .unwrap_or(filename)
+        .to_ascii_lowercase();
+
+    exclude_extensions.iter().any(|extension| {
+        filename
+            .strip_suffix(extension.to_ascii_lowercase().as_str())
+            .is_some_and(|name| name.ends_with('.') && name.len() > 1)
+    })
+}
+
+/// Makes do with the commits that were listed if listing them failed partway,
+/// warning about it with `progress_bar`. Also returns whether every commit was
+/// listed.
+/// Commits are dated by `--date-field`.
+fn listed_commits(
+    result: anyhow::Result<Vec<Commit>>,
+    args: &Args,
+    path: &RepositoryPath,
+    progress_bar: &ProgressBar,
+) -> anyhow::Result<(Vec<Commit>, bool)> {
+    let (commits, complete) = match result {
+        Ok(commits) => (commits, true),
+        Err(e) => match e.downcast::<PartialCommits>() {
+            Ok(PartialCommits { commits, error }) => {
+                progress_bar.println(format!(
+                    "{} {path}, only {} commits could be listed: {error:#}",
+                    style(format!("{:>12}", "Warning")).yellow().bold(),
+                    commits.len(),
+                ));
+
+                (commits, false)
+            }
+            Err(e) => return Err(e),
+        },
+    };
+
+    Ok((
+        commits
+            .into_iter()
+            .map(|commit| match (args.date_field, commit.committer_date) {
+                (DateField::Committer, Some(committer_date)) => Commit {
+                    date: committer_date,
+                    ..commit
+                },
+                _ => commit,
+            })
+            .collect(),
+        complete,
+    ))
+}
+
+/// Warns if the repositories in `experiences` clearly need more requests than
+/// the rate limit has left. It's a rough estimate of a request for every
+/// commit and another for every page of commits, and is skipped for providers
+/// without a rate limit or if it can't be made.
+async fn check_rate_limit(
+    sources: &Sources,
+    args: &Args,
+    author: &str,
+    experiences: &BTreeMap<String, Experience>,
+) {
+    // Every account has a rate limit of its own.
+    let mut remaining = HashMap::new();
+    let mut needed: HashMap<(Provider, Option<&str>), u64> = HashMap::new();
+
+    // Collected first so the iterator isn't held across requests, which would
+    // keep the future from being sent between threads.
+    let repositories: Vec<(&Experience, &Repository)> = experiences
+        .values()
+        .flat_map(|experience| {
+            experience
+                .repositories
+                .iter()
+                .map(move |repository| (experience, repository))
+        })
+        .collect();
+
+    for (experience, repository) in repositories {
+        let account = repository
+            .account
+            .as_deref()
+            .or(experience.account.as_deref());
+        let Ok(source) = sources.get_account(repository.provider, account) else {
+            continue;
+        };
+        let key = (repository.provider, account);
+        let remaining = match remaining.get(&key) {
+            Some(remaining) => *remaining,
+            None => {
+                let requests = source.remaining_requests().await.ok().flatten();
+
+                remaining.insert(key, requests);
+                requests
+            }
+        };
+
+        if remaining.is_none() {
+            continue;
+        }
+
+        let author = match args.author_match {
+            AuthorMatch::Login => Some(author_of(repository.author.as_deref(), account, author))
+                .filter(|author| *author != ALL_AUTHORS),
+            _ => None,
+        };
+        let Ok(Some(commits)) = source
+            .estimate_commits(&repository.path, author, args.since(), args.until())
+            .await
+        else {
+            continue;
+        };
+        let requests = if args.commits_only || args.api == Api::Graphql {
+            commits.div_ceil(100)
+        } else {
+            commits + commits.div_ceil(100)
+        };
+
+        *needed.entry(key).or_default() += requests;
+    }
+
+    for (key @ (provider, account), needed) in needed {
+        if let Some(Some(remaining)) = remaining.get(&key)
+            && needed > *remaining
+        {
+            let provider = match account {
+                Some(account) => format!("{provider} as {account}"),
+                None => provider.to_string(),
+            };
+
+            eprintln!(
+                "{} about {needed} requests to {provider} are needed but only {remaining} are left before the rate limit resets, consider --commits-only, --api graphql or --incremental",
+                style(format!("{:>12}", "Warning")).yellow().bold(),
+            );
+        }
+    }
+}
+
+/// Who to count the commits of in a repository: its own `author`, or else the
+/// account it's fetched with, or else `author`, unless that's everyone.
+fn author_of<'a>(
+    repository_author: Option<&'a str>,
+    account: Option<&'a str>,
+    author: &'a str,
+) -> &'a str {
+    repository_author
+        .or(account.filter(|_| author != ALL_AUTHORS))
+        .unwrap_or(author)
+}
+
+/// The SHAs of the commits reached by following first parents from the newest
+/// of `commits`, which is taken to be where the default branch is.
+fn first_parent_history(commits: &[Commit]) -> HashSet<String> {
+    let first_parents: HashMap<&str, Option<&str>> = commits
+        .iter()
+        .map(|commit| (commit.sha.as_str(), commit.first_parent.as_deref()))
+        .collect();
+    let mut history = HashSet::new();
+    let mut sha = commits
+        .iter()
+        .max_by_key(|commit| commit.date)
+        .map(|commit| commit.sha.as_str());
+
+    // The history ends at a parent that wasn't listed, such as one older than
+    // `--since`.
+    while let Some(current) = sha
+        && let Some(first_parent) = first_parents.get(current)
+        && history.insert(current.to_string())
+    {
+        sha = *first_parent;
+    }
+
+    history
+}
+
+/// Whether `commit` was authored by `author`, compared as `author_match` says.
+/// Emails and names are also compared to `git_identities`.
+fn is_by(
+    commit: &Commit,
+    author: &str,
+    git_identities: &BTreeSet<String>,
+    author_match: AuthorMatch,
+) -> bool {
+    if author == ALL_AUTHORS {
+        return true;
+    }
+
+    let matches = |field: &Option<String>| {
+        field.as_ref().is_some_and(|field| {
+            field.eq_ignore_ascii_case(author)
+                || git_identities
+                    .iter()
+                    .any(|identity| field.eq_ignore_ascii_case(identity))
+        })
+    };
+
+    match author_match {
+        AuthorMatch::Login => commit
+            .author_login
+            .as_ref()
+            .is_some_and(|login| login.eq_ignore_ascii_case(author)),
+        AuthorMatch::Email => matches(&commit.author_email),
+        AuthorMatch::Name => matches(&commit.author_name),
+        AuthorMatch::Any => {
+            matches(&commit.author_login)
+                || matches(&commit.author_email)
+                || matches(&commit.author_name)
+        }
+    }
+}
+
+/// Whether `login` is the login of `author`, which every login is if `author`
+/// is [`ALL_AUTHORS`].
+fn is_author(login: &str, author: &str) -> bool {
+    author == ALL_AUTHORS || login.eq_ignore_ascii_case(author)
+}
+
+/// The first 6 characters of `sha` to show it by, or all of it if it's
+/// shorter, like the SHAs some providers abbreviate.
+fn short_sha(sha: &str) -> &str {
+    sha.get(..6).unwrap_or(sha)
+}
+
+/// Builds the progress bar styles for experiences and for everything else,
+/// from the arguments, the `[progress]` config section, or the defaults, in
+/// that order of precedence.
+pub fn progress_styles(
+    args: &Args,
+    progress: &ProgressConfig,
+) -> anyhow::Result<(ProgressStyle, ProgressStyle)> {
+    let chars = args
+        .progress_chars
+        .as_deref()
+        .or(progress.chars.as_deref())
+        .unwrap_or("=> ");
+    let char_widths: Vec<usize> = chars
+        .chars()
+        .map(|char| console::measure_text_width(&char.to_string()))
+        .collect();
+
+    if char_widths.len() < 2 {
+        bail!("progress chars {chars:?} should have at least 2 characters");
+    }
+
+    if char_widths.iter().any(|width| *width != char_widths[0]) {
+        bail!("progress chars {chars:?} should all be the same width");
+    }
+
+    let style = |template: &str| -> anyhow::Result<ProgressStyle> {
+        Ok(ProgressStyle::with_template(template)
+            .with_context(|| format!("invalid progress template {template:?}"))?
+            .progress_chars(chars))
+    };
+
+    Ok((
+        style(
+            args.progress_template
+                .as_deref()
+                .or(progress.template.as_deref())
+                .unwrap_or("{prefix:>12.cyan.bold} [{bar:25}] {pos}/{len}"),
+        )?,
+        style(
+            args.progress_task_template
+                .as_deref()
+                .or(progress.task_template.as_deref())
+                .unwrap_or("{prefix:>12.cyan.bold} [{bar:25}] {pos}/{len}: {wide_msg}"),
+        )?,
+    ))
+}
+
+/// The terminal title showing how far along a progress bar at `position` of
+/// `length` is, for `--set-title`.
+pub fn progress_title(position: u64, length: Option<u64>) -> String {
+    format!(
+        "resume_stats: {:.0}%",
+        position as f64 / length.unwrap_or(1).max(1) as f64 * 100.0,
+    )
+}
+
+/// Fetches the commits of every experience's repositories and compiles them
+/// into stats for each experience, showing progress along the way.
+///
+/// Pressing Ctrl-C stops compiling and returns whatever was compiled so far.
+pub async fn compile_stats(
+    sources: Sources,
+    args: Arc<Args>,
+    needed_stats: NeededStats,
+) -> anyhow::Result<CompiledStats> {
+    compile(sources, args, needed_stats, None).await
+}
+
+/// Like [`compile_stats`], but yields each commit and repository as soon as
+/// it's counted, so the stats can be shown as they're compiled. The stream
+/// ends with [`StatsEvent::Finished`], or an error if compiling failed.
+pub fn compile_stats_stream(
+    sources: Sources,
+    args: Arc<Args>,
+    needed_stats: NeededStats,
+) -> impl Stream<Item = anyhow::Result<StatsEvent>> {
+    let (events, receiver) = mpsc::unbounded_channel();
+    let compiled = tokio::spawn(compile(sources, args, needed_stats, Some(events)));
+
+    // Every sender is dropped once compiling finishes, which ends the events.
+    stream::unfold(
+        (receiver, Some(compiled)),
+        |(mut receiver, compiled)| async move {
+            if let Some(event) = receiver.recv().await {
+                return Some((Ok(event), (receiver, compiled)));
+            }
+
+            let result = match compiled?.await {
+                Ok(result) => {
+                    result.map(|compiled_stats| StatsEvent::Finished(Box::new(compiled_stats)))
+                }
+                Err(e) => Err(e.into()),
+            };
+
+            Some((result, (receiver, None)))
+        },
+    )
+}
+
+async fn compile(
+    sources: Sources,
+    args: Arc<Args>,
+    needed_stats: NeededStats,
+    events: Option<mpsc::UnboundedSender<StatsEvent>>,
+) -> anyhow::Result<CompiledStats> {
+    let author = match needed_stats.author {
+        Some(author) => author,
+        None => sources.authenticated_login().await?.to_string(),
+    };
+    let language = args.language.as_ref().map(|language| {
+        language_named(
+            language,
+            &needed_stats.extension_aliases,
+            &needed_stats.linguist_extensions,
+        )
+    });
+    let (needed_languages, line_count_languages) = match &language {
+        // Only the language is counted, and only its files add lines.
+        Some(language) => (
+            BTreeSet::from([language.clone()]),
+            BTreeSet::from([language.clone()]),
+        ),
+        None => (needed_stats.languages, needed_stats.line_count_languages),
+    };
+    let discovers_languages = matches!(args.command, Some(Command::Languages));
+    let needed_experience = needed_stats.experience;
+
+    if (args.commits_only || args.api == Api::Graphql)
+        && needed_experience.values().any(|experience| {
+            experience
+                .repositories
+                .iter()
+                .any(|repository| repository.path_prefix.is_some())
+        })
+    {
+        bail!(
+            "path_prefix needs the files of each commit, which aren't fetched with --commits-only or --api graphql"
+        );
+    }
+
+    if args.languages_from_linguist_api
+        && needed_experience.values().any(|experience| {
+            experience
+                .repositories
+                .iter()
+                .any(|repository| repository.provider != Provider::GitHub)
+        })
+    {
+        bail!("--languages-from-linguist-api only works with repositories on GitHub");
+    }
+
+    if (args.commits_only || args.api == Api::Graphql) && language.is_some() {
+        bail!(
+            "--language needs the files of each commit, which aren't fetched with --commits-only or --api graphql"
+        );
+    }
+
+    let order = needed_stats.order;
+    let names = needed_experience
+        .iter()
+        .filter_map(|(experience, Experience { name, .. })| {
+            Some((experience.clone(), name.clone()?))
+        })
+        .collect();
+    let details = needed_experience
+        .iter()
+        .map(|(experience, Experience { details, .. })| (experience.clone(), details.clone()))
+        .collect();
+    let targets = needed_experience
+        .iter()
+        .map(|(experience, Experience { targets, .. })| (experience.clone(), *targets))
+        .collect();
+    let (experience_progress_style, progress_style) =
+        progress_styles(&args, &needed_stats.progress)?;
+    let settings_hash = settings_hash(
+        &args,
+        &author,
+        &needed_languages,
+        &needed_stats.exclude_extensions,
+        &line_count_languages,
+        &needed_stats.linguist_extensions,
+        &needed_stats.extension_aliases,
+    )?;
+    let cache = if args.refresh_cache {
+        Some(Mutex::new(Cache::default()))
+    } else if args.incremental {
+        Some(Mutex::new(Cache::read(&args.cache).await?))
+    } else {
+        None
+    };
+    let mut checkpoint = match &args.checkpoint {
+        Some(checkpoint) if args.resume => Some(Checkpoint::read(checkpoint).await?),
+        Some(_) => Some(Checkpoint::default()),
+        None => None,
+    };
+    let mut experience_hashes = HashMap::new();
+    let commit_dump = match &args.dump_commits {
+        Some(path) => Some(Mutex::new(BufWriter::new(
+            tokio::fs::File::create(path)
+                .await
+                .with_context(|| format!("failed to create {}", path.display()))?,
+        ))),
+        None => None,
+    };
+    let errors: Arc<Mutex<Vec<RepositoryError>>> = Arc::new(Mutex::new(Vec::new()));
+    let profiles: Arc<Mutex<HashMap<String, Profile>>> = Arc::new(Mutex::new(HashMap::new()));
+
+    check_rate_limit(&sources, &args, &author, &needed_experience).await;
+
+    let experience_permits = Arc::new(Semaphore::new(args.concurrency.get()));
+    // Bounds the commits fetched at once across every experience, however
+    // `--concurrency` and `--commit-concurrency` multiply.
+    let request_permits = Semaphore::new(args.max_parallel_requests.get());
+    let deadline = args
+        .max_duration
+        .map(|max_duration| Instant::now() + max_duration);
+    let multi_progress = MultiProgress::new();
+    let shared = Arc::new(Shared {
+        args: args.clone(),
+        sources,
+        author,
+        language,
+        needed_languages,
+        exclude_extensions: needed_stats.exclude_extensions,
+        line_count_languages,
+        git_identities: needed_stats.git_identities,
+        linguist_extensions: needed_stats.linguist_extensions,
+        extension_aliases: needed_stats.extension_aliases,
+        discovers_languages,
+        settings_hash,
+        cache,
+        commit_dump,
+        events,
+        stats: Mutex::new(HashMap::new()),
+        fetching: Mutex::new(HashMap::new()),
+        multi_progress: multi_progress.clone(),
+        progress_style,
+        request_permits,
+        deadline,
+    });
+    // Each experience is returned with whether `--max-duration` stopped it
+    // before it was done.
+    let mut join_set: JoinSet<Result<(String, bool), anyhow::Error>> = JoinSet::new();
+    let experience_progress_bar = multi_progress.add(
+        ProgressBar::new(needed_experience.len() as u64)
+            .with_style(experience_progress_style)
+            .with_prefix("Compiling"),
+    );
+
+    experience_progress_bar.tick();
+
+    let set_title = |progress_bar: &ProgressBar| {
+        if args.set_title {
+            Term::stderr().set_title(progress_title(
+                progress_bar.position(),
+                progress_bar.length(),
+            ));
+        }
+    };
+
+    set_title(&experience_progress_bar);
+
+    for (experience, experience_config) in needed_experience {
+        if checkpoint.is_some() {
+            let hash = experience_hash(&args, &shared.settings_hash, &experience_config)?;
+
+            if let Some(checkpointed) = checkpoint
+                .as_ref()
+                .and_then(|checkpoint| checkpoint.get(&experience, &hash))
+            {
+                if let Some(checkpointed) = checkpointed {
+                    shared
+                        .stats
+                        .lock()
+                        .await
+                        .insert(experience.clone(), checkpointed.clone());
+                }
+
+                experience_progress_bar.println(format!(
+                    "{} {experience} from the checkpoint",
+                    style(format!("{:>12}", "Resumed")).green().bold(),
+                ));
+                experience_progress_bar.inc(1);
+                set_title(&experience_progress_bar);
+
+                continue;
+            }
+
+            experience_hashes.insert(experience.clone(), hash);
+        }
+
+        let Experience {
+            repositories,
+            account: experience_account,
+            ..
+        } = experience_config;
+        let shared = shared.clone();
+        let errors = errors.clone();
+        let profiles = profiles.clone();
+        let experience_permits = experience_permits.clone();
+
+        join_set.spawn(async move {
+            let _permit = experience_permits
+                .acquire()
+                .await
+                .expect("the experience semaphore should never be closed");
+            let repository_progress_bar = shared.multi_progress.add(
+                ProgressBar::new(repositories.len() as u64)
+                    .with_style(shared.progress_style.clone())
+                    .with_prefix("Fetching"),
+            );
+
+            repository_progress_bar.tick();
+
+            let mut profile = Profile::default();
+            // Whether `--max-duration` was reached before every repository was
+            // counted.
+            let mut stopped = false;
+
+            for repository in repositories {
+                if is_past(shared.deadline) {
+                    stopped = true;
+
+                    break;
+                }
+
+                repository_progress_bar.set_message(format!("{} ({experience})", repository.path));
+
+                let configured_path = repository
+                    .upstream
+                    .as_ref()
+                    .unwrap_or(&repository.path)
+                    .to_string();
+                let forked = repository.upstream.is_some();
+
+                shared
+                    .fetching
+                    .lock()
+                    .await
+                    .insert(experience.clone(), configured_path.clone());
+
+                let result = compile_repository(
+                    &shared,
+                    &experience,
+                    repository,
+                    experience_account.as_deref(),
+                    &repository_progress_bar,
+                    &mut profile,
+                    &mut stopped,
+                )
+                .await;
+
+                shared.fetching.lock().await.remove(&experience);
+                repository_progress_bar.inc(1);
+
+                match result {
+                    Ok(None) => (),
+                    Ok(Some((path, stats))) => {
+                        shared
+                            .stats
+                            .lock()
+                            .await
+                            .entry(experience.clone())
+                            .or_default()
+                            .merge(&stats);
+                        repository_progress_bar.println(if forked {
+                            format!(
+                                "{} {configured_path} (from {path})",
+                                style(format!("{:>12}", "Fetched")).green().bold()
+                            )
+                        } else {
+                            format!(
+                                "{} {path}",
+                                style(format!("{:>12}", "Fetched")).green().bold()
+                            )
+                        });
+                    }
+                    Err(e) if shared.args.keep_going => {
+                        repository_progress_bar.println(format!(
+                            "{} {configured_path}",
+                            style(format!("{:>12}", "Failed")).red().bold()
+                        ));
+                        errors.lock().await.push(RepositoryError {
+                            experience: experience.clone(),
+                            repository: configured_path,
+                            kind: source::ErrorKind::of(&e),
+                            error: format!("{e:#}"),
+                        });
+                    }
+                    Err(e) => {
+                        return Err(e.context(RepositoryContext {
+                            experience: experience.clone(),
+                            repository: configured_path,
+                        }));
+                    }
+                }
+            }
+
+            repository_progress_bar.finish_and_clear();
+            profiles.lock().await.insert(experience.clone(), profile);
+
+            Ok((experience, stopped))
+        });
+    }
+
+    let ctrl_c = tokio::signal::ctrl_c();
+    // Experiences still going long after `--max-duration` are stopped where
+    // they are.
+    let hard_stop = async {
+        match deadline {
+            Some(deadline) => tokio::time::sleep_until((deadline + DEADLINE_GRACE).into()).await,
+            None => std::future::pending().await,
+        }
+    };
+    let mut partial = false;
+
+    tokio::pin!(ctrl_c);
+    tokio::pin!(hard_stop);
+
+    loop {
+        tokio::select! {
+            join_result = join_set.join_next() => match join_result {
+                Some(join_result) => {
+                    let (experience, stopped) = join_result??;
+
+                    partial |= stopped;
+
+                    // Experiences with failed repositories are left out so
+                    // they're fetched again when resuming.
+                    let failed = errors
+                        .lock()
+                        .await
+                        .iter()
+                        .any(|error| error.experience == experience);
+
+                    if let Some((checkpoint, checkpoint_path)) =
+                        checkpoint.as_mut().zip(args.checkpoint.as_ref())
+                        && !failed
+                        && !stopped
+                    {
+                        let experience_stats = shared.stats.lock().await.get(&experience).cloned();
+                        let hash = experience_hashes
+                            .remove(&experience)
+                            .expect("every compiled experience should have a hash");
+
+                        checkpoint.insert(experience, hash, experience_stats);
+                        checkpoint.write(checkpoint_path).await?;
+                    }
+
+                    experience_progress_bar.inc(1);
+                    set_title(&experience_progress_bar);
+                }
+                None => break,
+            },
+            ctrl_c_result = &mut ctrl_c => {
+                ctrl_c_result?;
+                join_set.shutdown().await;
+
+                partial = true;
+
+                break;
+            }
+            () = &mut hard_stop => {
+                join_set.shutdown().await;
+
+                partial = true;
+
+                break;
+            }
+        }
+    }
+
+    experience_progress_bar.finish_and_clear();
+    multi_progress.clear()?;
+
+    let shared =
+        Arc::into_inner(shared).expect("every task holding the shared state should have finished");
+
+    if let Some(cache) = shared.cache {
+        cache.into_inner().write(&args.cache).await?;
+    }
+
+    if let Some((commit_dump, path)) = shared.commit_dump.zip(args.dump_commits.as_ref()) {
+        commit_dump
+            .into_inner()
+            .flush()
+            .await
+            .with_context(|| format!("failed to write {}", path.display()))?;
+    }
+
+    let mut experiences = shared.stats.into_inner();
+    // Only experiences that were stopped partway through are still fetching.
+    let mut interrupted: Vec<RepositoryContext> = shared
+        .fetching
+        .into_inner()
+        .into_iter()
+        .map(|(experience, repository)| RepositoryContext {
+            experience,
+            repository,
+        })
+        .collect();
+
+    interrupted.sort_unstable_by(|a, b| {
+        (&a.experience, &a.repository).cmp(&(&b.experience, &b.repository))
+    });
+
+    if let Some(min_language_lines) = args.min_language_lines {
+        for stats in experiences.values_mut() {
+            let language_lines = &stats.language_lines;
+
+            stats.languages.retain(|language| {
+                language_lines
+                    .get(language)
+                    .is_some_and(|lines| *lines >= min_language_lines)
+            });
+        }
+    }
+
+    if args.languages_from_linguist_api {
+        for stats in experiences.values_mut() {
+            stats.languages = stats.repository_languages.keys().cloned().collect();
+        }
+    }
+
+    let errors = Arc::into_inner(errors)
+        .expect("every task holding errors should have finished")
+        .into_inner();
+
+    // A checkpoint is only needed until every experience has been compiled.
+    if let Some(checkpoint) = &args.checkpoint
+        && !partial
+        && errors.is_empty()
+    {
+        match tokio::fs::remove_file(checkpoint).await {
+            Err(e) if e.kind() != ErrorKind::NotFound => {
+                return Err(e)
+                    .with_context(|| format!("failed to remove {}", checkpoint.display()));
+            }
+            _ => (),
+        }
+    }
+
+    Ok(CompiledStats {
+        experiences,
+        errors,
+        names,
+        details,
+        targets,
+        order,
+        profiles: Arc::into_inner(profiles)
+            .expect("every task holding profiles should have finished")
+            .into_inner(),
+        author: shared.author,
+        partial,
+        interrupted,
+    })
+}
+
+/// What every repository is compiled with, shared by every experience.
+struct Shared {
+    args: Arc<Args>,
+    sources: Sources,
+    author: String,
+    language: Option<String>,
+    needed_languages: BTreeSet<String>,
+    exclude_extensions: BTreeSet<String>,
+    line_count_languages: BTreeSet<String>,
+    git_identities: BTreeSet<String>,
+    linguist_extensions: Linguist,
+    extension_aliases: BTreeMap<String, String>,
+    discovers_languages: bool,
+    settings_hash: String,
+    cache: Option<Mutex<Cache>>,
+    commit_dump: Option<Mutex<BufWriter<tokio::fs::File>>>,
+    events: Option<mpsc::UnboundedSender<StatsEvent>>,
+    stats: Mutex<HashMap<String, Stats>>,
+    /// The repository each experience is fetching, whose stats are only
+    /// merged into `stats` once it's done.
+    fetching: Mutex<HashMap<String, String>>,
+    multi_progress: MultiProgress,
+    progress_style: ProgressStyle,
+    request_permits: Semaphore,
+    deadline: Option<Instant>,
+}
+
+/// Counts the commits of one of `experience`'s repositories, returning the
+/// path they were fetched from along with what they add to the experience's
+/// stats, or `None` if the repository was skipped.
+async fn compile_repository(
+    shared: &Shared,
+    experience: &str,
+    repository: Repository,
+    experience_account: Option<&str>,
+    repository_progress_bar: &ProgressBar,
+    profile: &mut Profile,
+    stopped: &mut bool,
+) -> anyhow::Result<Option<(RepositoryPath, Stats)>> {
+    let Shared {
+        args,
+        sources,
+        author,
+        language,
+        needed_languages,
+        exclude_extensions,
+        line_count_languages,
+        git_identities,
+        linguist_extensions,
+        extension_aliases,
+        discovers_languages,
+        settings_hash,
+        cache,
+        commit_dump,
+        events,
+        multi_progress,
+        progress_style,
+        request_permits,
+        deadline,
+        ..
+    } = shared;
+    let Repository {
+        path,
+        provider,
+        upstream,
+        author: repository_author,
+        path_prefix,
+        account,
+    } = repository;
+    let account = account.as_deref().or(experience_account);
+    let author = author_of(repository_author.as_deref(), account, author);
+    // Clones don't know which account made a commit, so they're matched by
+    // emails and names, including the git config's, even with `login`.
+    let author_match = match (provider, args.author_match) {
+        (Provider::Local, AuthorMatch::Login) => AuthorMatch::Any,
+        (_, author_match) => author_match,
+    };
+    let configured_path = upstream.as_ref().unwrap_or(&path).to_string();
+    let source = sources.get_account(provider, account)?;
+    // What the repository adds to the experience, which is only added to it
+    // once the repository has been compiled without failing.
+    let mut stats = Stats::default();
+    let listing_started = Instant::now();
+    let metadata_key = format!("{provider}:{path}");
+    let mut metadata = match &cache {
+        Some(cache) => cache
+            .lock()
+            .await
+            .metadata(
+                &metadata_key,
+                chrono::Duration::hours(args.metadata_ttl as i64),
+            )
+            .cloned(),
+        None => None,
+    }
+    .unwrap_or_default();
+    let moved_path = match &metadata.current_path {
+        Some(current_path) if *current_path != path.to_string() => {
+            Some(RepositoryPath::try_from(current_path.clone())?)
+        }
+        Some(_) => None,
+        None => {
+            let moved_path = source.moved_to(&path).await?;
+
+            metadata.current_path = Some(moved_path.as_ref().unwrap_or(&path).to_string());
+
+            moved_path
+        }
+    };
+    let path = match moved_path {
+        Some(moved_path) => {
+            repository_progress_bar.println(format!(
+                "{} {path} to {moved_path}, consider updating the config",
+                style(format!("{:>12}", "Moved")).yellow().bold(),
+            ));
+
+            moved_path
+        }
+        None => path,
+    };
+
+    let private = match (args.visibility, metadata.private) {
+        (Visibility::All, _) => None,
+        (_, Some(private)) => Some(private),
+        (_, None) => {
+            let private = source.is_private(&path).await?;
+
+            metadata.private = Some(private);
+
+            Some(private)
+        }
+    };
+
+    if let Some(cache) = &cache {
+        cache.lock().await.insert_metadata(metadata_key, metadata);
+    }
+
+    if private.is_some_and(|private| private != (args.visibility == Visibility::Private)) {
+        repository_progress_bar.println(format!(
+            "{} {path}, it's not {}",
+            style(format!("{:>12}", "Skipped")).yellow().bold(),
+            if args.visibility == Visibility::Private {
+                "private"
+            } else {
+                "public"
+            },
+        ));
+
+        return Ok(None);
+    }
+
+    // Commits counted for a different author or directory can't be
+    // reused.
+    let mut cache_key = match repository_author.as_deref().or(account) {
+        Some(repository_author) => format!("{provider}:{path}:{repository_author}"),
+        None => format!("{provider}:{path}"),
+    };
+
+    if let Some(path_prefix) = &path_prefix {
+        cache_key += &format!(":{path_prefix}");
+    }
+
+    for identity in git_identities.iter() {
+        cache_key += &format!(":{identity}");
+    }
+
+    let cached = match &cache {
+        Some(cache) => cache.lock().await.get(&cache_key, settings_hash).cloned(),
+        None => None,
+    };
+    // Commits listed since the latest cached commit include it, so
+    // nothing new between fetches is missed. A partly counted
+    // repository can still have older commits left to count.
+    let since = cached
+        .as_ref()
+        .filter(|cached| !cached.partial)
+        .and_then(|cached| cached.stats.latest_commit)
+        .or(args.since());
+    // Only the provider can tell which account made a commit, so
+    // it's left to filter by login.
+    let listing_author = match author_match {
+        AuthorMatch::Login if !args.first_parent && author != ALL_AUTHORS => Some(author),
+        _ => None,
+    };
+    let listing = source
+        .list_commits(&path, listing_author, since, args.until())
+        .await;
+
+    if listing.as_ref().is_err_and(|e| e.is::<EmptyRepository>()) {
+        repository_progress_bar.println(format!(
+            "{} {path}, it doesn't have any commits yet",
+            style(format!("{:>12}", "Skipped")).yellow().bold(),
+        ));
+
+        return Ok(None);
+    }
+
+    let (mut commits, mut complete) = match listing_author {
+        Some(_) => listed_commits(listing, args, &path, repository_progress_bar)?,
+        None => {
+            let (commits, complete) =
+                listed_commits(listing, args, &path, repository_progress_bar)?;
+
+            let history = args.first_parent.then(|| first_parent_history(&commits));
+
+            (
+                commits
+                    .into_iter()
+                    .filter(|commit| {
+                        let reason = if !is_by(commit, author, git_identities, author_match) {
+                            Some("it was authored by someone else")
+                        } else if history
+                            .as_ref()
+                            .is_some_and(|history| !history.contains(&commit.sha))
+                        {
+                            Some("it isn't in the first-parent history")
+                        } else {
+                            None
+                        };
+
+                        if let Some(reason) = reason
+                            && args.explain
+                        {
+                            repository_progress_bar.println(format!(
+                                "{} {} ({path}), {reason}",
+                                style(format!("{:>12}", "Skipped")).yellow().bold(),
+                                short_sha(&commit.sha),
+                            ));
+                        }
+
+                        reason.is_none()
+                    })
+                    .collect::<Vec<Commit>>(),
+                complete,
+            )
+        }
+    };
+
+    // Providers filter by whichever date they like, so commits are
+    // checked again by the one `--date-field` chose.
+    commits.retain(|commit| {
+        let contained = args.contains(commit.date);
+
+        if !contained && args.explain {
+            repository_progress_bar.println(format!(
+                "{} {} ({path}), its {} date is outside --since and --until",
+                style(format!("{:>12}", "Skipped")).yellow().bold(),
+                short_sha(&commit.sha),
+                args.date_field.name(),
+            ));
+        }
+
+        contained
+    });
+
+    let present_files = if args.only_present_files {
+        Some(source.list_present_files(&path).await?)
+    } else {
+        None
+    };
+
+    profile.listing += listing_started.elapsed();
+
+    let mut repository_stats = Stats::default();
+    let mut shas = HashSet::new();
+    let mut latest_sha = None;
+
+    if let Some(cached) = cached {
+        // A partial listing is missing older commits, which the
+        // latest cached one may be.
+        if complete
+            && cached
+                .latest_sha
+                .as_ref()
+                .is_some_and(|latest_sha| !commits.iter().any(|commit| commit.sha == *latest_sha))
+        {
+            bail!(
+                "the history of {path} was rewritten since it was cached, run again with --refresh-cache"
+            );
+        }
+
+        commits.retain(|commit| !cached.shas.contains(&commit.sha));
+        stats.merge(&cached.stats);
+
+        repository_stats = cached.stats;
+        shas = cached.shas;
+        latest_sha = cached.latest_sha;
+    }
+
+    // The commits saved when the cache is flushed, which can only be
+    // those already counted.
+    let mut flushed_shas = shas.clone();
+
+    if let Some(latest_commit) = commits.iter().max_by_key(|commit| commit.date) {
+        // A new commit can't be older than a cached one unless it
+        // was pushed later, in which case the cached one is kept.
+        if repository_stats
+            .latest_commit
+            .is_none_or(|latest| latest_commit.date >= latest)
+        {
+            latest_sha = Some(latest_commit.sha.clone());
+        }
+    }
+
+    if args.with_share {
+        // The author's commits may have been filtered by the
+        // provider, so every commit is listed again.
+        let listing_started = Instant::now();
+        let (total_commits, total_complete) = listed_commits(
+            source.list_commits(&path, None, since, args.until()).await,
+            args,
+            &path,
+            repository_progress_bar,
+        )?;
+
+        profile.listing += listing_started.elapsed();
+
+        let history = args
+            .first_parent
+            .then(|| first_parent_history(&total_commits));
+        let total_commits: Vec<Commit> = total_commits
+            .into_iter()
+            .filter(|commit| {
+                args.counts_weekday(commit.date.weekday())
+                    && args.contains(commit.date)
+                    && !shas.contains(&commit.sha)
+                    && history
+                        .as_ref()
+                        .is_none_or(|history| history.contains(&commit.sha))
+            })
+            .collect();
+
+        repository_stats.total_commits += total_commits.len() as u64;
+        stats.total_commits += total_commits.len() as u64;
+        shas.extend(total_commits.into_iter().map(|commit| commit.sha));
+        complete &= total_complete;
+    }
+
+    shas.extend(commits.iter().map(|commit| commit.sha.clone()));
+
+    if args.merged_prs_only {
+        let checking_started = Instant::now();
+        let checked_commits: Vec<_> = stream::iter(commits)
+            .map(|commit| {
+                let path = &path;
+                let request_permits = &request_permits;
+
+                async move {
+                    let _permit = request_permits
+                        .acquire()
+                        .await
+                        .expect("the request semaphore should never be closed");
+                    let merged = source.is_in_merged_pull_request(path, &commit.sha).await;
+
+                    (commit, merged)
+                }
+            })
+            .buffered(args.commit_concurrency.get())
+            .collect()
+            .await;
+
+        commits = Vec::new();
+
+        for (commit, merged) in checked_commits {
+            if merged? {
+                commits.push(commit);
+            } else if args.verbose {
+                repository_progress_bar.println(format!(
+                    "{} {} ({path}), it isn't part of a merged pull request",
+                    style(format!("{:>12}", "Skipped")).yellow().bold(),
+                    short_sha(&commit.sha),
+                ));
+            }
+        }
+
+        profile.fetching += checking_started.elapsed();
+    }
+
+    // With `--sample`, only commits spread evenly across the
+    // history are fetched, and their stats are scaled up to the
+    // rest once they're counted.
+    let listed = commits.len();
+    let sample_ratio = match args.sample {
+        Some(sample) if listed > sample.get() => {
+            let sample = sample.get();
+            let sampled: HashSet<_> = (0..sample).map(|i| i * listed / sample).collect();
+            let mut i = 0;
+
+            commits.retain(|_| {
+                i += 1;
+                sampled.contains(&(i - 1))
+            });
+
+            // Estimates would be reused as if they were counted.
+            complete = false;
+
+            Some(listed as f64 / sample as f64)
+        }
+        _ => None,
+    };
+    let mut sample_stats = Stats::default();
+    let cached_without_files = repository_stats.commits_without_files;
+    let shas_before_counting = flushed_shas.len();
+    let commits_progress_bar = multi_progress.add(
+        ProgressBar::new(commits.len() as u64)
+            .with_style(progress_style.clone())
+            .with_prefix("Fetching"),
+    );
+
+    // Commits are fetched up to `--commit-concurrency` at a time
+    // but still counted one at a time in order.
+    let mut fetched_commits = stream::iter(commits)
+        .map(|commit| {
+            let fetches = args.counts_weekday(commit.date.weekday())
+                && !(commit.merge && args.skip_merges)
+                && !(args.commits_only || commit.merge && args.merge_lines == MergeLines::Skip);
+            let path = &path;
+            let request_permits = &request_permits;
+
+            async move {
+                if !fetches {
+                    return (commit, None);
+                }
+
+                let _permit = request_permits
+                    .acquire()
+                    .await
+                    .expect("the request semaphore should never be closed");
+                let files = source.get_changed_files(path, &commit.sha).await;
+
+                (commit, Some(files))
+            }
+        })
+        .buffered(args.commit_concurrency.get());
+
+    loop {
+        // Commits already being fetched are dropped along with the
+        // stream, and the repository isn't cached without them.
+        if is_past(*deadline) {
+            *stopped = true;
+            complete = false;
+
+            break;
+        }
+
+        // The total commits of `--with-share` are counted before any
+        // commit is, so resuming would count them again, and
+        // estimates can't be resumed at all.
+        if let Some(cache) = &cache
+            && !args.with_share
+            && sample_ratio.is_none()
+            && flushed_shas.len() > shas_before_counting
+            && (flushed_shas.len() - shas_before_counting) % args.flush_every == 0
+        {
+            let mut cache = cache.lock().await;
+
+            cache.insert(
+                cache_key.clone(),
+                CachedRepository {
+                    settings_hash: settings_hash.to_string(),
+                    shas: flushed_shas.clone(),
+                    latest_sha: latest_sha.clone(),
+                    stats: repository_stats.clone(),
+                    partial: true,
+                },
+            );
+            cache.write(&args.cache).await?;
+        }
+
+        let fetching_started = Instant::now();
+        let Some((
+            Commit {
+                sha,
+                date,
+                merge,
+                additions,
+                deletions,
+                ..
+            },
+            fetched_files,
+        )) = fetched_commits.next().await
+        else {
+            break;
+        };
+
+        profile.fetching += fetching_started.elapsed();
+        commits_progress_bar.set_message(format!("{} ({path})", short_sha(&sha)));
+        flushed_shas.insert(sha.clone());
+
+        if !args.counts_weekday(date.weekday()) {
+            if args.verbose {
+                commits_progress_bar.println(format!(
+                    "{} {} ({path}), it was made on a {}",
+                    style(format!("{:>12}", "Skipped")).yellow().bold(),
+                    short_sha(&sha),
+                    if args.weekdays_only {
+                        "weekend"
+                    } else {
+                        "weekday"
+                    },
+                ));
+            }
+
+            commits_progress_bar.inc(1);
+
+            continue;
+        }
+
+        if merge && args.skip_merges {
+            if args.verbose {
+                commits_progress_bar.println(format!(
+                    "{} {} ({path}), it's a merge commit",
+                    style(format!("{:>12}", "Skipped")).yellow().bold(),
+                    short_sha(&sha),
+                ));
+            }
+
+            commits_progress_bar.inc(1);
+
+            continue;
+        }
+
+        // Listed commits have everything but their files, so
+        // they're only fetched individually when files are needed.
+        let counts_files = !(args.commits_only || merge && args.merge_lines == MergeLines::Skip);
+        let files = match fetched_files {
+            None => None,
+            Some(fetched_files) => match fetched_files {
+                Ok(files) => files,
+                Err(e) if e.is::<CommitNotFound>() => {
+                    if args.verbose {
+                        commits_progress_bar.println(format!(
+                            "{} {} ({path}), it can't be found, its history may have been \
+                            rewritten",
+                            style(format!("{:>12}", "Skipped")).yellow().bold(),
+                            short_sha(&sha),
+                        ));
+                    }
+
+                    commits_progress_bar.inc(1);
+
+                    continue;
+                }
+                Err(e) => return Err(e),
+            },
+        };
+
+        if let Some((max_files, files)) = args
+            .skip_commits_over
+            .zip(files.as_ref().map(Vec::len))
+            .filter(|(max_files, files)| files > max_files)
+        {
+            if args.verbose {
+                commits_progress_bar.println(format!(
+                    "{} {} ({path}), it has {files} files which is over {max_files}",
+                    style(format!("{:>12}", "Skipped")).yellow().bold(),
+                    short_sha(&sha),
+                ));
+            }
+
+            commits_progress_bar.inc(1);
+
+            continue;
+        }
+
+        if let Some(path_prefix) = &path_prefix
+            && files.as_ref().is_none_or(|files| {
+                !files
+                    .iter()
+                    .any(|file| file.filename.starts_with(path_prefix))
+            })
+        {
+            if args.verbose {
+                commits_progress_bar.println(format!(
+                    "{} {} ({path}), it doesn't change anything in {path_prefix}",
+                    style(format!("{:>12}", "Skipped")).yellow().bold(),
+                    short_sha(&sha),
+                ));
+            }
+
+            commits_progress_bar.inc(1);
+
+            continue;
+        }
+
+        if let Some(files) = &files
+            && !files.is_empty()
+            && files.iter().all(|file| file.submodule)
+        {
+            commits_progress_bar.println(format!(
+                "{} {} ({path}) only moves submodules, list them as repositories to count the \
+                work in them",
+                style(format!("{:>12}", "Warning")).yellow().bold(),
+                short_sha(&sha),
+            ));
+        }
+
+        let counting_started = Instant::now();
+        let local_date = date.with_timezone(&args.timezone);
+        let mut commit_stats = CommitStats {
+            weekday_hour: (
+                local_date.weekday().num_days_from_monday() as usize,
+                local_date.hour() as usize,
+            ),
+            ..Default::default()
+        };
+
+        if let Some(files) = files {
+            for file in files {
+                // The commit a submodule points to isn't a line
+                // that was written.
+                let reason = if file.submodule {
+                    Some("it's a submodule")
+                } else if is_excluded(&file.filename, exclude_extensions) {
+                    Some("its extension is excluded")
+                } else if path_prefix
+                    .as_ref()
+                    .is_some_and(|path_prefix| !file.filename.starts_with(path_prefix))
+                {
+                    Some("it's outside path_prefix")
+                } else if present_files
+                    .as_ref()
+                    .is_some_and(|present_files| !present_files.contains(&file.filename))
+                {
+                    Some("it's no longer present")
+                } else {
+                    None
+                };
+
+                if let Some(reason) = reason {
+                    if args.explain {
+                        commits_progress_bar.println(format!(
+                            "{} {} ({}, {path}), {reason}",
+                            style(format!("{:>12}", "Skipped")).yellow().bold(),
+                            file.filename,
+                            short_sha(&sha),
+                        ));
+                    }
+
+                    continue;
+                }
+
+                if !args.no_distinct_files {
+                    commit_stats
+                        .files
+                        .push(format!("{configured_path}/{}", file.filename));
+                }
+
+                let mut counts_lines = line_count_languages.is_empty();
+                let lines = args.line_metric.lines(file.additions, file.deletions);
+
+                if let Some(extension) = PathBuf::from(file.filename).extension() {
+                    let language = extension.to_string_lossy().to_ascii_lowercase();
+
+                    if *discovers_languages {
+                        let extension =
+                            commit_stats.extensions.entry(language.clone()).or_default();
+
+                        extension.files += 1;
+                        extension.lines += lines;
+                    }
+
+                    let language = extension_aliases
+                        .get(&language)
+                        .cloned()
+                        .unwrap_or(language);
+                    let language = linguist_extensions
+                        .language(&language)
+                        .map_or(language, str::to_string);
+
+                    counts_lines |= line_count_languages.contains(&language);
+
+                    if needed_languages.contains(&language) {
+                        *commit_stats
+                            .language_lines
+                            .entry(language.clone())
+                            .or_default() += lines;
+                        commit_stats.languages.insert(language);
+                    }
+                }
+
+                if args.count_assets && file.binary {
+                    commit_stats.assets += 1;
+                }
+
+                if counts_lines {
+                    commit_stats.lines += lines;
+                    commit_stats.additions += file.additions;
+                    commit_stats.deletions += file.deletions;
+                }
+            }
+        } else if counts_files && let Some(additions) = additions {
+            // Without files, the lines can't be told apart by
+            // language.
+            if line_count_languages.is_empty() {
+                let deletions = deletions.unwrap_or_default();
+
+                commit_stats.lines += args.line_metric.lines(additions, deletions);
+                commit_stats.additions += additions;
+                commit_stats.deletions += deletions;
+            }
+        } else if counts_files {
+            commit_stats.without_files = true;
+        }
+
+        if let Some(language) = &language
+            && !commit_stats.languages.contains(language)
+        {
+            if args.verbose {
+                commits_progress_bar.println(format!(
+                    "{} {} ({path}), it doesn't change any {language} files",
+                    style(format!("{:>12}", "Skipped")).yellow().bold(),
+                    short_sha(&sha),
+                ));
+            }
+
+            commits_progress_bar.inc(1);
+
+            continue;
+        }
+
+        // How many lines a commit without files added is unknown.
+        if counts_files
+            && !commit_stats.without_files
+            && let Some(min_lines) = args.min_commit_lines
+            && commit_stats.additions < min_lines
+        {
+            if args.verbose {
+                commits_progress_bar.println(format!(
+                    "{} {} ({path}), it added {} lines which is under {min_lines}",
+                    style(format!("{:>12}", "Skipped")).yellow().bold(),
+                    short_sha(&sha),
+                    commit_stats.additions,
+                ));
+            }
+
+            profile.counting += counting_started.elapsed();
+            commits_progress_bar.inc(1);
+
+            continue;
+        }
+
+        if args.explain {
+            let mut languages: Vec<&str> =
+                commit_stats.languages.iter().map(String::as_str).collect();
+
+            languages.sort_unstable();
+            commits_progress_bar.println(format!(
+                "{} {} ({path}), {} lines in {}",
+                style(format!("{:>12}", "Counted")).green().bold(),
+                short_sha(&sha),
+                commit_stats.lines,
+                if languages.is_empty() {
+                    "no needed language".to_string()
+                } else {
+                    languages.join(", ")
+                },
+            ));
+        }
+
+        if sample_ratio.is_some() {
+            sample_stats.add_commit(date, &commit_stats);
+        } else {
+            repository_stats.add_commit(date, &commit_stats);
+            stats.add_commit(date, &commit_stats);
+        }
+
+        if let Some(commit_dump) = &commit_dump {
+            let mut record = serde_json::to_vec(&CommitRecord {
+                sha: &sha,
+                repository: &configured_path,
+                experience,
+                date,
+                additions: commit_stats.additions,
+                deletions: commit_stats.deletions,
+                languages: commit_stats.languages.iter().map(String::as_str).collect(),
+            })?;
+
+            record.push(b'\n');
+            commit_dump
+                .lock()
+                .await
+                .write_all(&record)
+                .await
+                .context("failed to write to --dump-commits")?;
+        }
+
+        if let Some(events) = &events {
+            // The events stop being sent once the stream is dropped.
+            let _ = events.send(StatsEvent::Commit {
+                experience: experience.to_string(),
+                repository: configured_path.clone(),
+                sha: sha.clone(),
+                date,
+                lines: commit_stats.lines,
+                deletions: commit_stats.deletions,
+                languages: commit_stats.languages.iter().cloned().collect(),
+            });
+        }
+
+        profile.counting += counting_started.elapsed();
+        commits_progress_bar.inc(1);
+    }
+
+    drop(fetched_commits);
+    commits_progress_bar.finish_and_clear();
+
+    if let Some(sample_ratio) = sample_ratio {
+        sample_stats.extrapolate(sample_ratio);
+        repository_stats.merge(&sample_stats);
+        stats.merge(&sample_stats);
+    }
+
+    let without_files = repository_stats.commits_without_files - cached_without_files;
+
+    if without_files > 0 {
+        repository_progress_bar.println(format!(
+            "{} {path}, {without_files} commits had no file data, so they're counted without \
+            lines or languages",
+            style(format!("{:>12}", "Warning")).yellow().bold(),
+        ));
+    }
+
+    if repository_stats.commits > 0 {
+        let languages = if args.languages_from_linguist_api {
+            source.list_languages(&path).await?
+        } else {
+            BTreeMap::new()
+        };
+
+        stats.repositories.insert(configured_path.clone());
+
+        for (language, bytes) in languages {
+            *stats.repository_languages.entry(language).or_default() += bytes;
+        }
+
+        // No owner is everyone, so counting every author leaves these out.
+        if author != ALL_AUTHORS {
+            if upstream
+                .as_ref()
+                .unwrap_or(&path)
+                .owner
+                .eq_ignore_ascii_case(author)
+            {
+                stats.owned_repositories += 1;
+            } else {
+                stats.external_repositories += 1;
+            }
+        }
+    }
+
+    if let Some(events) = &events {
+        let _ = events.send(StatsEvent::Repository {
+            experience: experience.to_string(),
+            repository: configured_path.clone(),
+            stats: Box::new(repository_stats.clone()),
+        });
+    }
+
+    // Caching a partial listing would leave the commits that
+    // weren't listed out of every later run.
+    if let Some(cache) = cache.as_ref().filter(|_| complete) {
+        cache.lock().await.insert(
+            cache_key,
+            CachedRepository {
+                settings_hash: settings_hash.to_string(),
+                shas,
+                latest_sha,
+                stats: repository_stats,
+                partial: false,
+            },
+        );
+    }
+
+    if is_past(*deadline) {
+        *stopped = true;
+
+        return Ok(Some((path, stats)));
+    }
+
+    let extras_started = Instant::now();
+
+    if args.include_reviews {
+        let pulls = source.list_pull_requests(&path).await?;
+        let reviews_progress_bar = multi_progress.add(
+            ProgressBar::new(pulls.len() as u64)
+                .with_style(progress_style.clone())
+                .with_prefix("Fetching"),
+        );
+        let mut reviews = 0;
+
+        for pull in pulls {
+            reviews_progress_bar.set_message(format!("#{} ({path})", pull.number));
+
+            // A review can't be submitted after its pull request
+            // was last updated, so older pull requests are skipped
+            // without listing their reviews.
+            if args
+                .since()
+                .zip(pull.updated_at)
+                .is_none_or(|(since, updated_at)| updated_at >= since)
+            {
+                reviews += source
+                    .list_reviews(&path, pull.number)
+                    .await?
+                    .into_iter()
+                    .filter(|review| {
+                        review
+                            .author
+                            .as_ref()
+                            .is_some_and(|login| is_author(login, author))
+                            && review
+                                .submitted_at
+                                .is_some_and(|submitted_at| args.contains(submitted_at))
+                    })
+                    .count() as u64;
+            }
+
+            reviews_progress_bar.inc(1);
+        }
+
+        reviews_progress_bar.finish_and_clear();
+        stats.reviews += reviews;
+    }
+
+    if args.include_releases {
+        let releases = source
+            .list_releases(&path)
+            .await?
+            .into_iter()
+            .filter(|release| {
+                release
+                    .author
+                    .as_ref()
+                    .is_some_and(|login| is_author(login, author))
+                    && release
+                        .published_at
+                        .is_some_and(|published_at| args.contains(published_at))
+            })
+            .count() as u64;
+
+        stats.releases += releases;
+    }
+
+    if args.include_branches {
+        let (deleted, created): (Vec<_>, Vec<_>) = source
+            .list_branch_events(&path)
+            .await?
+            .into_iter()
+            .filter(|event| {
+                event
+                    .author
+                    .as_ref()
+                    .is_some_and(|login| is_author(login, author))
+                    && event
+                        .created_at
+                        .is_some_and(|created_at| args.contains(created_at))
+            })
+            .partition(|event| event.deleted);
+
+        stats.branches_created += created.len() as u64;
+        stats.branches_deleted += deleted.len() as u64;
+    }
+
+    profile.reviews_and_releases += extras_started.elapsed();
+
+    Ok(Some((path, stats)))
+}
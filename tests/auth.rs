@@ -0,0 +1,185 @@
+use std::{cell::RefCell, path::Path};
+
+use resume_stats::auth::{self, TokenLookups, token_from};
+
+/// Lookups that only find the tokens they're given, recording what was
+/// prompted for and saved.
+#[derive(Default)]
+struct Lookups {
+    env_var: Option<&'static str>,
+    file: Option<&'static str>,
+    gh: Option<&'static str>,
+    keyring: Option<&'static str>,
+    keyring_unavailable: bool,
+    prompts: RefCell<u32>,
+    saved: RefCell<Option<String>>,
+}
+
+impl TokenLookups for Lookups {
+    fn env_var(&self, _name: &str) -> Option<String> {
+        self.env_var.map(str::to_string)
+    }
+
+    fn read_file(&self, _path: &Path) -> std::io::Result<String> {
+        self.file
+            .map(str::to_string)
+            .ok_or(std::io::ErrorKind::NotFound.into())
+    }
+
+    fn gh_token(&self, _host: &str) -> Option<String> {
+        self.gh.map(str::to_string)
+    }
+
+    fn keyring_token(&self, _service: &str) -> Result<Option<String>, keyring::Error> {
+        if self.keyring_unavailable {
+            return Err(keyring::Error::NoStorageAccess("locked".into()));
+        }
+
+        Ok(self.keyring.map(str::to_string))
+    }
+
+    fn save_keyring_token(&self, _service: &str, token: &str) -> Result<(), keyring::Error> {
+        *self.saved.borrow_mut() = Some(token.to_string());
+
+        Ok(())
+    }
+
+    fn prompt(&self, _prompt: &str) -> anyhow::Result<String> {
+        *self.prompts.borrow_mut() += 1;
+
+        Ok("prompted".to_string())
+    }
+}
+
+fn token(
+    lookups: &Lookups,
+    token_file: Option<&str>,
+    gh_host: Option<&str>,
+    use_keyring: bool,
+) -> anyhow::Result<String> {
+    token_from(
+        lookups,
+        "resume_stats",
+        "GITHUB_TOKEN",
+        token_file.map(Path::new),
+        gh_host,
+        use_keyring,
+        "Please provide a GitHub PAT",
+    )
+}
+
+#[test]
+fn looks_for_tokens_in_order() {
+    let every = Lookups {
+        env_var: Some("env"),
+        file: Some("file\n"),
+        gh: Some("gh"),
+        keyring: Some("keyring"),
+        ..Lookups::default()
+    };
+
+    assert_eq!(
+        token(&every, Some("token"), Some("github.com"), true).unwrap(),
+        "env"
+    );
+
+    let empty_env_var = Lookups {
+        env_var: Some(""),
+        ..every
+    };
+
+    assert_eq!(
+        token(&empty_env_var, Some("token"), Some("github.com"), true).unwrap(),
+        "file"
+    );
+    assert_eq!(
+        token(&empty_env_var, None, Some("github.com"), true).unwrap(),
+        "gh"
+    );
+    assert_eq!(token(&empty_env_var, None, None, true).unwrap(), "keyring");
+    assert_eq!(*empty_env_var.prompts.borrow(), 0);
+}
+
+#[test]
+fn fails_on_token_files_that_are_missing_or_empty() {
+    let gh = Lookups {
+        gh: Some("gh"),
+        ..Lookups::default()
+    };
+
+    // A token file that was given is never passed over.
+    assert!(token(&gh, Some("token"), Some("github.com"), true).is_err());
+    assert!(
+        token(
+            &Lookups {
+                file: Some("  \n"),
+                ..gh
+            },
+            Some("token"),
+            Some("github.com"),
+            true,
+        )
+        .is_err()
+    );
+}
+
+#[test]
+fn reads_tokens_from_token_files() {
+    let token_file =
+        std::env::temp_dir().join(format!("resume_stats_token_{}", std::process::id()));
+    let missing = token_file.with_extension("missing");
+
+    std::fs::write(&token_file, "ghp_file\n").unwrap();
+
+    let read = |token_file| {
+        auth::token(
+            "resume_stats_test",
+            "RESUME_STATS_UNSET_TOKEN",
+            Some(token_file),
+            None,
+            false,
+            "GitHub PAT",
+        )
+    };
+    let token = read(&token_file);
+
+    std::fs::remove_file(&token_file).unwrap();
+
+    assert_eq!(token.unwrap(), "ghp_file");
+    assert!(
+        read(&missing)
+            .unwrap_err()
+            .to_string()
+            .contains("failed to read")
+    );
+}
+
+#[test]
+fn prompts_for_tokens_it_cant_find() {
+    let nowhere = Lookups::default();
+
+    assert_eq!(token(&nowhere, None, None, true).unwrap(), "prompted");
+    assert_eq!(nowhere.saved.borrow().as_deref(), Some("prompted"));
+
+    let without_keyring = Lookups {
+        keyring: Some("keyring"),
+        ..Lookups::default()
+    };
+
+    assert_eq!(
+        token(&without_keyring, None, None, false).unwrap(),
+        "prompted"
+    );
+    assert_eq!(*without_keyring.saved.borrow(), None);
+
+    let keyring_unavailable = Lookups {
+        keyring_unavailable: true,
+        ..Lookups::default()
+    };
+
+    assert_eq!(
+        token(&keyring_unavailable, None, None, true).unwrap(),
+        "prompted"
+    );
+    assert_eq!(*keyring_unavailable.saved.borrow(), None);
+}
@@ -0,0 +1,19 @@
+use resume_stats::badge::badge;
+
+#[test]
+fn badge_shows_label_and_value() {
+    let svg = badge("commits", "1234");
+
+    assert!(svg.starts_with("<svg "));
+    assert!(svg.contains(r#"aria-label="commits: 1234""#));
+    assert!(svg.contains(">commits</text>"));
+    assert!(svg.contains(">1234</text>"));
+}
+
+#[test]
+fn badge_escapes_text() {
+    let svg = badge("a<b", "\"&\"");
+
+    assert!(svg.contains(">a&lt;b</text>"));
+    assert!(svg.contains(">&quot;&amp;&quot;</text>"));
+}
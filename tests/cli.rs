@@ -0,0 +1,572 @@
+use std::{
+    process::{Output, Stdio},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use rustls::{
+    ServerConfig,
+    pki_types::{CertificateDer, PrivateKeyDer, pem::PemObject},
+};
+use serde_json::json;
+use tokio::net::{TcpListener, TcpStream};
+use tokio_rustls::TlsAcceptor;
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{method, path},
+};
+
+/// Runs the binary with `config` written to a file of its own and `args`
+/// after it. It's run without waiting on it, so mock servers can still
+/// respond.
+async fn run(name: &str, config: &str, args: &[&str]) -> Output {
+    let config_path = std::env::temp_dir().join(format!(
+        "resume_stats_cli_{name}_{}.toml",
+        std::process::id()
+    ));
+
+    tokio::fs::write(&config_path, config).await.unwrap();
+
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_resume_stats"))
+        .arg("--config")
+        .arg(&config_path)
+        .args(args)
+        .env("GITHUB_TOKEN", "token")
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .unwrap();
+
+    tokio::fs::remove_file(&config_path).await.unwrap();
+
+    output
+}
+
+/// Serves `server` over TLS as localhost, with a certificate signed by the CA
+/// in tests/fixtures/ca.pem, returning the URL it can be reached at.
+async fn serve_over_tls(server: &MockServer) -> String {
+    let fixture = |name: &str| format!("{}/tests/fixtures/{name}", env!("CARGO_MANIFEST_DIR"));
+    let certificates = CertificateDer::pem_file_iter(fixture("localhost.pem"))
+        .unwrap()
+        .collect::<Result<Vec<_>, _>>()
+        .unwrap();
+    let key = PrivateKeyDer::from_pem_file(fixture("localhost-key.pem")).unwrap();
+    let config =
+        ServerConfig::builder_with_provider(Arc::new(rustls::crypto::ring::default_provider()))
+            .with_safe_default_protocol_versions()
+            .unwrap()
+            .with_no_client_auth()
+            .with_single_cert(certificates, key)
+            .unwrap();
+    let acceptor = TlsAcceptor::from(Arc::new(config));
+    let listener = TcpListener::bind("127.0.0.1:0").await.unwrap();
+    let port = listener.local_addr().unwrap().port();
+    let upstream = *server.address();
+
+    tokio::spawn(async move {
+        loop {
+            let (stream, _) = listener.accept().await.unwrap();
+            let acceptor = acceptor.clone();
+
+            tokio::spawn(async move {
+                // Clients that don't trust the certificate hang up here.
+                if let Ok(mut stream) = acceptor.accept(stream).await {
+                    let mut upstream = TcpStream::connect(upstream).await.unwrap();
+
+                    let _ = tokio::io::copy_bidirectional(&mut stream, &mut upstream).await;
+                }
+            });
+        }
+    });
+
+    format!("https://localhost:{port}")
+}
+
+#[tokio::test]
+async fn reports_errors_in_the_error_format_of_the_defaults() {
+    let output = run(
+        "defaults_error_format",
+        r#"
+            author = "octocat"
+
+            [defaults]
+            error-format = "json"
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+        "#,
+        &["--only", "missing"],
+    )
+    .await;
+    let error: serde_json::Value = serde_json::from_slice(&output.stderr).unwrap();
+
+    assert!(!output.status.success());
+    assert_eq!(error["kind"], "other");
+    assert_eq!(
+        error["message"],
+        "experience \"missing\" is not in the config"
+    );
+}
+
+#[tokio::test]
+async fn only_keeps_the_experiences_given() {
+    let output = run(
+        "only",
+        r#"
+            author = "octocat"
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+
+            [experience.world]
+            repositories = ["octocat/world"]
+        "#,
+        &["--only", "hello", "--print-config"],
+    )
+    .await;
+    let config: toml::Table = toml::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+
+    assert!(output.status.success());
+    assert_eq!(
+        config["experience"]
+            .as_table()
+            .unwrap()
+            .keys()
+            .collect::<Vec<_>>(),
+        ["hello"]
+    );
+}
+
+#[tokio::test]
+async fn suggests_experiences_for_typos_in_only() {
+    let output = run(
+        "only_typo",
+        r#"
+            author = "octocat"
+
+            [experience.frontend]
+            repositories = ["octocat/hello"]
+        "#,
+        &["--only", "fronten"],
+    )
+    .await;
+
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("experience \"fronten\" is not in the config, did you mean \"frontend\"?")
+    );
+}
+
+#[tokio::test]
+async fn expands_environment_variables_in_the_config() {
+    let config = r#"
+        author = "${GITHUB_TOKEN}-bot"
+
+        [experience.hello]
+        company = "${RESUME_STATS_UNSET_VAR}"
+        repositories = ["octocat/hello"]
+    "#;
+    let missing = run("env_missing", config, &["--print-config"]).await;
+    let allowed = run(
+        "env_allowed",
+        config,
+        &["--print-config", "--allow-missing-env"],
+    )
+    .await;
+    let config: toml::Table = toml::from_str(&String::from_utf8(allowed.stdout).unwrap()).unwrap();
+
+    assert!(!missing.status.success());
+    assert!(
+        String::from_utf8(missing.stderr)
+            .unwrap()
+            .contains("failed to expand ${RESUME_STATS_UNSET_VAR}")
+    );
+    assert!(allowed.status.success());
+    assert_eq!(config["author"].as_str(), Some("token-bot"));
+    assert_eq!(
+        config["experience"]["hello"]["company"].as_str(),
+        Some("${RESUME_STATS_UNSET_VAR}")
+    );
+}
+
+#[tokio::test]
+async fn fails_on_empty_stats_when_asked() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "full_name": "octocat/hello" })),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(&server)
+        .await;
+
+    let config = r#"
+        author = "octocat"
+
+        [experience.hello]
+        repositories = ["octocat/hello"]
+    "#;
+    let base_url = server.uri();
+    let lenient = run("empty", config, &["--base-url", &base_url]).await;
+    let strict = run(
+        "fail_on_empty",
+        config,
+        &["--base-url", &base_url, "--fail-on-empty"],
+    )
+    .await;
+
+    assert!(
+        lenient.status.success(),
+        "{}",
+        String::from_utf8_lossy(&lenient.stderr)
+    );
+    assert!(!strict.status.success());
+    assert!(
+        String::from_utf8(strict.stderr)
+            .unwrap()
+            .contains("no commits were found")
+    );
+}
+
+#[tokio::test]
+async fn generates_shell_completions() {
+    for (shell, expected) in [
+        ("bash", "_resume_stats()"),
+        ("zsh", "#compdef resume_stats"),
+        ("fish", "complete -c resume_stats"),
+    ] {
+        // The config isn't read to generate completions, so it doesn't need
+        // to be valid.
+        let output = run(&format!("completions_{shell}"), "", &["completions", shell]).await;
+        let completions = String::from_utf8(output.stdout).unwrap();
+
+        assert!(output.status.success(), "{shell}");
+        assert!(completions.contains(expected), "{shell}");
+        assert!(completions.contains("fail-on-empty"), "{shell}");
+    }
+}
+
+#[tokio::test]
+async fn gives_up_on_slow_responses_after_the_read_timeout() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!({ "full_name": "octocat/hello" }))
+                .set_delay(Duration::from_secs(10)),
+        )
+        .mount(&server)
+        .await;
+
+    let start = Instant::now();
+    let output = run(
+        "read_timeout",
+        r#"
+            author = "octocat"
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+        "#,
+        &["--base-url", &server.uri(), "--read-timeout", "1"],
+    )
+    .await;
+
+    assert!(!output.status.success());
+    assert!(start.elapsed() < Duration::from_secs(10));
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("timed out")
+    );
+}
+
+#[tokio::test]
+async fn fails_on_ca_certs_that_cant_be_read() {
+    let output = run(
+        "ca_cert",
+        r#"
+            author = "octocat"
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+        "#,
+        &["--ca-cert", "/nonexistent/ca.pem"],
+    )
+    .await;
+
+    assert!(!output.status.success());
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("failed to read /nonexistent/ca.pem")
+    );
+}
+
+#[tokio::test]
+async fn prints_the_config_with_the_options_it_runs_with() {
+    let output = run(
+        "print_config",
+        r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [defaults]
+            min-commit-lines = 5
+            skip-merges = true
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+        "#,
+        &[
+            "--print-config",
+            "--since",
+            "2023-01-01",
+            "--min-commit-lines",
+            "10",
+        ],
+    )
+    .await;
+    let config: toml::Table = toml::from_str(&String::from_utf8(output.stdout).unwrap()).unwrap();
+    let options = &config["options"];
+
+    assert!(output.status.success());
+    assert_eq!(config["author"].as_str(), Some("octocat"));
+    assert_eq!(config["languages"].as_array().unwrap().len(), 1);
+    assert_eq!(options["since"].as_str(), Some("2023-01-01"));
+    // The command line takes precedence over the defaults of the config.
+    assert_eq!(options["min_commit_lines"].as_integer(), Some(10));
+    assert_eq!(options["skip_merges"].as_bool(), Some(true));
+    assert_eq!(options["merge_lines"].as_str(), Some("full"));
+}
+
+#[tokio::test]
+async fn lists_repositories_interrupted_while_being_fetched() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "full_name": "octocat/hello" })),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .set_body_json(json!([]))
+                .set_delay(Duration::from_secs(60)),
+        )
+        .mount(&server)
+        .await;
+
+    let config_path = std::env::temp_dir().join(format!(
+        "resume_stats_cli_interrupted_{}.toml",
+        std::process::id()
+    ));
+
+    tokio::fs::write(
+        &config_path,
+        r#"
+            author = "octocat"
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+        "#,
+    )
+    .await
+    .unwrap();
+
+    let child = tokio::process::Command::new(env!("CARGO_BIN_EXE_resume_stats"))
+        .arg("--config")
+        .arg(&config_path)
+        .args(["--base-url", &server.uri(), "--format", "json"])
+        .env("GITHUB_TOKEN", "token")
+        .stdin(Stdio::null())
+        .stdout(Stdio::piped())
+        .stderr(Stdio::piped())
+        .spawn()
+        .unwrap();
+
+    // Interrupts the run once it's waiting on the repository's commits.
+    while !server
+        .received_requests()
+        .await
+        .unwrap()
+        .iter()
+        .any(|request| request.url.path() == "/repos/octocat/hello/commits")
+    {
+        tokio::time::sleep(Duration::from_millis(10)).await;
+    }
+
+    let interrupted = std::process::Command::new("kill")
+        .args(["-INT", &child.id().unwrap().to_string()])
+        .status()
+        .unwrap();
+    let output = child.wait_with_output().await.unwrap();
+
+    tokio::fs::remove_file(&config_path).await.unwrap();
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert!(interrupted.success());
+    assert!(output.status.success());
+    assert_eq!(report["metadata"]["partial"], true);
+    assert_eq!(
+        report["interrupted"],
+        json!([{ "experience": "hello", "repository": "octocat/hello" }])
+    );
+    assert!(
+        String::from_utf8(output.stderr)
+            .unwrap()
+            .contains("octocat/hello (hello), the commits counted in it so far are left out")
+    );
+}
+
+#[tokio::test]
+async fn trusts_ca_certs_for_github() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!({ "full_name": "octocat/hello" })),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .mount(&server)
+        .await;
+
+    let config = r#"
+        author = "octocat"
+
+        [experience.hello]
+        repositories = ["octocat/hello"]
+    "#;
+    let base_url = serve_over_tls(&server).await;
+    let ca_cert = format!("{}/tests/fixtures/ca.pem", env!("CARGO_MANIFEST_DIR"));
+    let untrusted = run("untrusted_ca", config, &["--base-url", &base_url]).await;
+
+    // Nothing gets through to the API without trusting the certificate.
+    assert!(!untrusted.status.success());
+    assert!(server.received_requests().await.unwrap().is_empty());
+
+    let trusted = run(
+        "trusted_ca",
+        config,
+        &["--base-url", &base_url, "--ca-cert", &ca_cert],
+    )
+    .await;
+    let requests = server.received_requests().await.unwrap();
+
+    assert!(
+        trusted.status.success(),
+        "{}",
+        String::from_utf8_lossy(&trusted.stderr)
+    );
+    assert!(
+        requests
+            .iter()
+            .any(|request| request.url.path() == "/repos/octocat/hello/commits")
+    );
+    assert!(requests.iter().all(|request| {
+        request.headers.get("authorization").unwrap() == "Bearer token"
+            && request.headers.get("user-agent").unwrap() == "octocrab"
+    }));
+}
+
+#[tokio::test]
+async fn prints_the_config_without_any_tokens() {
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_resume_stats"))
+        .args(["--config", "/dev/null", "--print-config", "--no-keyring"])
+        // Points GitHub somewhere unreachable, so looking the author up fails.
+        .args(["--base-url", "http://127.0.0.1:9"])
+        .env_remove("GITHUB_TOKEN")
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .unwrap();
+    let stdout = String::from_utf8(output.stdout).unwrap();
+    let config: toml::Table = toml::from_str(&stdout).unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+    assert!(stdout.starts_with("# `author` isn't set"));
+    assert!(!config.contains_key("author"));
+}
+
+#[tokio::test]
+async fn counts_local_clones_without_a_token_or_author() {
+    let directory =
+        std::env::temp_dir().join(format!("resume_stats_cli_local_{}", std::process::id()));
+    let clone = git2::Repository::init(directory.join("clones/hello")).unwrap();
+
+    clone
+        .remote("origin", "git@github.com:octocat/hello.git")
+        .unwrap();
+    std::fs::write(
+        directory.join(".gitconfig"),
+        "[user]\n\temail = octocat@example.com\n",
+    )
+    .unwrap();
+    std::fs::write(directory.join("clones/hello/main.rs"), "fn main() {}\n").unwrap();
+
+    let signature = git2::Signature::new(
+        "Octocat",
+        "octocat@example.com",
+        &git2::Time::new(1_673_784_000, 0),
+    )
+    .unwrap();
+    let mut index = clone.index().unwrap();
+
+    index.add_path("main.rs".as_ref()).unwrap();
+
+    let tree = clone.find_tree(index.write_tree().unwrap()).unwrap();
+
+    clone
+        .commit(Some("HEAD"), &signature, &signature, "main.rs", &tree, &[])
+        .unwrap();
+
+    let output = tokio::process::Command::new(env!("CARGO_BIN_EXE_resume_stats"))
+        .args(["--config", "/dev/null", "--no-keyring", "--format", "json"])
+        .arg("--local-repos")
+        .arg(directory.join("clones/*"))
+        // Points GitHub somewhere unreachable, so asking it who the author is
+        // fails.
+        .args(["--base-url", "http://127.0.0.1:9"])
+        .env("HOME", &directory)
+        .env("XDG_CONFIG_HOME", &directory)
+        .env_remove("GITHUB_TOKEN")
+        .stdin(Stdio::null())
+        .output()
+        .await
+        .unwrap();
+
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    assert!(
+        output.status.success(),
+        "{}",
+        String::from_utf8_lossy(&output.stderr)
+    );
+
+    let report: serde_json::Value = serde_json::from_slice(&output.stdout).unwrap();
+
+    assert_eq!(report["experiences"]["local"]["commits"], 1);
+}
@@ -0,0 +1,3652 @@
+use std::{
+    collections::{BTreeSet, HashSet},
+    sync::Arc,
+    time::{Duration, Instant},
+};
+
+use async_trait::async_trait;
+use chrono::{DateTime, Utc};
+use clap::Parser;
+use futures_util::StreamExt;
+use octocrab::Octocrab;
+use resume_stats::{
+    args::Args,
+    config::{NeededStats, ProgressConfig, RepositoryPath},
+    linguist::Linguist,
+    report::ErrorReport,
+    source::{
+        Bitbucket, ChangedFile, Commit, ErrorKind, GitHubGraphql, GitLab, Local, PullRequest,
+        Release, Review, Source, Sources,
+    },
+    stats::{StatsEvent, compile_stats, compile_stats_stream, progress_styles, progress_title},
+};
+use serde_json::{Value, json};
+use wiremock::{
+    Mock, MockServer, ResponseTemplate,
+    matchers::{body_string_contains, method, path, path_regex, query_param},
+};
+
+fn commit_summary(server: &MockServer, sha: &str, date: &str) -> Value {
+    json!({
+        "url": format!("{}/commits/{sha}", server.uri()),
+        "sha": sha,
+        "node_id": sha,
+        "html_url": format!("{}/commits/{sha}", server.uri()),
+        "comments_url": format!("{}/commits/{sha}/comments", server.uri()),
+        "commit": {
+            "url": format!("{}/git/commits/{sha}", server.uri()),
+            "author": {
+                "name": "Octocat",
+                "email": "octocat@example.com",
+                "date": date,
+            },
+            "committer": null,
+            "message": "",
+            "comment_count": 0,
+            "tree": {
+                "sha": sha,
+                "url": format!("{}/git/trees/{sha}", server.uri()),
+            },
+        },
+        "author": null,
+        "committer": null,
+        "parents": [],
+    })
+}
+
+fn commit_detail(server: &MockServer, sha: &str, date: &str, files: &[(&str, u64)]) -> Value {
+    let mut commit = commit_summary(server, sha, date);
+
+    commit["files"] = files
+        .iter()
+        .map(|(filename, additions)| {
+            json!({
+                "sha": sha,
+                "filename": filename,
+                "status": "modified",
+                "additions": additions,
+                "deletions": 0,
+                "changes": additions,
+                "blob_url": null,
+                "raw_url": null,
+                "contents_url": format!("{}/contents/{filename}", server.uri()),
+                "patch": "",
+            })
+        })
+        .collect();
+
+    commit
+}
+
+fn user(server: &MockServer, login: &str) -> Value {
+    let url = format!("{}/users/{login}", server.uri());
+
+    json!({
+        "login": login,
+        "id": 1,
+        "node_id": login,
+        "avatar_url": url,
+        "gravatar_id": "",
+        "url": url,
+        "html_url": url,
+        "followers_url": url,
+        "following_url": url,
+        "gists_url": url,
+        "starred_url": url,
+        "subscriptions_url": url,
+        "organizations_url": url,
+        "repos_url": url,
+        "events_url": url,
+        "received_events_url": url,
+        "type": "User",
+        "site_admin": false,
+        "patch_url": null,
+    })
+}
+
+/// Serves `name` as the current name of `repository`, which differs if it's
+/// been renamed.
+async fn mock_repository_name(server: &MockServer, repository: &str, name: &str) {
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{repository}")))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({ "full_name": name })))
+        .mount(server)
+        .await;
+}
+
+/// A commit's SHA, author date, and files with their number of additions.
+type MockCommit<'a> = (&'a str, &'a str, &'a [(&'a str, u64)]);
+
+/// Serves the given commits for `repository`, which is in the format
+/// owner/repository.
+async fn mock_repository(server: &MockServer, repository: &str, commits: &[MockCommit<'_>]) {
+    mock_repository_name(server, repository, repository).await;
+    Mock::given(method("GET"))
+        .and(path(format!("/repos/{repository}/commits")))
+        .and(query_param("author", "octocat"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(
+                commits
+                    .iter()
+                    .map(|(sha, date, _)| commit_summary(server, sha, date))
+                    .collect::<Vec<_>>(),
+            ),
+        )
+        .mount(server)
+        .await;
+
+    for (sha, date, files) in commits {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{repository}/commits/{sha}")))
+            .respond_with(
+                ResponseTemplate::new(200).set_body_json(commit_detail(server, sha, date, files)),
+            )
+            .mount(server)
+            .await;
+    }
+}
+
+fn octocrab(server: &MockServer) -> Octocrab {
+    Octocrab::builder()
+        .base_uri(server.uri())
+        .unwrap()
+        .build()
+        .unwrap()
+}
+
+/// A source with a single commit to every repository, a single pull request
+/// reviewed by octocat and someone else, and a release by each of them.
+struct FakeSource;
+
+fn date(date: &str) -> DateTime<Utc> {
+    date.parse().unwrap()
+}
+
+#[async_trait]
+impl Source for FakeSource {
+    async fn list_commits(
+        &self,
+        _path: &RepositoryPath,
+        _author: Option<&str>,
+        _since: Option<DateTime<Utc>>,
+        _until: Option<DateTime<Utc>>,
+    ) -> anyhow::Result<Vec<Commit>> {
+        Ok(vec![Commit {
+            sha: "aaaaaaaa".to_string(),
+            date: date("2023-01-15T12:00:00Z"),
+            committer_date: None,
+            author_login: Some("octocat".to_string()),
+            author_name: Some("Octocat".to_string()),
+            author_email: Some("octocat@example.com".to_string()),
+            merge: false,
+            first_parent: None,
+            additions: None,
+            deletions: None,
+        }])
+    }
+
+    async fn get_changed_files(
+        &self,
+        _path: &RepositoryPath,
+        _sha: &str,
+    ) -> anyhow::Result<Option<Vec<ChangedFile>>> {
+        Ok(Some(vec![ChangedFile {
+            filename: "main.rs".to_string(),
+            additions: 4,
+            deletions: 0,
+            binary: false,
+            submodule: false,
+        }]))
+    }
+
+    async fn list_pull_requests(&self, _path: &RepositoryPath) -> anyhow::Result<Vec<PullRequest>> {
+        Ok(vec![PullRequest {
+            number: 1,
+            updated_at: Some(date("2023-02-01T12:00:00Z")),
+        }])
+    }
+
+    async fn list_reviews(
+        &self,
+        _path: &RepositoryPath,
+        _number: u64,
+    ) -> anyhow::Result<Vec<Review>> {
+        Ok(vec![
+            Review {
+                author: Some("Octocat".to_string()),
+                submitted_at: Some(date("2023-01-20T12:00:00Z")),
+            },
+            Review {
+                author: Some("someone".to_string()),
+                submitted_at: Some(date("2023-01-21T12:00:00Z")),
+            },
+        ])
+    }
+
+    async fn list_releases(&self, _path: &RepositoryPath) -> anyhow::Result<Vec<Release>> {
+        Ok(vec![
+            Release {
+                author: Some("octocat".to_string()),
+                published_at: Some(date("2023-03-01T12:00:00Z")),
+            },
+            Release {
+                author: Some("someone".to_string()),
+                published_at: Some(date("2023-04-01T12:00:00Z")),
+            },
+        ])
+    }
+
+    async fn authenticated_login(&self) -> anyhow::Result<String> {
+        Ok("octocat".to_string())
+    }
+}
+
+fn needed_stats(toml: &str) -> NeededStats {
+    toml::from_str(toml).unwrap()
+}
+
+#[tokio::test]
+async fn sums_lines_and_detects_languages() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            (
+                "aaaaaaaa",
+                "2023-01-15T12:00:00Z",
+                &[("src/main.rs", 10), ("README.md", 3)],
+            ),
+            (
+                "bbbbbbbb",
+                "2024-06-01T12:00:00Z",
+                &[("src/lib.rs", 5), ("build.sh", 2)],
+            ),
+        ],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs", "sh"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let hello = &stats["hello"];
+
+    assert_eq!(hello.commits, 2);
+    assert_eq!(hello.lines, 20);
+    assert_eq!(
+        hello.languages,
+        HashSet::from(["rs".to_string(), "sh".to_string()])
+    );
+    assert_eq!(
+        hello.earliest_commit.unwrap().to_rfc3339(),
+        "2023-01-15T12:00:00+00:00"
+    );
+    assert_eq!(
+        hello.latest_commit.unwrap().to_rfc3339(),
+        "2024-06-01T12:00:00+00:00"
+    );
+    assert_eq!(
+        hello.years.keys().copied().collect::<Vec<_>>(),
+        [2023, 2024]
+    );
+    assert_eq!(hello.years[&2023].commits, 1);
+    assert_eq!(hello.years[&2023].lines, 13);
+    assert_eq!(hello.years[&2024].lines, 7);
+}
+
+#[tokio::test]
+async fn caps_commits_fetched_at_once() {
+    let server = MockServer::start().await;
+    let shas = ["aaaaaaaa", "bbbbbbbb", "cccccccc", "dddddddd"];
+
+    mock_repository_name(&server, "octocat/hello", "octocat/hello").await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(
+                shas.iter()
+                    .map(|sha| commit_summary(&server, sha, "2023-01-15T12:00:00Z"))
+                    .collect::<Vec<_>>(),
+            ),
+        )
+        .mount(&server)
+        .await;
+
+    for sha in shas {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/octocat/hello/commits/{sha}")))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(commit_detail(
+                        &server,
+                        sha,
+                        "2023-01-15T12:00:00Z",
+                        &[("main.rs", 10)],
+                    ))
+                    .set_delay(Duration::from_millis(100)),
+            )
+            .mount(&server)
+            .await;
+    }
+
+    let started = Instant::now();
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from([
+            "resume_stats",
+            "--commit-concurrency",
+            "4",
+            "--max-parallel-requests",
+            "1",
+        ])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 4);
+    assert_eq!(stats["hello"].lines, 40);
+    // With only one request at a time, the delays add up.
+    assert!(started.elapsed() >= Duration::from_millis(400));
+    assert!(Args::try_parse_from(["resume_stats", "--concurrency", "0"]).is_err());
+}
+
+#[tokio::test]
+async fn explains_without_changing_what_is_counted() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            (
+                "aaaaaaaa",
+                "2023-01-15T12:00:00Z",
+                &[("main.rs", 10), ("Cargo.lock", 50)],
+            ),
+            ("bbbbbbbb", "2022-06-01T12:00:00Z", &[("main.rs", 5)]),
+        ],
+    )
+    .await;
+
+    let args = Args::parse_from([
+        "resume_stats",
+        "--explain",
+        "--since",
+        "2023-01-01",
+        "--weekdays-only",
+    ]);
+
+    assert!(args.verbose);
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(args),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+            exclude_extensions = ["lock"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    // 2023-01-15 was a Sunday.
+    assert_eq!(stats.get("hello").map_or(0, |stats| stats.commits), 0);
+}
+
+#[tokio::test]
+async fn accumulates_repositories_per_experience() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/one",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("one.rs", 1)])],
+    )
+    .await;
+    mock_repository(
+        &server,
+        "octocat/two",
+        &[
+            ("bbbbbbbb", "2023-02-15T12:00:00Z", &[("two.rs", 2)]),
+            ("cccccccc", "2023-03-15T12:00:00Z", &[("two.rs", 4)]),
+        ],
+    )
+    .await;
+    mock_repository(
+        &server,
+        "octocat/three",
+        &[("dddddddd", "2023-04-15T12:00:00Z", &[("three.rs", 8)])],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.both]
+            repositories = ["octocat/one", "octocat/two"]
+
+            [experience.other]
+            repositories = ["octocat/three"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats.len(), 2);
+    assert_eq!(stats["both"].commits, 3);
+    assert_eq!(stats["both"].lines, 7);
+    assert_eq!(stats["both"].languages, HashSet::from(["rs".to_string()]));
+    assert_eq!(
+        stats["both"].files,
+        BTreeSet::from([
+            "octocat/one/one.rs".to_string(),
+            "octocat/two/two.rs".to_string(),
+        ])
+    );
+    assert_eq!(stats["other"].commits, 1);
+    assert_eq!(stats["other"].lines, 8);
+}
+
+#[tokio::test]
+async fn commits_only_skips_commit_details() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)]),
+            ("bbbbbbbb", "2024-06-01T12:00:00Z", &[("main.rs", 5)]),
+        ],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats", "--commits-only"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let hello = &stats["hello"];
+
+    assert_eq!(hello.commits, 2);
+    assert_eq!(hello.lines, 0);
+    assert!(hello.languages.is_empty());
+    assert_eq!(
+        hello.latest_commit.unwrap().to_rfc3339(),
+        "2024-06-01T12:00:00+00:00"
+    );
+
+    let requests = server.received_requests().await.unwrap();
+
+    assert!(requests.iter().all(|request| {
+        !request
+            .url
+            .path()
+            .starts_with("/repos/octocat/hello/commits/")
+    }));
+}
+
+#[tokio::test]
+async fn defaults_author_to_authenticated_user() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/user"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(user(&server, "octocat")))
+        .expect(1)
+        .mount(&server)
+        .await;
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+}
+
+#[tokio::test]
+async fn caches_the_authenticated_login_per_sources() {
+    let mut servers = Vec::new();
+
+    for login in ["octocat", "hubot"] {
+        let server = MockServer::start().await;
+
+        Mock::given(method("GET"))
+            .and(path("/user"))
+            .respond_with(ResponseTemplate::new(200).set_body_json(user(&server, login)))
+            .expect(1)
+            .mount(&server)
+            .await;
+        servers.push(server);
+    }
+
+    let octocat = Sources::new(octocrab(&servers[0]));
+    let hubot = Sources::new(octocrab(&servers[1]));
+
+    assert_eq!(octocat.authenticated_login().await.unwrap(), "octocat");
+    assert_eq!(hubot.authenticated_login().await.unwrap(), "hubot");
+    assert_eq!(octocat.authenticated_login().await.unwrap(), "octocat");
+}
+
+#[tokio::test]
+async fn fetches_bitbucket_repositories() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/repositories/octocat/hello/commits"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "values": [
+                {
+                    "hash": "aaaaaaaa",
+                    "date": "2023-01-15T12:00:00+00:00",
+                    "author": {
+                        "raw": "Octocat <octocat@example.com>",
+                        "user": { "nickname": "octocat" },
+                    },
+                },
+                {
+                    "hash": "bbbbbbbb",
+                    "date": "2023-02-15T12:00:00+00:00",
+                    "author": { "raw": "Someone Else <someone@example.com>" },
+                },
+            ],
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repositories/octocat/hello/diffstat/aaaaaaaa"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "values": [
+                {
+                    "status": "modified",
+                    "lines_added": 7,
+                    "lines_removed": 1,
+                    "old": { "path": "src/main.rs" },
+                    "new": { "path": "src/main.rs" },
+                },
+            ],
+        })))
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server))
+            .with_bitbucket(Bitbucket::new("token".to_string()).with_base_url(server.uri())),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = [{ path = "octocat/hello", provider = "bitbucket" }]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let hello = &stats["hello"];
+
+    assert_eq!(hello.commits, 1);
+    assert_eq!(hello.lines, 7);
+    assert_eq!(hello.languages, HashSet::from(["rs".to_string()]));
+}
+
+#[tokio::test]
+async fn fetches_gitlab_repositories() {
+    let server = MockServer::start().await;
+
+    Mock::given(method("GET"))
+        .and(path("/projects/octocat%2Fhello/repository/commits"))
+        .and(query_param("author", "octocat"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "id": "aaaaaaaa",
+                "authored_date": "2023-01-15T12:00:00.000+00:00",
+                "author_name": "Octocat",
+                "author_email": "octocat@example.com",
+            },
+        ])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path(
+            "/projects/octocat%2Fhello/repository/commits/aaaaaaaa/diff",
+        ))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            {
+                "old_path": "src/main.rs",
+                "new_path": "src/main.rs",
+                "deleted_file": false,
+                "diff": "@@ -1,1 +1,3 @@\n-old\n+new\n+newer\n+newest\n",
+            },
+        ])))
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server))
+            .with_gitlab(GitLab::new("token".to_string()).with_base_url(server.uri())),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = [{ path = "octocat/hello", provider = "gitlab" }]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let hello = &stats["hello"];
+
+    assert_eq!(hello.commits, 1);
+    assert_eq!(hello.lines, 3);
+    assert_eq!(hello.languages, HashSet::from(["rs".to_string()]));
+}
+
+#[tokio::test]
+async fn keeps_commits_listed_before_a_page_fails() {
+    let server = MockServer::start().await;
+    let commits = || path("/projects/octocat%2Fhello/repository/commits");
+    let gitlab_commit = |sha: &str| {
+        json!([{
+            "id": sha,
+            "authored_date": "2023-01-15T12:00:00.000+00:00",
+            "author_name": "Octocat",
+            "author_email": "octocat@example.com",
+        }])
+    };
+
+    Mock::given(method("GET"))
+        .and(commits())
+        .and(query_param("page", "1"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-next-page", "2")
+                .set_body_json(gitlab_commit("aaaaaaaa")),
+        )
+        .mount(&server)
+        .await;
+    // The second page fails once before it's retried.
+    Mock::given(method("GET"))
+        .and(commits())
+        .and(query_param("page", "2"))
+        .respond_with(ResponseTemplate::new(500))
+        .up_to_n_times(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(commits())
+        .and(query_param("page", "2"))
+        .respond_with(
+            ResponseTemplate::new(200)
+                .insert_header("x-next-page", "3")
+                .set_body_json(gitlab_commit("bbbbbbbb")),
+        )
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(commits())
+        .and(query_param("page", "3"))
+        .respond_with(ResponseTemplate::new(403))
+        .mount(&server)
+        .await;
+
+    for sha in ["aaaaaaaa", "bbbbbbbb"] {
+        Mock::given(method("GET"))
+            .and(path(format!(
+                "/projects/octocat%2Fhello/repository/commits/{sha}/diff"
+            )))
+            .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+            .mount(&server)
+            .await;
+    }
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server))
+            .with_gitlab(GitLab::new("token".to_string()).with_base_url(server.uri())),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+
+            [experience.hello]
+            repositories = [{ path = "octocat/hello", provider = "gitlab" }]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 2);
+}
+
+#[tokio::test]
+async fn compiles_stats_from_any_source() {
+    let stats = compile_stats(
+        Sources::new(FakeSource),
+        Arc::new(Args::parse_from([
+            "resume_stats",
+            "--include-reviews",
+            "--include-releases",
+        ])),
+        needed_stats(
+            r#"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let hello = &stats["hello"];
+
+    assert_eq!(hello.commits, 1);
+    assert_eq!(hello.lines, 4);
+    assert_eq!(hello.reviews, 1);
+    assert_eq!(hello.releases, 1);
+    assert_eq!(hello.languages, HashSet::from(["rs".to_string()]));
+}
+
+#[tokio::test]
+async fn counts_github_reviews_by_the_author() {
+    let server = MockServer::start().await;
+    let pull = |number: u64, updated_at: &str| {
+        json!({
+            "url": format!("{}/repos/octocat/hello/pulls/{number}", server.uri()),
+            "id": number,
+            "number": number,
+            "updated_at": updated_at,
+            "head": { "ref": "feature", "sha": "aaaaaaaa" },
+            "base": { "ref": "main", "sha": "bbbbbbbb" },
+        })
+    };
+    let review = |id: u64, login: &str, submitted_at: &str| {
+        json!({
+            "id": id,
+            "node_id": id.to_string(),
+            "html_url": format!("{}/octocat/hello/pull/1#review-{id}", server.uri()),
+            "user": user(&server, login),
+            "submitted_at": submitted_at,
+        })
+    };
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/pulls"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            pull(1, "2023-02-01T12:00:00Z"),
+            pull(2, "2022-06-01T12:00:00Z"),
+        ])))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/pulls/1/reviews"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            review(1, "octocat", "2023-01-20T12:00:00Z"),
+            review(2, "someone", "2023-01-21T12:00:00Z"),
+            review(3, "octocat", "2022-12-01T12:00:00Z"),
+        ])))
+        .mount(&server)
+        .await;
+    // Pull requests last updated before `--since` can't have a review to
+    // count, so their reviews aren't listed.
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/pulls/2/reviews"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([])))
+        .expect(0)
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from([
+            "resume_stats",
+            "--include-reviews",
+            "--since",
+            "2023-01-01",
+        ])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].reviews, 1);
+}
+
+#[tokio::test]
+async fn counts_branches_from_events() {
+    let server = MockServer::start().await;
+    let event = |kind: &str, login: &str, ref_type: &str, created_at: &str| {
+        json!({
+            "type": kind,
+            "actor": { "login": login },
+            "payload": { "ref_type": ref_type },
+            "created_at": created_at,
+        })
+    };
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-16T12:00:00Z", &[("main.rs", 1)])],
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/events"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!([
+            event("CreateEvent", "octocat", "branch", "2023-02-01T12:00:00Z"),
+            event("CreateEvent", "Octocat", "branch", "2023-02-02T12:00:00Z"),
+            event("CreateEvent", "octocat", "tag", "2023-02-03T12:00:00Z"),
+            event("CreateEvent", "someone", "branch", "2023-02-04T12:00:00Z"),
+            event("DeleteEvent", "octocat", "branch", "2023-02-05T12:00:00Z"),
+            event("DeleteEvent", "octocat", "branch", "2022-02-05T12:00:00Z"),
+            { "type": "PushEvent", "actor": { "login": "octocat" } },
+        ])))
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from([
+            "resume_stats",
+            "--include-branches",
+            "--since",
+            "2023-01-01",
+        ])),
+        needed_stats(
+            r#"
+            author = "octocat"
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let hello = &stats["hello"];
+
+    assert_eq!(hello.branches_created, 2);
+    assert_eq!(hello.branches_deleted, 1);
+}
+
+#[tokio::test]
+async fn extrapolates_from_a_sample() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-16T12:00:00Z", &[("main.rs", 10)]),
+            ("bbbbbbbb", "2023-01-17T12:00:00Z", &[("main.rs", 1)]),
+            ("cccccccc", "2023-01-18T12:00:00Z", &[("main.rs", 30)]),
+            ("dddddddd", "2023-01-19T12:00:00Z", &[("main.rs", 1)]),
+        ],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats", "--sample", "2"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap();
+    let hello = &stats.experiences["hello"];
+
+    assert!(stats.estimated());
+    assert_eq!(hello.commits, 4);
+    assert_eq!(hello.lines, 80);
+    assert_eq!(hello.language_lines["rs"], 80);
+}
+
+#[tokio::test]
+async fn only_counts_commits_in_language() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            (
+                "aaaaaaaa",
+                "2023-01-16T12:00:00Z",
+                &[("main.rs", 10), ("README.md", 5)],
+            ),
+            ("bbbbbbbb", "2023-01-17T12:00:00Z", &[("index.ts", 20)]),
+            ("cccccccc", "2023-01-18T12:00:00Z", &[("lib.RS", 3)]),
+        ],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats", "--language", "RS"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs", "ts", "md"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let hello = &stats["hello"];
+
+    assert_eq!(hello.commits, 2);
+    assert_eq!(hello.lines, 13);
+    assert_eq!(hello.languages, HashSet::from(["rs".to_string()]));
+}
+
+#[tokio::test]
+async fn streams_commits_as_theyre_counted() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-16T12:00:00Z", &[("main.rs", 10)]),
+            ("bbbbbbbb", "2023-01-17T12:00:00Z", &[("main.rs", 5)]),
+        ],
+    )
+    .await;
+
+    let events: Vec<StatsEvent> = compile_stats_stream(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .map(Result::unwrap)
+    .collect()
+    .await;
+
+    let [
+        StatsEvent::Commit { lines: 10, .. },
+        StatsEvent::Commit { lines: 5, .. },
+        StatsEvent::Repository {
+            repository, stats, ..
+        },
+        StatsEvent::Finished(compiled_stats),
+    ] = events.as_slice()
+    else {
+        panic!("unexpected events");
+    };
+
+    assert_eq!(repository, "octocat/hello");
+    assert_eq!(stats.commits, 2);
+    assert_eq!(compiled_stats.experiences["hello"].lines, 15);
+}
+
+#[tokio::test]
+async fn skips_empty_repositories() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-16T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+    mock_repository_name(&server, "octocat/new", "octocat/new").await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/new/commits"))
+        .respond_with(ResponseTemplate::new(409).set_body_json(json!({
+            "message": "Git Repository is empty.",
+            "documentation_url": "https://docs.github.com/rest/commits/commits#list-commits",
+        })))
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+
+            [experience.hello]
+            repositories = ["octocat/new", "octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap();
+
+    assert!(stats.errors.is_empty());
+    assert_eq!(stats.experiences["hello"].commits, 1);
+}
+
+#[tokio::test]
+async fn stops_at_max_duration() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-16T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+
+    let args = Args::parse_from(["resume_stats", "--max-duration", "0s"]);
+
+    assert_eq!(
+        Args::parse_from(["resume_stats", "--max-duration", "2m"]).max_duration,
+        Some(Duration::from_secs(120))
+    );
+    assert!(Args::try_parse_from(["resume_stats", "--max-duration", "2d"]).is_err());
+    assert!(
+        Args::try_parse_from(["resume_stats", "--max-duration", "99999999999999999h"]).is_err()
+    );
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(args),
+        needed_stats(
+            r#"
+            author = "octocat"
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap();
+
+    assert!(stats.partial);
+    assert!(
+        stats
+            .experiences
+            .get("hello")
+            .is_none_or(|stats| stats.commits == 0)
+    );
+}
+
+#[tokio::test]
+async fn only_counts_commits_in_merged_pull_requests() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-16T12:00:00Z", &[("main.rs", 10)]),
+            ("bbbbbbbb", "2023-01-17T12:00:00Z", &[("main.rs", 5)]),
+            ("cccccccc", "2023-01-18T12:00:00Z", &[("main.rs", 1)]),
+        ],
+    )
+    .await;
+
+    for (sha, pulls) in [
+        ("aaaaaaaa", json!([{ "merged_at": "2023-01-20T12:00:00Z" }])),
+        ("bbbbbbbb", json!([{ "merged_at": null }])),
+        ("cccccccc", json!([])),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/octocat/hello/commits/{sha}/pulls")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(pulls))
+            .mount(&server)
+            .await;
+    }
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats", "--merged-prs-only"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].lines, 10);
+}
+
+#[tokio::test]
+async fn lists_languages_detected_by_github() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-16T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+    mock_repository(&server, "octocat/other", &[]).await;
+
+    for (repository, languages) in [
+        ("hello", json!({ "Rust": 1000, "Shell": 20 })),
+        ("other", json!({ "Python": 500 })),
+    ] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/octocat/{repository}/languages")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(languages))
+            .mount(&server)
+            .await;
+    }
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from([
+            "resume_stats",
+            "--languages-from-linguist-api",
+        ])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello", "octocat/other"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let hello = &stats["hello"];
+
+    assert_eq!(
+        hello.languages,
+        HashSet::from(["Rust".to_string(), "Shell".to_string()])
+    );
+    assert_eq!(hello.repository_languages["Rust"], 1000);
+    assert_eq!(hello.language_lines["rs"], 10);
+}
+
+#[tokio::test]
+async fn counts_lines_by_line_metric() {
+    let server = MockServer::start().await;
+    let mut detail = commit_detail(
+        &server,
+        "aaaaaaaa",
+        "2023-01-16T12:00:00Z",
+        &[("main.rs", 10), ("old.rs", 0)],
+    );
+
+    detail["files"][0]["deletions"] = json!(4);
+    detail["files"][1]["deletions"] = json!(8);
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-16T12:00:00Z", &[])],
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits/aaaaaaaa"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(detail))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    for (line_metric, lines, language_lines) in [
+        ("additions", 10, 10),
+        ("deletions", 12, 12),
+        ("changed", 22, 22),
+        // Only main.rs added more lines than it removed.
+        ("net", 6, 6),
+    ] {
+        let stats = compile_stats(
+            Sources::new(octocrab(&server)),
+            Arc::new(Args::parse_from([
+                "resume_stats",
+                "--line-metric",
+                line_metric,
+            ])),
+            needed_stats(
+                r#"
+                author = "octocat"
+                languages = ["rs"]
+
+                [experience.hello]
+                repositories = ["octocat/hello"]
+                "#,
+            ),
+        )
+        .await
+        .unwrap()
+        .experiences;
+        let hello = &stats["hello"];
+
+        assert_eq!(hello.lines, lines, "{line_metric}");
+        assert_eq!(hello.language_lines["rs"], language_lines, "{line_metric}");
+    }
+}
+
+#[tokio::test]
+async fn filters_min_commit_lines_by_additions_with_any_line_metric() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-16T12:00:00Z", &[]),
+            ("bbbbbbbb", "2023-01-17T12:00:00Z", &[]),
+        ],
+    )
+    .await;
+
+    for (sha, additions, deletions) in [("aaaaaaaa", 10, 1), ("bbbbbbbb", 2, 20)] {
+        let mut detail = commit_detail(
+            &server,
+            sha,
+            "2023-01-16T12:00:00Z",
+            &[("main.rs", additions)],
+        );
+
+        detail["files"][0]["deletions"] = json!(deletions);
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/octocat/hello/commits/{sha}")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(detail))
+            .with_priority(1)
+            .mount(&server)
+            .await;
+    }
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from([
+            "resume_stats",
+            "--line-metric",
+            "deletions",
+            "--min-commit-lines",
+            "5",
+        ])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let hello = &stats["hello"];
+
+    // The second commit removed 20 lines, but only added 2.
+    assert_eq!(hello.commits, 1);
+    assert_eq!(hello.lines, 1);
+}
+
+#[tokio::test]
+async fn resumes_partly_counted_repositories() {
+    let server = MockServer::start().await;
+    let cache =
+        std::env::temp_dir().join(format!("resume_stats_partial_{}.json", std::process::id()));
+    let args = Arc::new(Args::parse_from([
+        "resume_stats".as_ref(),
+        "--incremental".as_ref(),
+        "--flush-every".as_ref(),
+        "1".as_ref(),
+        "--cache".as_ref(),
+        cache.as_os_str(),
+    ]));
+    let config = r#"
+        author = "octocat"
+        languages = ["rs"]
+
+        [experience.hello]
+        repositories = ["octocat/hello"]
+    "#;
+    let commits: &[MockCommit] = &[
+        ("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)]),
+        ("bbbbbbbb", "2023-01-16T12:00:00Z", &[("main.rs", 5)]),
+        ("cccccccc", "2023-01-17T12:00:00Z", &[("main.rs", 1)]),
+    ];
+
+    mock_repository(&server, "octocat/hello", commits).await;
+    // The run crashes on the last commit, after the others were flushed.
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits/cccccccc"))
+        .respond_with(ResponseTemplate::new(400))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let _ = compile_stats(
+        Sources::new(octocrab(&server)),
+        args.clone(),
+        needed_stats(config),
+    )
+    .await;
+
+    server.reset().await;
+    mock_repository(&server, "octocat/hello", commits).await;
+
+    let stats = compile_stats(Sources::new(octocrab(&server)), args, needed_stats(config))
+        .await
+        .unwrap()
+        .experiences;
+    let requests = server.received_requests().await.unwrap();
+
+    tokio::fs::remove_file(&cache).await.unwrap();
+
+    assert_eq!(stats["hello"].commits, 3);
+    assert_eq!(stats["hello"].lines, 16);
+    assert!(requests.iter().all(|request| {
+        ![
+            "/repos/octocat/hello/commits/aaaaaaaa",
+            "/repos/octocat/hello/commits/bbbbbbbb",
+        ]
+        .contains(&request.url.path())
+    }));
+}
+
+#[tokio::test]
+async fn lists_github_commits_100_per_page() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+    // The rate limit check estimates a request per 100 commits listed.
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits"))
+        .and(query_param("per_page", "100"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(json!([commit_summary(
+                &server,
+                "aaaaaaaa",
+                "2023-01-15T12:00:00Z"
+            )])),
+        )
+        .expect(1)
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+}
+
+#[tokio::test]
+async fn filters_commits_by_weekday() {
+    let server = MockServer::start().await;
+
+    // A Saturday, a Sunday, and a Monday.
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-14T12:00:00Z", &[("main.rs", 1)]),
+            ("bbbbbbbb", "2023-01-15T12:00:00Z", &[("main.rs", 2)]),
+            ("cccccccc", "2023-01-16T12:00:00Z", &[("main.rs", 4)]),
+        ],
+    )
+    .await;
+
+    for (flag, commits, lines, weekdays) in [
+        (None, 3, 7, [1, 0, 0, 0, 0, 1, 1]),
+        (Some("--weekdays-only"), 1, 4, [1, 0, 0, 0, 0, 0, 0]),
+        (Some("--weekends-only"), 2, 3, [0, 0, 0, 0, 0, 1, 1]),
+    ] {
+        let stats = compile_stats(
+            Sources::new(octocrab(&server)),
+            Arc::new(Args::parse_from(["resume_stats"].into_iter().chain(flag))),
+            needed_stats(
+                r#"
+                author = "octocat"
+                languages = ["rs"]
+
+                [experience.hello]
+                repositories = ["octocat/hello"]
+                "#,
+            ),
+        )
+        .await
+        .unwrap()
+        .experiences;
+
+        assert_eq!(stats["hello"].commits, commits, "{flag:?}");
+        assert_eq!(stats["hello"].lines, lines, "{flag:?}");
+        assert_eq!(stats["hello"].weekdays, weekdays, "{flag:?}");
+    }
+}
+
+#[tokio::test]
+async fn skips_commits_over_max_files() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)]),
+            (
+                "bbbbbbbb",
+                "2023-01-16T12:00:00Z",
+                &[("a.rs", 100), ("b.rs", 100), ("c.rs", 100)],
+            ),
+        ],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from([
+            "resume_stats",
+            "--skip-commits-over",
+            "2",
+        ])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].lines, 10);
+    assert_eq!(
+        stats["hello"].latest_commit,
+        Some(date("2023-01-15T12:00:00Z"))
+    );
+}
+
+#[tokio::test]
+async fn counts_commits_with_short_shas() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("abc", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats", "--verbose"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].lines, 10);
+}
+
+#[tokio::test]
+async fn skips_commits_that_cant_be_found() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits"))
+        .and(query_param("author", "octocat"))
+        .respond_with(ResponseTemplate::new(200).set_body_json([
+            commit_summary(&server, "aaaaaaaa", "2023-01-15T12:00:00Z"),
+            commit_summary(&server, "bbbbbbbb", "2023-02-15T12:00:00Z"),
+        ]))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits/bbbbbbbb"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "message": "Not Found",
+            "documentation_url": "https://docs.github.com/rest",
+        })))
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].lines, 10);
+}
+
+#[tokio::test]
+async fn matches_git_identities() {
+    let server = MockServer::start().await;
+    let mut linked_commit = commit_summary(&server, "bbbbbbbb", "2023-02-15T12:00:00Z");
+    let mut other_commit = commit_summary(&server, "cccccccc", "2023-03-15T12:00:00Z");
+
+    mock_repository_name(&server, "octocat/hello", "octocat/hello").await;
+
+    linked_commit["commit"]["author"]["email"] = json!("octocat@users.noreply.github.com");
+    linked_commit["author"] = user(&server, "octocat");
+    other_commit["commit"]["author"]["email"] = json!("someone@example.com");
+    other_commit["commit"]["author"]["name"] = json!("Someone");
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits"))
+        .respond_with(ResponseTemplate::new(200).set_body_json([
+            commit_summary(&server, "aaaaaaaa", "2023-01-15T12:00:00Z"),
+            linked_commit,
+            other_commit,
+        ]))
+        .mount(&server)
+        .await;
+
+    for sha in ["aaaaaaaa", "bbbbbbbb"] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/octocat/hello/commits/{sha}")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(commit_detail(
+                &server,
+                sha,
+                "2023-01-15T12:00:00Z",
+                &[("main.rs", 10)],
+            )))
+            .mount(&server)
+            .await;
+    }
+
+    let mut needed_stats = needed_stats(
+        r#"
+        author = "octocat"
+
+        [experience.hello]
+        repositories = ["octocat/hello"]
+        "#,
+    );
+
+    needed_stats.git_identities = BTreeSet::from(["octocat@example.com".to_string()]);
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from([
+            "resume_stats",
+            "--author-match",
+            "email",
+        ])),
+        needed_stats,
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    // Only the commit made with the git config's email, since logins aren't
+    // compared with `--author-match email`.
+    assert_eq!(stats["hello"].commits, 1);
+}
+
+#[tokio::test]
+async fn matches_authors_by_email() {
+    let server = MockServer::start().await;
+    let mut other_commit = commit_summary(&server, "bbbbbbbb", "2023-02-15T12:00:00Z");
+
+    mock_repository_name(&server, "octocat/hello", "octocat/hello").await;
+
+    other_commit["commit"]["author"]["email"] = json!("someone@example.com");
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits"))
+        .respond_with(ResponseTemplate::new(200).set_body_json([
+            commit_summary(&server, "aaaaaaaa", "2023-01-15T12:00:00Z"),
+            other_commit,
+        ]))
+        .mount(&server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits/aaaaaaaa"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(commit_detail(
+            &server,
+            "aaaaaaaa",
+            "2023-01-15T12:00:00Z",
+            &[("main.rs", 10)],
+        )))
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from([
+            "resume_stats",
+            "--author-match",
+            "email",
+        ])),
+        needed_stats(
+            r#"
+            author = "Octocat@example.com"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].lines, 10);
+
+    let requests = server.received_requests().await.unwrap();
+
+    assert!(
+        requests
+            .iter()
+            .all(|request| request.url.query_pairs().all(|(key, _)| key != "author"))
+    );
+}
+
+#[tokio::test]
+async fn counts_every_author() {
+    let server = MockServer::start().await;
+    let mut other_commit = commit_summary(&server, "bbbbbbbb", "2023-02-15T12:00:00Z");
+
+    mock_repository_name(&server, "octocat/hello", "octocat/hello").await;
+
+    other_commit["commit"]["author"]["email"] = json!("someone@example.com");
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits"))
+        .respond_with(ResponseTemplate::new(200).set_body_json([
+            commit_summary(&server, "aaaaaaaa", "2023-01-15T12:00:00Z"),
+            other_commit,
+        ]))
+        .mount(&server)
+        .await;
+
+    for sha in ["aaaaaaaa", "bbbbbbbb"] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/octocat/hello/commits/{sha}")))
+            .respond_with(ResponseTemplate::new(200).set_body_json(commit_detail(
+                &server,
+                sha,
+                "2023-01-15T12:00:00Z",
+                &[("main.rs", 10)],
+            )))
+            .mount(&server)
+            .await;
+    }
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "*"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 2);
+    assert_eq!(stats["hello"].lines, 20);
+
+    let requests = server.received_requests().await.unwrap();
+
+    assert!(
+        requests
+            .iter()
+            .all(|request| request.url.query_pairs().all(|(key, _)| key != "author"))
+    );
+}
+
+#[tokio::test]
+async fn dates_commits_by_date_field() {
+    let server = MockServer::start().await;
+    // Authored before `--since`, but rebased after it.
+    let mut rebased_commit = commit_summary(&server, "aaaaaaaa", "2022-12-15T12:00:00Z");
+
+    rebased_commit["commit"]["committer"] = json!({
+        "name": "Octocat",
+        "email": "octocat@example.com",
+        "date": "2023-03-15T12:00:00Z",
+    });
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2022-12-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits"))
+        .respond_with(ResponseTemplate::new(200).set_body_json([rebased_commit]))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let compile = |date_field| {
+        compile_stats(
+            Sources::new(octocrab(&server)),
+            Arc::new(Args::parse_from([
+                "resume_stats",
+                "--since",
+                "2023-01-01",
+                "--date-field",
+                date_field,
+            ])),
+            needed_stats(
+                r#"
+                author = "octocat"
+                languages = ["rs"]
+
+                [experience.hello]
+                repositories = ["octocat/hello"]
+                "#,
+            ),
+        )
+    };
+
+    let stats = compile("committer").await.unwrap().experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(
+        stats["hello"].earliest_commit.unwrap().to_rfc3339(),
+        "2023-03-15T12:00:00+00:00"
+    );
+    assert!(
+        compile("author")
+            .await
+            .unwrap()
+            .experiences
+            .get("hello")
+            .is_none_or(|stats| stats.commits == 0)
+    );
+}
+
+#[tokio::test]
+async fn fetches_repositories_with_other_accounts() {
+    let server = MockServer::start().await;
+    let work_server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+    mock_repository_name(&work_server, "acme/app", "acme/app").await;
+    Mock::given(method("GET"))
+        .and(path("/repos/acme/app/commits"))
+        .and(query_param("author", "octocat-at-acme"))
+        .respond_with(ResponseTemplate::new(200).set_body_json([commit_summary(
+            &work_server,
+            "bbbbbbbb",
+            "2023-02-15T12:00:00Z",
+        )]))
+        .mount(&work_server)
+        .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/acme/app/commits/bbbbbbbb"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(commit_detail(
+            &work_server,
+            "bbbbbbbb",
+            "2023-02-15T12:00:00Z",
+            &[("main.rs", 20)],
+        )))
+        .mount(&work_server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server))
+            .with_github_account("octocat-at-acme", octocrab(&work_server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.personal]
+            repositories = ["octocat/hello"]
+
+            [experience.acme]
+            account = "octocat-at-acme"
+            repositories = ["acme/app"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["personal"].lines, 10);
+    assert_eq!(stats["acme"].lines, 20);
+    assert!(
+        server
+            .received_requests()
+            .await
+            .unwrap()
+            .iter()
+            .all(|request| !request.url.path().starts_with("/repos/acme"))
+    );
+}
+
+#[tokio::test]
+async fn names_experiences() {
+    let compiled_stats = compile_stats(
+        Sources::new(FakeSource),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            [experience.acme]
+            name = "Acme Corp (Senior Engineer)"
+            repositories = ["acme/app"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(compiled_stats.name("acme"), "Acme Corp (Senior Engineer)");
+    assert_eq!(compiled_stats.name("hello"), "hello");
+}
+
+#[tokio::test]
+async fn follows_renamed_repositories() {
+    let server = MockServer::start().await;
+
+    mock_repository_name(&server, "octocat/old", "octocat/new").await;
+    mock_repository(
+        &server,
+        "octocat/new",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/old"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].lines, 10);
+}
+
+#[tokio::test]
+async fn incrementally_fetches_new_commits() {
+    let server = MockServer::start().await;
+    let cache = std::env::temp_dir().join(format!(
+        "resume_stats_incremental_{}.json",
+        std::process::id()
+    ));
+    let args = Arc::new(Args::parse_from([
+        "resume_stats".as_ref(),
+        "--incremental".as_ref(),
+        "--cache".as_ref(),
+        cache.as_os_str(),
+    ]));
+    let config = r#"
+        author = "octocat"
+        languages = ["rs"]
+
+        [experience.hello]
+        repositories = ["octocat/hello"]
+    "#;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+    compile_stats(
+        Sources::new(octocrab(&server)),
+        args.clone(),
+        needed_stats(config),
+    )
+    .await
+    .unwrap();
+    server.reset().await;
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)]),
+            ("bbbbbbbb", "2023-02-15T12:00:00Z", &[("main.rs", 5)]),
+        ],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        args.clone(),
+        needed_stats(config),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 2);
+    assert_eq!(stats["hello"].lines, 15);
+
+    let requests = server.received_requests().await.unwrap();
+
+    assert!(
+        requests
+            .iter()
+            .all(|request| request.url.path() != "/repos/octocat/hello/commits/aaaaaaaa")
+    );
+    // Whether the repository moved is reused within `--metadata-ttl`.
+    assert!(
+        requests
+            .iter()
+            .all(|request| request.url.path() != "/repos/octocat/hello")
+    );
+
+    server.reset().await;
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("cccccccc", "2023-03-15T12:00:00Z", &[("main.rs", 1)])],
+    )
+    .await;
+
+    let error = compile_stats(Sources::new(octocrab(&server)), args, needed_stats(config))
+        .await
+        .err()
+        .unwrap();
+
+    assert!(format!("{error:#}").contains("--refresh-cache"));
+
+    std::fs::remove_file(cache).unwrap();
+}
+
+#[tokio::test]
+async fn only_caches_metadata_with_incremental() {
+    let server = MockServer::start().await;
+    let cache =
+        std::env::temp_dir().join(format!("resume_stats_metadata_{}.json", std::process::id()));
+    let args = Arc::new(Args::parse_from([
+        "resume_stats".as_ref(),
+        "--cache".as_ref(),
+        cache.as_os_str(),
+    ]));
+    let config = r#"
+        author = "octocat"
+        languages = ["rs"]
+
+        [experience.hello]
+        repositories = ["octocat/hello"]
+    "#;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+
+    for _ in 0..2 {
+        compile_stats(
+            Sources::new(octocrab(&server)),
+            args.clone(),
+            needed_stats(config),
+        )
+        .await
+        .unwrap();
+    }
+
+    let metadata_requests = server
+        .received_requests()
+        .await
+        .unwrap()
+        .into_iter()
+        .filter(|request| request.url.path() == "/repos/octocat/hello")
+        .count();
+
+    // Each run checks whether the repository moved again, since nothing is
+    // written to the cache.
+    assert_eq!(metadata_requests, 2);
+    assert!(!cache.exists());
+}
+
+#[tokio::test]
+async fn discovers_every_extension() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            (
+                "aaaaaaaa",
+                "2023-01-15T12:00:00Z",
+                &[("src/main.rs", 10), ("README.md", 3), ("LICENSE", 20)],
+            ),
+            ("bbbbbbbb", "2024-06-01T12:00:00Z", &[("src/main.rs", 5)]),
+        ],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats", "languages"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let extensions = &stats["hello"].extensions;
+
+    assert_eq!(extensions.len(), 2);
+    assert_eq!(extensions["rs"].files, 2);
+    assert_eq!(extensions["rs"].lines, 15);
+    assert_eq!(extensions["md"].files, 1);
+    assert_eq!(extensions["md"].lines, 3);
+}
+
+#[tokio::test]
+async fn leaves_out_languages_with_few_lines() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            (
+                "aaaaaaaa",
+                "2023-01-15T12:00:00Z",
+                &[("src/main.rs", 10), ("build.sh", 2)],
+            ),
+            ("bbbbbbbb", "2024-06-01T12:00:00Z", &[("src/lib.rs", 5)]),
+        ],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from([
+            "resume_stats",
+            "--min-language-lines",
+            "5",
+        ])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs", "sh"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let hello = &stats["hello"];
+
+    assert_eq!(hello.languages, HashSet::from(["rs".to_string()]));
+    assert_eq!(hello.language_lines["rs"], 15);
+    assert_eq!(hello.language_lines["sh"], 2);
+}
+
+#[tokio::test]
+async fn counts_owned_and_external_repositories() {
+    let compiled_stats = compile_stats(
+        Sources::new(FakeSource),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+
+            [experience.hello]
+            repositories = ["octocat/hello", "OctoCat/world", "acme/app"]
+
+            [experience.app]
+            repositories = ["acme/app"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap();
+    let stats = &compiled_stats.experiences;
+
+    assert_eq!(stats["hello"].owned_repositories, 2);
+    assert_eq!(stats["hello"].external_repositories, 1);
+    assert_eq!(stats["hello"].repositories.len(), 3);
+    assert_eq!(compiled_stats.repositories(), 3);
+
+    let stats = compile_stats(
+        Sources::new(FakeSource),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "*"
+
+            [experience.hello]
+            repositories = ["octocat/hello", "acme/app"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    // Nobody owns every author's repositories, so they aren't told apart.
+    assert_eq!(stats["hello"].owned_repositories, 0);
+    assert_eq!(stats["hello"].external_repositories, 0);
+    assert_eq!(stats["hello"].repositories.len(), 2);
+}
+
+#[tokio::test]
+async fn ignores_excluded_extensions() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[(
+            "aaaaaaaa",
+            "2023-01-15T12:00:00Z",
+            &[
+                ("Cargo.lock", 400),
+                ("web/app.min.js", 900),
+                ("web/app.js", 30),
+                ("src/main.rs", 10),
+            ],
+        )],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["js", "lock", "rs"]
+            exclude_extensions = ["lock", "min.js"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let hello = &stats["hello"];
+
+    assert_eq!(hello.lines, 40);
+    assert_eq!(
+        hello.languages,
+        HashSet::from(["js".to_string(), "rs".to_string()])
+    );
+}
+
+#[tokio::test]
+async fn matches_extensions_ignoring_case_and_aliases() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[(
+            "aaaaaaaa",
+            "2023-01-15T12:00:00Z",
+            &[
+                ("src/Main.RS", 10),
+                ("web/App.JSX", 20),
+                ("web/vendor.MIN.JS", 900),
+            ],
+        )],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["js", "rs"]
+            exclude_extensions = ["min.js"]
+            extension_aliases = { jsx = "js" }
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let hello = &stats["hello"];
+
+    assert_eq!(hello.lines, 30);
+    assert_eq!(
+        hello.languages,
+        HashSet::from(["js".to_string(), "rs".to_string()])
+    );
+}
+
+#[tokio::test]
+async fn keeps_going_past_failed_repositories() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+    Mock::given(path_regex("^/repos/octocat/missing"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "message": "Not Found",
+            "documentation_url": "https://docs.github.com/rest",
+        })))
+        .mount(&server)
+        .await;
+
+    let config = r#"
+        author = "octocat"
+        languages = ["rs"]
+
+        [experience.hello]
+        repositories = ["octocat/missing", "octocat/hello"]
+    "#;
+
+    let Err(e) = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(config),
+    )
+    .await
+    else {
+        panic!("the missing repository should stop the run");
+    };
+    let error_report = ErrorReport::new(&e);
+
+    assert_eq!(error_report.kind, ErrorKind::NotFound);
+    assert_eq!(error_report.experience, Some("hello"));
+    assert_eq!(error_report.repository, Some("octocat/missing"));
+
+    let compiled_stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats", "--keep-going"])),
+        needed_stats(config),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(compiled_stats.experiences["hello"].commits, 1);
+    assert_eq!(compiled_stats.errors.len(), 1);
+    assert_eq!(compiled_stats.errors[0].experience, "hello");
+    assert_eq!(compiled_stats.errors[0].repository, "octocat/missing");
+    assert_eq!(compiled_stats.errors[0].kind, ErrorKind::NotFound);
+}
+
+#[tokio::test]
+async fn leaves_repositories_that_fail_partway_out_of_the_stats() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+    mock_repository(
+        &server,
+        "octocat/world",
+        &[
+            ("bbbbbbbb", "2023-02-15T12:00:00Z", &[("main.rs", 5)]),
+            ("cccccccc", "2023-01-15T12:00:00Z", &[("main.rs", 3)]),
+        ],
+    )
+    .await;
+    // The first commit listed is counted before the second can't be fetched.
+    Mock::given(path("/repos/octocat/world/commits/cccccccc"))
+        .respond_with(ResponseTemplate::new(403).set_body_json(json!({
+            "message": "Resource not accessible by personal access token",
+            "documentation_url": "https://docs.github.com/rest",
+        })))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let compiled_stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats", "--keep-going"])),
+        needed_stats(
+            r#"
+                author = "octocat"
+                languages = ["rs"]
+
+                [experience.hello]
+                repositories = ["octocat/hello", "octocat/world"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap();
+    let hello = &compiled_stats.experiences["hello"];
+
+    assert_eq!(compiled_stats.errors.len(), 1);
+    assert_eq!(compiled_stats.errors[0].repository, "octocat/world");
+    assert_eq!(hello.commits, 1);
+    assert_eq!(hello.lines, 10);
+    assert_eq!(
+        hello.repositories,
+        BTreeSet::from(["octocat/hello".to_string()])
+    );
+}
+
+#[tokio::test]
+async fn anonymizes_errors_of_failed_repositories() {
+    let server = MockServer::start().await;
+
+    Mock::given(path_regex("^/repos/octocat/secret"))
+        .respond_with(ResponseTemplate::new(404).set_body_json(json!({
+            "message": "octocat/secret was not found",
+            "documentation_url": "https://docs.github.com/rest",
+        })))
+        .mount(&server)
+        .await;
+
+    let mut compiled_stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats", "--keep-going"])),
+        needed_stats(
+            r#"
+                author = "octocat"
+
+                [experience.hello]
+                repositories = ["octocat/secret"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap();
+
+    assert!(compiled_stats.errors[0].error.contains("octocat/secret"));
+
+    compiled_stats.anonymize();
+
+    let error = &compiled_stats.errors[0];
+    let error_report = serde_json::to_string(&ErrorReport::from(error)).unwrap();
+
+    assert_eq!(error.kind, ErrorKind::NotFound);
+    assert_eq!(error.error, ErrorKind::NotFound.description());
+    assert!(!error_report.contains("secret"));
+    assert!(!error_report.contains("hello"));
+}
+
+#[tokio::test]
+async fn resumes_from_checkpoints() {
+    let server = MockServer::start().await;
+    let checkpoint = std::env::temp_dir().join(format!(
+        "resume_stats_checkpoint_{}.json",
+        std::process::id()
+    ));
+    let args = |resume: bool| {
+        let mut args = vec![
+            "resume_stats".as_ref(),
+            "--keep-going".as_ref(),
+            "--checkpoint".as_ref(),
+            checkpoint.as_os_str(),
+        ];
+
+        if resume {
+            args.push("--resume".as_ref());
+        }
+
+        Arc::new(Args::parse_from(args))
+    };
+    let config = r#"
+        author = "octocat"
+        languages = ["rs"]
+
+        [experience.hello]
+        repositories = ["octocat/hello"]
+
+        [experience.world]
+        repositories = ["octocat/world"]
+    "#;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+
+    let compiled_stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        args(false),
+        needed_stats(config),
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(compiled_stats.errors.len(), 1);
+    assert!(checkpoint.exists());
+
+    server.reset().await;
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)]),
+            ("bbbbbbbb", "2023-02-15T12:00:00Z", &[("main.rs", 5)]),
+        ],
+    )
+    .await;
+    mock_repository(
+        &server,
+        "octocat/world",
+        &[("cccccccc", "2023-03-15T12:00:00Z", &[("main.rs", 1)])],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        args(true),
+        needed_stats(config),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["world"].commits, 1);
+    assert!(!checkpoint.exists());
+}
+
+#[tokio::test]
+async fn computes_share_of_every_commit() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+    // Lower priority than the author's commits, so only the request for every
+    // commit gets these.
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits"))
+        .respond_with(ResponseTemplate::new(200).set_body_json([
+            commit_summary(&server, "aaaaaaaa", "2023-01-15T12:00:00Z"),
+            commit_summary(&server, "bbbbbbbb", "2023-01-16T12:00:00Z"),
+            commit_summary(&server, "cccccccc", "2023-01-17T12:00:00Z"),
+            commit_summary(&server, "dddddddd", "2023-01-18T12:00:00Z"),
+        ]))
+        .with_priority(6)
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats", "--with-share"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].total_commits, 4);
+    assert_eq!(stats["hello"].share(), Some(25.0));
+}
+
+#[tokio::test]
+async fn controls_how_merges_are_counted() {
+    let server = MockServer::start().await;
+    let mut merge = commit_summary(&server, "bbbbbbbb", "2023-02-15T12:00:00Z");
+
+    merge["parents"] = json!([
+        { "sha": "aaaaaaaa", "url": format!("{}/commits/aaaaaaaa", server.uri()) },
+        { "sha": "cccccccc", "url": format!("{}/commits/cccccccc", server.uri()) },
+    ]);
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)]),
+            ("bbbbbbbb", "2023-02-15T12:00:00Z", &[("main.rs", 100)]),
+        ],
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits"))
+        .and(query_param("author", "octocat"))
+        .respond_with(ResponseTemplate::new(200).set_body_json([
+            commit_summary(&server, "aaaaaaaa", "2023-01-15T12:00:00Z"),
+            merge,
+        ]))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let config = r#"
+        author = "octocat"
+        languages = ["rs"]
+
+        [experience.hello]
+        repositories = ["octocat/hello"]
+    "#;
+    let compile = |args: &'static [&'static str]| {
+        compile_stats(
+            Sources::new(octocrab(&server)),
+            Arc::new(Args::parse_from(args)),
+            needed_stats(config),
+        )
+    };
+
+    let stats = compile(&["resume_stats"]).await.unwrap().experiences;
+
+    assert_eq!(stats["hello"].commits, 2);
+    assert_eq!(stats["hello"].lines, 110);
+
+    let stats = compile(&["resume_stats", "--merge-lines", "skip"])
+        .await
+        .unwrap()
+        .experiences;
+
+    assert_eq!(stats["hello"].commits, 2);
+    assert_eq!(stats["hello"].lines, 10);
+
+    let stats = compile(&["resume_stats", "--skip-merges"])
+        .await
+        .unwrap()
+        .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].lines, 10);
+}
+
+#[tokio::test]
+async fn only_counts_first_parent_history() {
+    let server = MockServer::start().await;
+    let parents = |shas: &[&str]| {
+        shas.iter()
+            .map(|sha| json!({ "sha": sha, "url": format!("{}/commits/{sha}", server.uri()) }))
+            .collect::<Value>()
+    };
+    let commits = [
+        ("aaaaaaaa", "2023-01-15T12:00:00Z", parents(&[])),
+        ("cccccccc", "2023-01-20T12:00:00Z", parents(&["aaaaaaaa"])),
+        (
+            "bbbbbbbb",
+            "2023-02-15T12:00:00Z",
+            parents(&["aaaaaaaa", "cccccccc"]),
+        ),
+    ];
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)]),
+            ("cccccccc", "2023-01-20T12:00:00Z", &[("main.rs", 50)]),
+            ("bbbbbbbb", "2023-02-15T12:00:00Z", &[("main.rs", 50)]),
+        ],
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits"))
+        .respond_with(
+            ResponseTemplate::new(200).set_body_json(
+                commits
+                    .into_iter()
+                    .rev()
+                    .map(|(sha, date, parents)| {
+                        let mut commit = commit_summary(&server, sha, date);
+
+                        commit["author"] = user(&server, "octocat");
+                        commit["parents"] = parents;
+                        commit
+                    })
+                    .collect::<Vec<_>>(),
+            ),
+        )
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats", "--first-parent"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 2);
+    assert_eq!(stats["hello"].lines, 60);
+}
+
+#[tokio::test]
+async fn only_counts_repositories_with_visibility() {
+    let server = MockServer::start().await;
+
+    for (repository, private) in [("octocat/hello", false), ("octocat/secret", true)] {
+        Mock::given(method("GET"))
+            .and(path(format!("/repos/{repository}")))
+            .respond_with(
+                ResponseTemplate::new(200)
+                    .set_body_json(json!({ "full_name": repository, "private": private })),
+            )
+            .with_priority(1)
+            .mount(&server)
+            .await;
+        mock_repository(
+            &server,
+            repository,
+            &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+        )
+        .await;
+    }
+
+    let config = r#"
+        author = "octocat"
+        languages = ["rs"]
+
+        [experience.hello]
+        repositories = ["octocat/hello", "octocat/secret"]
+    "#;
+    let compile = |args: &'static [&'static str]| {
+        compile_stats(
+            Sources::new(octocrab(&server)),
+            Arc::new(Args::parse_from(args)),
+            needed_stats(config),
+        )
+    };
+
+    let stats = compile(&["resume_stats", "--visibility", "public"])
+        .await
+        .unwrap()
+        .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].owned_repositories, 1);
+
+    let stats = compile(&["resume_stats"]).await.unwrap().experiences;
+
+    assert_eq!(stats["hello"].commits, 2);
+}
+
+#[tokio::test]
+async fn counts_local_clones() {
+    let server = MockServer::start().await;
+    let directory =
+        std::env::temp_dir().join(format!("resume_stats_local_repos_{}", std::process::id()));
+    let clone = git2::Repository::init(directory.join("hello")).unwrap();
+
+    clone
+        .remote("origin", "git@github.com:octocat/hello.git")
+        .unwrap();
+
+    let signature = git2::Signature::new(
+        "Octocat",
+        "octocat@example.com",
+        &git2::Time::new(1_673_784_000, 0),
+    )
+    .unwrap();
+    let mut parent = None;
+
+    for (filename, contents) in [("main.rs", "fn main() {\n}\n"), ("notes.md", "# Notes\n")] {
+        std::fs::write(directory.join("hello").join(filename), contents).unwrap();
+
+        let mut index = clone.index().unwrap();
+
+        index.add_path(filename.as_ref()).unwrap();
+
+        let tree = clone.find_tree(index.write_tree().unwrap()).unwrap();
+        let parents: Vec<git2::Commit> = parent
+            .iter()
+            .map(|oid| clone.find_commit(*oid).unwrap())
+            .collect();
+
+        parent = Some(
+            clone
+                .commit(
+                    Some("HEAD"),
+                    &signature,
+                    &signature,
+                    filename,
+                    &tree,
+                    &parents.iter().collect::<Vec<_>>(),
+                )
+                .unwrap(),
+        );
+    }
+
+    std::fs::create_dir(directory.join("not-a-clone")).unwrap();
+
+    let local = Local::discover(&format!("{}/*", directory.display())).unwrap();
+    let mut needed_stats = needed_stats(
+        r#"
+        author = "octocat@example.com"
+        languages = ["rs"]
+        "#,
+    );
+
+    needed_stats.add_local_repositories(local.paths());
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)).with_local(local),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats,
+    )
+    .await;
+
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    let stats = stats.unwrap().experiences;
+
+    assert_eq!(stats["local"].commits, 2);
+    assert_eq!(stats["local"].lines, 3);
+    assert_eq!(stats["local"].languages, HashSet::from(["rs".to_string()]));
+}
+
+#[tokio::test]
+async fn matches_local_clones_by_git_identities() {
+    let server = MockServer::start().await;
+    let directory = std::env::temp_dir().join(format!(
+        "resume_stats_local_identities_{}",
+        std::process::id()
+    ));
+    let clone = git2::Repository::init(directory.join("hello")).unwrap();
+
+    clone
+        .remote("origin", "git@github.com:octocat/hello.git")
+        .unwrap();
+
+    let mut parent = None;
+
+    for (filename, email) in [
+        ("main.rs", "mona@example.com"),
+        ("lib.rs", "someone@example.com"),
+    ] {
+        std::fs::write(directory.join("hello").join(filename), "fn main() {}\n").unwrap();
+
+        let signature =
+            git2::Signature::new("Someone", email, &git2::Time::new(1_673_784_000, 0)).unwrap();
+        let mut index = clone.index().unwrap();
+
+        index.add_path(filename.as_ref()).unwrap();
+
+        let tree = clone.find_tree(index.write_tree().unwrap()).unwrap();
+        let parents: Vec<git2::Commit> = parent
+            .iter()
+            .map(|oid| clone.find_commit(*oid).unwrap())
+            .collect();
+
+        parent = Some(
+            clone
+                .commit(
+                    Some("HEAD"),
+                    &signature,
+                    &signature,
+                    filename,
+                    &tree,
+                    &parents.iter().collect::<Vec<_>>(),
+                )
+                .unwrap(),
+        );
+    }
+
+    let local = Local::discover(&format!("{}/*", directory.display())).unwrap();
+    let mut needed_stats = needed_stats(
+        r#"
+        author = "octocat"
+        languages = ["rs"]
+        "#,
+    );
+
+    needed_stats.git_identities = BTreeSet::from(["mona@example.com".to_string()]);
+    needed_stats.add_local_repositories(local.paths());
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)).with_local(local),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats,
+    )
+    .await;
+
+    std::fs::remove_dir_all(&directory).unwrap();
+
+    // Only the commit made with the git config's email, even with the default
+    // `--author-match login`.
+    assert_eq!(stats.unwrap().experiences["local"].commits, 1);
+}
+
+#[tokio::test]
+async fn counts_commits_without_files() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)]),
+            ("bbbbbbbb", "2023-02-15T12:00:00Z", &[]),
+        ],
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits/bbbbbbbb"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(commit_summary(
+            &server,
+            "bbbbbbbb",
+            "2023-02-15T12:00:00Z",
+        )))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from([
+            "resume_stats",
+            "--min-commit-lines",
+            "5",
+        ])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 2);
+    assert_eq!(stats["hello"].commits_without_files, 1);
+    assert_eq!(stats["hello"].lines, 10);
+}
+
+#[tokio::test]
+async fn only_counts_files_under_path_prefix() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/monorepo",
+        &[
+            (
+                "aaaaaaaa",
+                "2023-01-15T12:00:00Z",
+                &[("services/payments/main.rs", 10), ("README.md", 5)],
+            ),
+            (
+                "bbbbbbbb",
+                "2023-02-15T12:00:00Z",
+                &[("services/payments-v2/main.rs", 20)],
+            ),
+        ],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs", "md"]
+
+            [experience.payments]
+            repositories = [{ path = "octocat/monorepo", path_prefix = "services/payments" }]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["payments"].commits, 1);
+    assert_eq!(stats["payments"].lines, 10);
+    assert_eq!(
+        stats["payments"].languages,
+        HashSet::from(["rs".to_string()])
+    );
+}
+
+#[tokio::test]
+async fn buckets_commits_by_hour_in_timezone() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            // A Monday in UTC, but still Sunday evening in New York.
+            ("aaaaaaaa", "2023-01-16T03:00:00Z", &[("main.rs", 10)]),
+            ("bbbbbbbb", "2023-01-16T15:30:00Z", &[("main.rs", 10)]),
+        ],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from([
+            "resume_stats",
+            "--timezone",
+            "America/New_York",
+        ])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+    let punchcard = stats["hello"].punchcard;
+
+    assert_eq!(punchcard[6][22], 1);
+    assert_eq!(punchcard[0][10], 1);
+    assert_eq!(punchcard.iter().flatten().sum::<u64>(), 2);
+    assert_eq!(stats["hello"].weekdays[0], 2);
+}
+
+#[tokio::test]
+async fn names_languages_with_linguist() {
+    let mut needed_stats = needed_stats(
+        r#"
+        languages = ["Rust"]
+
+        [experience.hello]
+        repositories = ["octocat/hello"]
+        "#,
+    );
+
+    needed_stats.linguist_extensions = Linguist::parse("Rust:\n  extensions: [\".rs\"]\n").unwrap();
+
+    let stats = compile_stats(
+        Sources::new(FakeSource),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats,
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(
+        stats["hello"].languages,
+        HashSet::from(["Rust".to_string()])
+    );
+    assert_eq!(stats["hello"].language_lines["Rust"], 4);
+}
+
+#[tokio::test]
+async fn only_counts_lines_of_line_count_languages() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[(
+            "aaaaaaaa",
+            "2023-01-15T12:00:00Z",
+            &[("main.rs", 10), ("README.md", 3), ("package.json", 5)],
+        )],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs", "md", "json"]
+            line_count_languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].lines, 10);
+    assert_eq!(stats["hello"].languages.len(), 3);
+}
+
+#[tokio::test]
+async fn attributes_forks_to_their_upstream() {
+    let stats = compile_stats(
+        Sources::new(FakeSource),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+
+            [experience.hello]
+            repositories = [{ path = "octocat/project", upstream = "org/project" }]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].owned_repositories, 0);
+    assert_eq!(stats["hello"].external_repositories, 1);
+}
+
+#[tokio::test]
+async fn leaves_out_submodule_pointers() {
+    let server = MockServer::start().await;
+    let mut commit = commit_detail(
+        &server,
+        "aaaaaaaa",
+        "2023-01-15T12:00:00Z",
+        &[("main.rs", 10), ("vendor/library", 1)],
+    );
+
+    commit["files"][1]["patch"] =
+        "@@ -1 +1 @@\n-Subproject commit aaaaaaaa\n+Subproject commit bbbbbbbb".into();
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[])],
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits/aaaaaaaa"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(commit))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].lines, 10);
+}
+
+#[tokio::test]
+async fn counts_binary_files_as_assets() {
+    let server = MockServer::start().await;
+    let mut commit = commit_detail(
+        &server,
+        "aaaaaaaa",
+        "2023-01-15T12:00:00Z",
+        &[("main.rs", 10), ("logo.png", 0)],
+    );
+
+    commit["files"][1]["patch"] = Value::Null;
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[])],
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/commits/aaaaaaaa"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(commit))
+        .with_priority(1)
+        .mount(&server)
+        .await;
+
+    let needed = r#"
+        author = "octocat"
+        languages = ["rs"]
+
+        [experience.hello]
+        repositories = ["octocat/hello"]
+    "#;
+
+    for (args, assets) in [
+        (&["resume_stats"][..], 0),
+        (&["resume_stats", "--count-assets"][..], 1),
+    ] {
+        let stats = compile_stats(
+            Sources::new(octocrab(&server)),
+            Arc::new(Args::parse_from(args)),
+            needed_stats(needed),
+        )
+        .await
+        .unwrap()
+        .experiences;
+
+        assert_eq!(stats["hello"].assets, assets, "{args:?}");
+        assert_eq!(stats["hello"].lines, 10, "{args:?}");
+    }
+}
+
+#[tokio::test]
+async fn only_counts_files_still_present() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[(
+            "aaaaaaaa",
+            "2023-01-15T12:00:00Z",
+            &[
+                ("src/main.rs", 10),
+                ("src/deleted.rs", 5),
+                ("deleted.py", 1),
+            ],
+        )],
+    )
+    .await;
+    Mock::given(method("GET"))
+        .and(path("/repos/octocat/hello/git/trees/HEAD"))
+        .and(query_param("recursive", "1"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "sha": "bbbbbbbb",
+            "tree": [
+                { "path": "src", "type": "tree" },
+                { "path": "src/main.rs", "type": "blob" },
+            ],
+            "truncated": false,
+        })))
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats", "--only-present-files"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs", "py"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].lines, 10);
+    assert_eq!(stats["hello"].languages, HashSet::from(["rs".to_string()]));
+}
+
+#[tokio::test]
+async fn overrides_the_author_of_a_repository() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)])],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from(["resume_stats"])),
+        needed_stats(
+            r#"
+            author = "monalisa"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = [{ path = "octocat/hello", author = "octocat" }]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].lines, 10);
+}
+
+#[tokio::test]
+async fn lists_lines_with_graphql() {
+    let server = MockServer::start().await;
+
+    mock_repository_name(&server, "octocat/hello", "octocat/hello").await;
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(body_string_contains("user(login"))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": { "user": { "id": "MDQ6VXNlcjE=" } },
+        })))
+        .mount(&server)
+        .await;
+    Mock::given(method("POST"))
+        .and(path("/graphql"))
+        .and(body_string_contains("history("))
+        .respond_with(ResponseTemplate::new(200).set_body_json(json!({
+            "data": {
+                "repository": {
+                    "defaultBranchRef": {
+                        "target": {
+                            "history": {
+                                "pageInfo": { "hasNextPage": false, "endCursor": null },
+                                "nodes": [
+                                    {
+                                        "oid": "aaaaaaaa",
+                                        "authoredDate": "2023-01-15T12:00:00Z",
+                                        "committedDate": "2023-01-15T12:00:00Z",
+                                        "additions": 10,
+                                        "deletions": 2,
+                                        "parents": { "totalCount": 0, "nodes": [] },
+                                        "author": {
+                                            "name": "Octocat",
+                                            "email": "octocat@example.com",
+                                            "user": { "login": "octocat" },
+                                        },
+                                    },
+                                    {
+                                        "oid": "bbbbbbbb",
+                                        "authoredDate": "2023-02-15T12:00:00Z",
+                                        "committedDate": "2023-02-15T12:00:00Z",
+                                        "additions": 5,
+                                        "deletions": 0,
+                                        "parents": { "totalCount": 1, "nodes": [{ "oid": "aaaaaaaa" }] },
+                                        "author": null,
+                                    },
+                                ],
+                            },
+                        },
+                    },
+                },
+            },
+        })))
+        .mount(&server)
+        .await;
+
+    let stats = compile_stats(
+        Sources::new(GitHubGraphql::new(octocrab(&server))),
+        Arc::new(Args::parse_from(["resume_stats", "--api", "graphql"])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 2);
+    assert_eq!(stats["hello"].lines, 15);
+    assert!(stats["hello"].languages.is_empty());
+}
+
+#[tokio::test]
+async fn leaves_out_commits_under_min_commit_lines() {
+    let server = MockServer::start().await;
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)]),
+            ("bbbbbbbb", "2023-02-15T12:00:00Z", &[("main.rs", 1)]),
+        ],
+    )
+    .await;
+
+    let stats = compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from([
+            "resume_stats",
+            "--min-commit-lines",
+            "2",
+        ])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap()
+    .experiences;
+
+    assert_eq!(stats["hello"].commits, 1);
+    assert_eq!(stats["hello"].lines, 10);
+}
+
+#[tokio::test]
+async fn dumps_counted_commits() {
+    let server = MockServer::start().await;
+    let dump =
+        std::env::temp_dir().join(format!("resume_stats_commits_{}.jsonl", std::process::id()));
+
+    mock_repository(
+        &server,
+        "octocat/hello",
+        &[
+            ("aaaaaaaa", "2023-01-15T12:00:00Z", &[("main.rs", 10)]),
+            ("bbbbbbbb", "2023-02-15T12:00:00Z", &[("README.md", 3)]),
+        ],
+    )
+    .await;
+
+    compile_stats(
+        Sources::new(octocrab(&server)),
+        Arc::new(Args::parse_from([
+            "resume_stats".as_ref(),
+            "--dump-commits".as_ref(),
+            dump.as_os_str(),
+        ])),
+        needed_stats(
+            r#"
+            author = "octocat"
+            languages = ["rs"]
+
+            [experience.hello]
+            repositories = ["octocat/hello"]
+            "#,
+        ),
+    )
+    .await
+    .unwrap();
+
+    let records = std::fs::read_to_string(&dump).unwrap();
+
+    std::fs::remove_file(&dump).unwrap();
+
+    let mut records: Vec<Value> = records
+        .lines()
+        .map(|record| serde_json::from_str(record).unwrap())
+        .collect();
+
+    records.sort_by_key(|record| record["sha"].as_str().unwrap().to_string());
+
+    assert_eq!(records.len(), 2);
+    assert_eq!(records[0]["sha"], "aaaaaaaa");
+    assert_eq!(records[0]["repository"], "octocat/hello");
+    assert_eq!(records[0]["experience"], "hello");
+    assert_eq!(records[0]["additions"], 10);
+    assert_eq!(records[0]["languages"], json!(["rs"]));
+    assert_eq!(records[1]["additions"], 3);
+    assert_eq!(records[1]["languages"], json!([]));
+}
+
+#[test]
+fn validates_progress_styles() {
+    let progress = ProgressConfig {
+        template: Some("{prefix} [{bar:abc}]".to_string()),
+        task_template: None,
+        chars: Some("#-".to_string()),
+    };
+    let Err(error) = progress_styles(&Args::parse_from(["resume_stats"]), &progress) else {
+        panic!("the config's template should be rejected");
+    };
+
+    assert!(error.to_string().contains("invalid progress template"));
+    // The command line takes precedence over the config.
+    assert!(
+        progress_styles(
+            &Args::parse_from(["resume_stats", "--progress-template", "{bar:25} {pos}"]),
+            &progress,
+        )
+        .is_ok()
+    );
+
+    for chars in ["#", "中> "] {
+        let Err(error) = progress_styles(
+            &Args::parse_from(["resume_stats", "--progress-chars", chars]),
+            &ProgressConfig::default(),
+        ) else {
+            panic!("progress chars {chars:?} should be rejected");
+        };
+
+        assert!(error.to_string().contains("progress chars"), "{error}");
+    }
+}
+
+#[test]
+fn titles_the_terminal_with_the_progress() {
+    assert_eq!(progress_title(0, Some(4)), "resume_stats: 0%");
+    assert_eq!(progress_title(1, Some(3)), "resume_stats: 33%");
+    assert_eq!(progress_title(4, Some(4)), "resume_stats: 100%");
+    // Without any experiences, there's nothing to divide by.
+    assert_eq!(progress_title(0, Some(0)), "resume_stats: 0%");
+    assert_eq!(progress_title(0, None), "resume_stats: 0%");
+}
@@ -0,0 +1,255 @@
+use clap::Parser;
+use resume_stats::{
+    args::{Args, Format},
+    config::{Provider, RepositoryPath, closest_match, read_needed_stats},
+};
+
+#[tokio::test]
+async fn orders_experiences_from_an_array() {
+    let config = std::env::temp_dir().join(format!(
+        "resume_stats_experience_array_{}.toml",
+        std::process::id()
+    ));
+
+    tokio::fs::write(
+        &config,
+        r#"
+            author = "octocat"
+
+            [[experience]]
+            name = "Zeta"
+            role = "Maintainer"
+            company = "Zeta Labs"
+            start = "2023-01"
+            target_commits = 500
+            repositories = ["zeta/app"]
+
+            [[experience]]
+            name = "Alpha"
+            repositories = ["alpha/app"]
+        "#,
+    )
+    .await
+    .unwrap();
+
+    let args = Args::parse_from([
+        "resume_stats".as_ref(),
+        "--config".as_ref(),
+        config.as_os_str(),
+    ]);
+    let needed_stats = read_needed_stats(&args).await;
+
+    tokio::fs::remove_file(&config).await.unwrap();
+
+    let needed_stats = needed_stats.unwrap();
+    let zeta = &needed_stats.experience["Zeta"];
+
+    assert_eq!(needed_stats.order, ["Zeta", "Alpha"]);
+    assert_eq!(zeta.details.role.as_deref(), Some("Maintainer"));
+    assert_eq!(zeta.details.company.as_deref(), Some("Zeta Labs"));
+    assert_eq!(zeta.details.start.as_deref(), Some("2023-01"));
+    assert_eq!(zeta.details.end, None);
+    assert_eq!(zeta.targets.commits, Some(500));
+    assert_eq!(zeta.targets.lines, None);
+    assert_eq!(zeta.repositories[0].path.to_string(), "zeta/app");
+}
+
+#[test]
+fn suggests_the_closest_name() {
+    let names = [
+        "frontend".to_string(),
+        "backend".to_string(),
+        "ops".to_string(),
+    ];
+
+    assert_eq!(closest_match("fronten", &names), Some(&names[0]));
+    assert_eq!(closest_match("bakend", &names), Some(&names[1]));
+    // Short names are only suggested for small typos.
+    assert_eq!(closest_match("dev", &names), None);
+    assert_eq!(closest_match("website", &names), None);
+}
+
+#[test]
+fn rejects_repository_paths_with_extra_slashes() {
+    let Err(error) = RepositoryPath::parse("org/team/repo", Provider::GitHub) else {
+        panic!("org/team/repo should be rejected");
+    };
+
+    assert!(error.to_string().contains("more than one slash"));
+    assert!(RepositoryPath::parse("org", Provider::GitHub).is_err());
+    assert!(RepositoryPath::parse("org/", Provider::GitHub).is_err());
+    assert!(RepositoryPath::parse("/repo", Provider::GitLab).is_err());
+    assert!(RepositoryPath::parse("org//repo", Provider::GitLab).is_err());
+}
+
+#[test]
+fn parses_gitlab_subgroups() {
+    let path = RepositoryPath::parse("org/team/repo", Provider::GitLab).unwrap();
+
+    assert_eq!(path.owner, "org/team");
+    assert_eq!(path.repository, "repo");
+    assert_eq!(path.to_string(), "org/team/repo");
+}
+
+#[test]
+fn applies_defaults_unless_given_on_the_command_line() {
+    let defaults: toml::Table = toml::from_str(
+        r#"
+            format = "json"
+            since = 2023-01-01
+            commit_concurrency = 4
+            json-pretty = true
+            only = ["acme", "solo"]
+        "#,
+    )
+    .unwrap();
+    let args = Args::parse_with_defaults(
+        ["resume_stats", "--format", "csv", "--only", "beta"],
+        &defaults,
+    )
+    .unwrap();
+
+    assert!(args.format == Format::Csv);
+    assert_eq!(args.since, "2023-01-01".parse().ok());
+    assert_eq!(args.commit_concurrency.get(), 4);
+    assert!(args.json_pretty);
+    assert_eq!(args.only, ["beta"]);
+}
+
+#[test]
+fn rejects_unknown_defaults() {
+    let defaults: toml::Table = toml::from_str("formt = \"json\"").unwrap();
+    let Err(error) = Args::parse_with_defaults(["resume_stats"], &defaults) else {
+        panic!("formt should be rejected");
+    };
+
+    assert!(error.to_string().contains("did you mean \"format\"?"));
+
+    let defaults: toml::Table = toml::from_str("config = \"Other.toml\"").unwrap();
+
+    assert!(Args::parse_with_defaults(["resume_stats"], &defaults).is_err());
+}
+
+#[tokio::test]
+async fn merges_accounts_of_experiences() {
+    let config = |name: &str| {
+        std::env::temp_dir().join(format!("resume_stats_{name}_{}.toml", std::process::id()))
+    };
+    let (base, overlay, conflicting) = (config("base"), config("overlay"), config("conflicting"));
+
+    for (path, toml) in [
+        (
+            &base,
+            r#"
+                author = "octocat"
+
+                [experience.acme]
+                repositories = ["acme/app"]
+            "#,
+        ),
+        (
+            &overlay,
+            "[experience.acme]\nrepositories = []\naccount = \"octocat-work\"\n",
+        ),
+        (
+            &conflicting,
+            "[experience.acme]\nrepositories = []\naccount = \"someone\"\n",
+        ),
+    ] {
+        tokio::fs::write(path, toml).await.unwrap();
+    }
+
+    let read = |configs: &[&std::path::PathBuf]| {
+        let mut args = vec!["resume_stats".into()];
+
+        for config in configs {
+            args.extend(["--config".into(), config.as_os_str().to_owned()]);
+        }
+
+        Args::parse_from::<_, std::ffi::OsString>(args)
+    };
+    let merged = read_needed_stats(&read(&[&base, &overlay])).await;
+    let conflicted = read_needed_stats(&read(&[&overlay, &conflicting])).await;
+
+    for path in [&base, &overlay, &conflicting] {
+        tokio::fs::remove_file(path).await.unwrap();
+    }
+
+    assert_eq!(
+        merged.unwrap().experience["acme"].account.as_deref(),
+        Some("octocat-work")
+    );
+    assert!(conflicted.is_err());
+}
+
+#[tokio::test]
+async fn merges_every_config_in_the_config_dir() {
+    let config_dir =
+        std::env::temp_dir().join(format!("resume_stats_config_dir_{}", std::process::id()));
+
+    tokio::fs::create_dir_all(&config_dir).await.unwrap();
+
+    for (name, toml) in [
+        (
+            "a.toml",
+            r#"
+                author = "octocat"
+                languages = ["rs"]
+                order = ["acme"]
+
+                [experience.acme]
+                repositories = ["acme/app"]
+            "#,
+        ),
+        (
+            "b.toml",
+            r#"
+                languages = ["py"]
+
+                [experience.acme]
+                repositories = ["acme/api"]
+
+                [experience.side]
+                repositories = ["octocat/side"]
+            "#,
+        ),
+        ("notes.txt", "author = \"someone\""),
+    ] {
+        tokio::fs::write(config_dir.join(name), toml).await.unwrap();
+    }
+
+    let args = Args::parse_from([
+        "resume_stats".as_ref(),
+        "--config-dir".as_ref(),
+        config_dir.as_os_str(),
+    ]);
+    let merged = read_needed_stats(&args).await;
+
+    tokio::fs::write(config_dir.join("c.toml"), "author = \"someone\"")
+        .await
+        .unwrap();
+
+    let conflicted = read_needed_stats(&args).await;
+
+    tokio::fs::remove_dir_all(&config_dir).await.unwrap();
+
+    let merged = merged.unwrap();
+    let repositories: Vec<String> = merged.experience["acme"]
+        .repositories
+        .iter()
+        .map(|repository| repository.path.to_string())
+        .collect();
+
+    assert_eq!(merged.author.as_deref(), Some("octocat"));
+    assert_eq!(
+        merged.languages,
+        ["py".to_string(), "rs".to_string()].into()
+    );
+    assert_eq!(repositories, ["acme/app", "acme/api"]);
+    assert!(merged.experience.contains_key("side"));
+    let Err(error) = conflicted else {
+        panic!("conflicting authors should be rejected");
+    };
+
+    assert!(format!("{error:#}").contains("conflicting authors"));
+}
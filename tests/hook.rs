@@ -0,0 +1,46 @@
+#![cfg(unix)]
+
+use std::path::PathBuf;
+
+use resume_stats::hook::run_on_complete;
+
+#[tokio::test]
+async fn passes_the_stats_and_outputs_to_the_command() {
+    let received =
+        std::env::temp_dir().join(format!("resume_stats_on_complete_{}", std::process::id()));
+    let outputs = [PathBuf::from("stats.json"), PathBuf::from("stats.md")];
+
+    run_on_complete(
+        &format!(
+            "{{ cat; echo; echo \"$#\" \"$1\" \"$2\"; }} > {}",
+            received.display()
+        ),
+        r#"{"commits":2}"#,
+        &outputs,
+    )
+    .await
+    .unwrap();
+
+    assert_eq!(
+        std::fs::read_to_string(&received).unwrap(),
+        "{\"commits\":2}\n2 stats.json stats.md\n"
+    );
+
+    std::fs::remove_file(received).unwrap();
+}
+
+#[tokio::test]
+async fn returns_the_failure_of_the_command_to_warn_about() {
+    run_on_complete("true", "{}", &[]).await.unwrap();
+
+    let error = run_on_complete("false", "{}", &[]).await.unwrap_err();
+
+    assert_eq!(
+        format!("{error:#}"),
+        "--on-complete command failed (exit status: 1)"
+    );
+    // Commands that don't read their stdin don't fail it either.
+    run_on_complete("exit 0", &"{}".repeat(100_000), &[])
+        .await
+        .unwrap();
+}
@@ -0,0 +1,78 @@
+use std::collections::{BTreeMap, HashMap, HashSet};
+
+use resume_stats::{
+    import::Import,
+    stats::{CompiledStats, Stats},
+};
+
+fn compiled_stats() -> CompiledStats {
+    CompiledStats {
+        experiences: HashMap::from([(
+            "acme".to_string(),
+            Stats {
+                languages: HashSet::from(["rs".to_string()]),
+                language_lines: BTreeMap::from([("rs".to_string(), 20)]),
+                commits: 2,
+                lines: 20,
+                ..Stats::default()
+            },
+        )]),
+        names: HashMap::new(),
+        details: HashMap::new(),
+        targets: HashMap::new(),
+        order: Vec::new(),
+        profiles: HashMap::new(),
+        author: "octocat".to_string(),
+        errors: Vec::new(),
+        partial: false,
+        interrupted: Vec::new(),
+    }
+}
+
+#[test]
+fn adds_imported_stats() {
+    let mut compiled_stats = compiled_stats();
+
+    Import::parse(
+        r#"{
+            "acme": { "languages": { "rs": 5, "pl": 100 }, "commits": 10 },
+            "legacy": { "languages": { "pl": 0 }, "commits": 300, "lines": 15000 }
+        }"#,
+    )
+    .unwrap()
+    .merge_into(&mut compiled_stats);
+
+    let acme = &compiled_stats.experiences["acme"];
+
+    assert_eq!(acme.commits, 12);
+    assert_eq!(acme.lines, 125);
+    assert_eq!(acme.language_lines["pl"], 100);
+    assert_eq!(acme.language_lines["rs"], 25);
+
+    let legacy = &compiled_stats.experiences["legacy"];
+
+    assert_eq!(legacy.commits, 300);
+    assert_eq!(legacy.lines, 15000);
+    assert_eq!(legacy.languages, HashSet::from(["pl".to_string()]));
+    assert!(legacy.language_lines.is_empty());
+}
+
+#[test]
+fn replaces_compiled_stats() {
+    let mut compiled_stats = compiled_stats();
+
+    Import::parse(r#"{ "acme": { "commits": 7, "lines": 70, "mode": "replace" } }"#)
+        .unwrap()
+        .merge_into(&mut compiled_stats);
+
+    let acme = &compiled_stats.experiences["acme"];
+
+    assert_eq!(acme.commits, 7);
+    assert_eq!(acme.lines, 70);
+    assert!(acme.languages.is_empty());
+}
+
+#[test]
+fn rejects_unknown_fields() {
+    assert!(Import::parse(r#"{ "acme": { "comits": 7 } }"#).is_err());
+}
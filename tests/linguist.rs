@@ -0,0 +1,75 @@
+use resume_stats::linguist::Linguist;
+
+const LANGUAGES_YML: &str = r#"
+C:
+  type: programming
+  extensions:
+  - ".c"
+  - ".h"
+C++:
+  type: programming
+  extensions:
+  - ".cpp"
+  - ".h"
+Objective-C:
+  type: programming
+  extensions:
+  - ".m"
+  - ".h"
+Rust:
+  type: programming
+  extensions:
+  - ".rs"
+  - ".rs.in"
+TypeScript:
+  type: programming
+  extensions:
+  - ".ts"
+  - ".cts"
+  - ".mts"
+Dockerfile:
+  type: programming
+  filenames:
+  - Dockerfile
+"#;
+
+#[test]
+fn names_languages_by_extension() {
+    let linguist = Linguist::parse(LANGUAGES_YML).unwrap();
+
+    assert_eq!(linguist.language("rs"), Some("Rust"));
+    assert_eq!(linguist.language("mts"), Some("TypeScript"));
+    assert_eq!(linguist.language("h"), Some("C"));
+    assert_eq!(linguist.language("in"), None);
+    assert_eq!(linguist.language("py"), None);
+}
+
+#[test]
+fn prefers_primary_extensions() {
+    let linguist = Linguist::parse(
+        r#"
+        Alpha:
+          extensions: [".shared", ".a"]
+        Beta:
+          extensions: [".b", ".shared"]
+        Gamma:
+          extensions: [".own", ".b"]
+        "#,
+    )
+    .unwrap();
+
+    assert_eq!(linguist.language("shared"), Some("Alpha"));
+    assert_eq!(linguist.language("b"), Some("Beta"));
+    assert_eq!(linguist.language("own"), Some("Gamma"));
+}
+
+#[test]
+fn knows_languages_with_extensions() {
+    let linguist = Linguist::parse(LANGUAGES_YML).unwrap();
+
+    assert!(linguist.knows("Rust"));
+    assert!(linguist.knows("Objective-C"));
+    assert!(!linguist.knows("rs"));
+    assert!(!linguist.knows("Dockerfile"));
+    assert!(linguist.names().any(|name| name == "rs"));
+}
@@ -0,0 +1,574 @@
+use std::{
+    collections::{BTreeMap, BTreeSet, HashMap, HashSet},
+    path::Path,
+};
+
+use clap::Parser;
+use resume_stats::{
+    args::{Args, Format},
+    config::NeededStats,
+    output::{humanize, render, summary},
+    report::config_hash,
+    source::ErrorKind,
+    stats::{CompiledStats, RepositoryContext, RepositoryError, Stats},
+};
+
+fn compiled_stats() -> CompiledStats {
+    CompiledStats {
+        experiences: HashMap::from([(
+            "acme".to_string(),
+            Stats {
+                earliest_commit: Some("2023-01-15T12:00:00Z".parse().unwrap()),
+                latest_commit: Some("2024-06-01T12:00:00Z".parse().unwrap()),
+                languages: HashSet::from(["rs".to_string(), "sh".to_string()]),
+                commits: 2,
+                lines: 20,
+                repositories: BTreeSet::from(["acme/website".to_string()]),
+                ..Stats::default()
+            },
+        )]),
+        names: HashMap::from([("acme".to_string(), "Acme, Inc.".to_string())]),
+        details: HashMap::new(),
+        targets: HashMap::new(),
+        order: Vec::new(),
+        profiles: HashMap::new(),
+        author: "octocat".to_string(),
+        errors: Vec::new(),
+        partial: false,
+        interrupted: Vec::new(),
+    }
+}
+
+#[test]
+fn infers_formats_from_extensions() {
+    assert!(Format::from_path(Path::new("stats.json")).unwrap() == Format::Json);
+    assert!(Format::from_path(Path::new("stats.MD")).unwrap() == Format::Markdown);
+    assert!(Format::from_path(Path::new("badge.svg")).unwrap() == Format::Badge);
+    assert!(Format::from_path(Path::new("stats.txt")).is_err());
+    assert!(Format::from_path(Path::new("stats")).is_err());
+}
+
+#[test]
+fn renders_markdown_tables() {
+    let markdown = render(
+        Format::Markdown,
+        &Args::parse_from(["resume_stats"]),
+        "",
+        &compiled_stats(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        markdown,
+        "| Experience | Since | Until | Commits | Repositories | Languages | Lines |\n\
+         | --- | --- | --- | --- | --- | --- | --- |\n\
+         | Acme, Inc. | 2023-01-15 | 2024-06-01 | 2 | 1 | rs, sh | 20 |\n"
+    );
+}
+
+#[test]
+fn quotes_csv_fields() {
+    let csv = render(
+        Format::Csv,
+        &Args::parse_from(["resume_stats", "--commits-only"]),
+        "",
+        &compiled_stats(),
+    )
+    .unwrap();
+
+    assert_eq!(
+        csv,
+        "Experience,Since,Until,Commits,Repositories\n\"Acme, Inc.\",2023-01-15,2024-06-01,2,1\n"
+    );
+}
+
+#[test]
+fn shows_created_and_deleted_branches_in_tables() {
+    let mut compiled_stats = compiled_stats();
+    let stats = compiled_stats.experiences.get_mut("acme").unwrap();
+
+    stats.branches_created = 3;
+    stats.branches_deleted = 1;
+
+    let csv = render(
+        Format::Csv,
+        &Args::parse_from(["resume_stats", "--commits-only", "--include-branches"]),
+        "",
+        &compiled_stats,
+    )
+    .unwrap();
+
+    assert_eq!(
+        csv,
+        "Experience,Since,Until,Commits,Repositories,Branches created,Branches deleted\n\
+         \"Acme, Inc.\",2023-01-15,2024-06-01,2,1,3,1\n"
+    );
+}
+
+#[test]
+fn groups_tables_by_language() {
+    let mut compiled_stats = compiled_stats();
+    let acme = compiled_stats.experiences.get_mut("acme").unwrap();
+
+    acme.language_commits = BTreeMap::from([("rs".to_string(), 2), ("sh".to_string(), 1)]);
+    acme.language_lines = BTreeMap::from([("rs".to_string(), 15), ("sh".to_string(), 5)]);
+    compiled_stats.experiences.insert(
+        "solo".to_string(),
+        Stats {
+            languages: HashSet::from(["rs".to_string()]),
+            language_commits: BTreeMap::from([("rs".to_string(), 3)]),
+            language_lines: BTreeMap::from([("rs".to_string(), 30)]),
+            ..Stats::default()
+        },
+    );
+
+    let markdown = render(
+        Format::Markdown,
+        &Args::parse_from(["resume_stats", "--group-by", "language"]),
+        "",
+        &compiled_stats,
+    )
+    .unwrap();
+
+    assert_eq!(
+        markdown,
+        "| Language | Experiences | Commits | Lines |\n\
+         | --- | --- | --- | --- |\n\
+         | rs | Acme, Inc., solo | 5 | 45 |\n\
+         | sh | Acme, Inc. | 1 | 5 |\n"
+    );
+}
+
+#[test]
+fn renders_one_line_summaries() {
+    let mut compiled_stats = compiled_stats();
+    let acme = compiled_stats.experiences.get_mut("acme").unwrap();
+
+    acme.commits = 1204;
+    acme.repositories = (0..18).map(|i| format!("acme/repository-{i}")).collect();
+    acme.language_lines = BTreeMap::from([("rs".to_string(), 5), ("sh".to_string(), 15)]);
+    // Repositories in several experiences are only counted once.
+    compiled_stats.experiences.insert(
+        "solo".to_string(),
+        Stats {
+            repositories: BTreeSet::from(["acme/repository-0".to_string()]),
+            ..Stats::default()
+        },
+    );
+
+    let oneline = |args: &[&str]| {
+        render(
+            Format::Oneline,
+            &Args::parse_from([&["resume_stats"], args].concat()),
+            "",
+            &compiled_stats,
+        )
+        .unwrap()
+    };
+
+    assert_eq!(oneline(&[]), "1,204 commits across 18 repos in sh, rs.\n");
+    assert_eq!(
+        oneline(&["--top-languages", "1"]),
+        "1,204 commits across 18 repos in sh.\n"
+    );
+    assert_eq!(
+        oneline(&["--top-languages", "0", "--humanize"]),
+        "1.2k commits across 18 repos.\n"
+    );
+}
+
+#[test]
+fn renders_social_cards() {
+    let mut compiled_stats = compiled_stats();
+    let acme = compiled_stats.experiences.get_mut("acme").unwrap();
+
+    acme.commits = 1204;
+    acme.language_lines = BTreeMap::from([("rs".to_string(), 5), ("sh".to_string(), 15)]);
+
+    let card = render(
+        Format::Card,
+        &Args::parse_from(["resume_stats", "--humanize"]),
+        "",
+        &compiled_stats,
+    )
+    .unwrap();
+
+    assert_eq!(
+        serde_json::from_str::<serde_json::Value>(&card).unwrap(),
+        serde_json::json!({
+            "title": "octocat",
+            "subtitle": "2023–2024",
+            "numbers": [
+                { "label": "Commits", "value": 1204, "text": "1.2k" },
+                { "label": "Lines", "value": 20, "text": "20" },
+                { "label": "Repos", "value": 1, "text": "1" },
+            ],
+            "languages": ["sh", "rs"],
+        })
+    );
+}
+
+#[test]
+fn aligns_the_summary_to_the_widest_label() {
+    let mut compiled_stats = compiled_stats();
+
+    compiled_stats.experiences.insert(
+        "beta".to_string(),
+        Stats {
+            commits: 12345,
+            commits_without_files: 3,
+            ..Stats::default()
+        },
+    );
+    compiled_stats.order = vec!["acme".to_string(), "beta".to_string()];
+
+    let summary = summary(&Args::parse_from(["resume_stats"]), &compiled_stats);
+    let value_column = |lines: &[&str], column: usize| {
+        for line in lines {
+            let (label, value) = line.split_at(column);
+
+            assert!(label.ends_with(": ") || label.ends_with("  "), "{line:?}");
+            assert!(!value.starts_with(' '), "{line:?}");
+        }
+    };
+    let (overview, experiences) = summary.split_once("\n\n").unwrap();
+    let fields: Vec<&str> = experiences
+        .lines()
+        .filter(|line| line.starts_with("    "))
+        .collect();
+
+    // "Current streak:" and "Longest streak:" are the widest overall labels,
+    // and "Languages:" the widest of any experience.
+    value_column(&overview.lines().collect::<Vec<_>>(), 16);
+    value_column(&fields, 15);
+    assert!(fields.contains(
+        &"    Missing:   3 commits had no file data, so their lines and languages aren't counted"
+    ));
+    assert!(fields.contains(&"    Commits:   12345"));
+}
+
+#[test]
+fn heads_the_summary_with_the_overall_activity() {
+    let mut compiled_stats = compiled_stats();
+
+    compiled_stats.experiences.insert(
+        "beta".to_string(),
+        Stats {
+            earliest_commit: Some("2021-03-02T12:00:00Z".parse().unwrap()),
+            latest_commit: Some("2022-05-01T12:00:00Z".parse().unwrap()),
+            commits: 1,
+            ..Stats::default()
+        },
+    );
+
+    let summary = summary(&Args::parse_from(["resume_stats"]), &compiled_stats);
+    let (overview, _) = summary.split_once("\n\n").unwrap();
+
+    // The earliest commit of one experience and the latest of another.
+    assert!(
+        overview.contains("Active since:   March 2, 2021"),
+        "{overview}"
+    );
+    assert!(
+        overview.contains("Last active:    June 1, 2024"),
+        "{overview}"
+    );
+}
+
+#[test]
+fn only_breaks_commits_down_by_weekday_when_asked() {
+    let mut compiled_stats = compiled_stats();
+
+    compiled_stats.experiences.get_mut("acme").unwrap().weekdays = [1, 0, 0, 0, 0, 0, 1];
+
+    let weekdays_line = "    Weekdays:  Mon 1, Tue 0, Wed 0, Thu 0, Fri 0, Sat 0, Sun 1";
+
+    assert!(!summary(&Args::parse_from(["resume_stats"]), &compiled_stats).contains("Weekdays:"));
+    assert!(
+        summary(
+            &Args::parse_from(["resume_stats", "--weekday-breakdown"]),
+            &compiled_stats
+        )
+        .lines()
+        .any(|line| line == weekdays_line)
+    );
+}
+
+#[test]
+fn reports_languages_no_experience_changed() {
+    let mut compiled_stats = compiled_stats();
+
+    compiled_stats
+        .experiences
+        .get_mut("acme")
+        .unwrap()
+        .language_lines = BTreeMap::from([("rs".to_string(), 20)]);
+    compiled_stats.experiences.insert(
+        "beta".to_string(),
+        Stats {
+            language_lines: BTreeMap::from([("py".to_string(), 5)]),
+            ..Stats::default()
+        },
+    );
+
+    let languages = BTreeSet::from(["py", "rs", "rss", "sh"].map(str::to_string));
+
+    // Languages only count as used if lines of them were counted.
+    assert_eq!(compiled_stats.unused_languages(&languages), ["rss", "sh"]);
+}
+
+#[test]
+fn caps_the_languages_named_in_the_summary() {
+    let mut compiled_stats = compiled_stats();
+    let acme = compiled_stats.experiences.get_mut("acme").unwrap();
+
+    acme.languages = HashSet::from(["c", "py", "rs", "sh"].map(str::to_string));
+    acme.language_lines = BTreeMap::from([("py".to_string(), 5), ("rs".to_string(), 20)]);
+
+    let languages_line = |args: &[&str]| {
+        summary(&Args::parse_from(args), &compiled_stats)
+            .lines()
+            .find(|line| line.starts_with("    Languages:"))
+            .unwrap()
+            .to_string()
+    };
+
+    // The languages with the most lines come first, then the rest by name.
+    assert_eq!(
+        languages_line(&["resume_stats"]),
+        "    Languages: rs, py, c, sh"
+    );
+    assert_eq!(
+        languages_line(&["resume_stats", "--max-languages", "2"]),
+        "    Languages: rs, py … and 2 more"
+    );
+    assert!(Args::try_parse_from(["resume_stats", "--max-languages", "0"]).is_err());
+
+    // Machine-readable formats keep every language.
+    let args = Args::parse_from(["resume_stats", "--max-languages", "2"]);
+    let json: serde_json::Value =
+        serde_json::from_str(&render(Format::Json, &args, "", &compiled_stats).unwrap()).unwrap();
+
+    assert_eq!(
+        json["experiences"]["acme"]["languages"]
+            .as_array()
+            .unwrap()
+            .len(),
+        4
+    );
+}
+
+#[test]
+fn summarizes_empty_stats() {
+    assert_eq!(
+        summary(
+            &Args::parse_from(["resume_stats"]),
+            &CompiledStats {
+                experiences: HashMap::new(),
+                ..compiled_stats()
+            },
+        ),
+        ""
+    );
+
+    let mut compiled_stats = compiled_stats();
+
+    compiled_stats
+        .experiences
+        .insert("acme".to_string(), Stats::default());
+
+    assert_eq!(
+        summary(&Args::parse_from(["resume_stats"]), &compiled_stats),
+        "Acme, Inc.:\n    Languages: \n    Commits:   0\n    Files:     0 distinct\n    Repos:     0 (0 owned, 0 external)\n    Lines:     0\n"
+    );
+}
+
+#[test]
+fn only_tells_owned_repositories_apart_for_one_author() {
+    let compiled_stats = CompiledStats {
+        author: "*".to_string(),
+        ..compiled_stats()
+    };
+
+    assert!(
+        summary(&Args::parse_from(["resume_stats"]), &compiled_stats)
+            .contains("    Repos:     1\n")
+    );
+}
+
+#[test]
+fn heads_reports_with_what_produced_them() {
+    let args = Args::parse_from([
+        "resume_stats",
+        "--since",
+        "2023-01-01",
+        "--until",
+        "2024-12-31",
+    ]);
+    let json: serde_json::Value =
+        serde_json::from_str(&render(Format::Json, &args, "abc123", &compiled_stats()).unwrap())
+            .unwrap();
+    let toml: toml::Table =
+        toml::from_str(&render(Format::Toml, &args, "abc123", &compiled_stats()).unwrap()).unwrap();
+    let metadata = &json["metadata"];
+
+    assert_eq!(metadata["version"], env!("CARGO_PKG_VERSION"));
+    assert_eq!(metadata["config_hash"], "abc123");
+    assert_eq!(metadata["author"], "octocat");
+    assert_eq!(metadata["since"], "2023-01-01");
+    assert_eq!(metadata["until"], "2024-12-31");
+    assert!(
+        metadata["generated_at"]
+            .as_str()
+            .unwrap()
+            .parse::<chrono::DateTime<chrono::Utc>>()
+            .is_ok()
+    );
+    assert_eq!(toml["metadata"]["config_hash"].as_str(), Some("abc123"));
+}
+
+#[test]
+fn hashes_configs_by_their_contents_and_options() {
+    let needed_stats = |toml: &str| -> NeededStats { toml::from_str(toml).unwrap() };
+    let args = Args::parse_from(["resume_stats"]);
+    let hash = config_hash(&args, &needed_stats("author = \"octocat\"")).unwrap();
+
+    assert_eq!(
+        hash,
+        config_hash(&args, &needed_stats("author = \"octocat\"")).unwrap()
+    );
+    assert_ne!(
+        hash,
+        config_hash(&args, &needed_stats("author = \"someone\"")).unwrap()
+    );
+
+    for options in [
+        &["--merge-lines", "skip"][..],
+        &["--line-metric", "changed"],
+        &["--skip-commits-over", "100"],
+    ] {
+        assert_ne!(
+            hash,
+            config_hash(
+                &Args::parse_from(["resume_stats"].iter().chain(options)),
+                &needed_stats("author = \"octocat\""),
+            )
+            .unwrap(),
+            "{options:?}"
+        );
+    }
+}
+
+#[test]
+fn ends_json_with_a_newline() {
+    let compiled_stats = compiled_stats();
+
+    for format in [Format::Json, Format::Card, Format::PunchcardJson] {
+        for args in [
+            Args::parse_from(["resume_stats"]),
+            Args::parse_from(["resume_stats", "--json-pretty"]),
+        ] {
+            let json = render(format, &args, "", &compiled_stats).unwrap();
+
+            assert!(json.ends_with("}\n"), "{json:?}");
+            assert_eq!(json.lines().count() > 1, args.json_pretty);
+        }
+    }
+}
+
+#[test]
+fn only_indents_json_with_json_pretty() {
+    let compiled_stats = compiled_stats();
+    let render_json =
+        |args: &[&str]| render(Format::Json, &Args::parse_from(args), "", &compiled_stats).unwrap();
+    let compact = render_json(&["resume_stats"]);
+    let pretty = render_json(&["resume_stats", "--json-pretty"]);
+    let without_time = |json: &str| {
+        let mut value: serde_json::Value = serde_json::from_str(json).unwrap();
+
+        value["metadata"]["generated_at"].take();
+        value
+    };
+
+    assert!(!compact.contains("\n  "));
+    assert!(pretty.contains("\n  \"metadata\": {\n    \"version\""));
+    assert_eq!(without_time(&compact), without_time(&pretty));
+}
+
+#[test]
+fn anonymizes_experiences_and_repositories() {
+    let mut compiled_stats = compiled_stats();
+
+    compiled_stats.experiences.insert(
+        "secret".to_string(),
+        Stats {
+            commits: 7,
+            repositories: BTreeSet::from(["acme/website".to_string(), "acme/secret".to_string()]),
+            files: BTreeSet::from(["acme/secret/payments.rs".to_string()]),
+            ..Stats::default()
+        },
+    );
+    compiled_stats.order = vec!["secret".to_string()];
+    compiled_stats.errors.push(RepositoryError {
+        experience: "secret".to_string(),
+        repository: "acme/internal".to_string(),
+        kind: ErrorKind::NotFound,
+        error: "not found".to_string(),
+    });
+    compiled_stats.interrupted.push(RepositoryContext {
+        experience: "secret".to_string(),
+        repository: "acme/wip".to_string(),
+    });
+
+    let anonymized = compiled_stats.anonymize();
+    let secret = &compiled_stats.experiences["Experience A"];
+
+    assert_eq!(compiled_stats.order, ["Experience A", "Experience B"]);
+    assert_eq!(compiled_stats.name("Experience B"), "Experience B");
+    assert_eq!(compiled_stats.experiences["Experience B"].commits, 2);
+    assert_eq!(secret.commits, 7);
+    assert_eq!(
+        secret.repositories,
+        BTreeSet::from(["Repo 2".to_string(), "Repo 3".to_string()])
+    );
+    assert!(secret.files.iter().all(|file| !file.contains("payments")));
+    assert_eq!(compiled_stats.repositories(), 2);
+    assert_eq!(compiled_stats.errors[0].repository, "Repo 1");
+    assert_eq!(compiled_stats.interrupted[0].experience, "Experience A");
+    assert_eq!(compiled_stats.interrupted[0].repository, "Repo 4");
+    assert_eq!(anonymized.experiences["Experience A"], "secret");
+    assert_eq!(anonymized.repositories["Repo 1"], "acme/internal");
+    assert_eq!(anonymized.repositories["Repo 3"], "acme/website");
+}
+
+#[test]
+fn orders_experiences_as_configured() {
+    let mut compiled_stats = compiled_stats();
+
+    for experience in ["beta", "zeta", "gamma"] {
+        compiled_stats
+            .experiences
+            .insert(experience.to_string(), Stats::default());
+    }
+
+    compiled_stats.order = vec!["zeta".to_string(), "acme".to_string()];
+
+    assert_eq!(
+        compiled_stats
+            .ordered()
+            .into_iter()
+            .map(|(experience, _)| experience)
+            .collect::<Vec<_>>(),
+        ["zeta", "acme", "beta", "gamma"]
+    );
+}
+
+#[test]
+fn humanizes_large_counts() {
+    assert_eq!(humanize(999), "999");
+    assert_eq!(humanize(1000), "1k");
+    assert_eq!(humanize(12_347), "12.3k");
+    assert_eq!(humanize(999_960), "1M");
+    assert_eq!(humanize(4_260_000), "4.3M");
+    assert_eq!(humanize(7_000_000_000_000), "7000B");
+}
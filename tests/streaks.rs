@@ -0,0 +1,57 @@
+use std::collections::BTreeSet;
+
+use chrono::NaiveDate;
+use resume_stats::stats::Streaks;
+
+fn days(days: &[&str]) -> BTreeSet<NaiveDate> {
+    days.iter().map(|day| day.parse().unwrap()).collect()
+}
+
+#[test]
+fn finds_longest_streak() {
+    let streaks = Streaks::new(
+        &days(&[
+            "2024-02-27",
+            "2024-02-28",
+            "2024-02-29",
+            "2024-03-01",
+            "2024-03-05",
+            "2024-03-06",
+        ]),
+        "2024-04-01".parse().unwrap(),
+    );
+
+    assert_eq!(
+        streaks,
+        Streaks {
+            longest: 4,
+            current: 0
+        }
+    );
+}
+
+#[test]
+fn keeps_current_streak_until_today() {
+    let days = days(&["2024-03-04", "2024-03-05", "2024-03-06"]);
+
+    assert_eq!(
+        Streaks::new(&days, "2024-03-06".parse().unwrap()).current,
+        3
+    );
+    assert_eq!(
+        Streaks::new(&days, "2024-03-07".parse().unwrap()).current,
+        3
+    );
+    assert_eq!(
+        Streaks::new(&days, "2024-03-08".parse().unwrap()).current,
+        0
+    );
+}
+
+#[test]
+fn has_no_streaks_without_commits() {
+    assert_eq!(
+        Streaks::new(&BTreeSet::new(), "2024-03-08".parse().unwrap()),
+        Streaks::default()
+    );
+}